@@ -0,0 +1,133 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use meme_generator_core::{
+    error::Error,
+    meme::{Image, OptionValue},
+};
+use meme_generator_utils::decoder::{SharedFrames, with_shared_frames};
+
+use crate::{cache::generate_with_cache, memes::get_meme, parallel::map_parallel};
+
+/// 一次批量制作的最大项数
+pub const MAX_BATCH_ITEMS: usize = 100;
+
+/// 批量制作中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// 表情名
+    pub key: String,
+    #[serde(default)]
+    pub texts: Vec<String>,
+    #[serde(default)]
+    pub options: HashMap<String, OptionValue>,
+    /// 使用的共享图片序号，不指定时按顺序使用表情所需数量的图片
+    #[serde(default)]
+    pub images: Option<Vec<usize>>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    /// 表情不存在
+    MemeNotFound(String),
+    /// 图片序号超出共享图片数量
+    ImageIndexOutOfRange(usize, usize),
+    /// 批量制作的项数超出限制
+    TooManyItems(usize, usize),
+    /// 制作表情失败
+    Meme(Error),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::MemeNotFound(key) => write!(f, "Meme not found: {key}"),
+            BatchError::ImageIndexOutOfRange(index, len) => {
+                write!(f, "Image index {index} out of range, got {len} images")
+            }
+            BatchError::TooManyItems(max, actual) => {
+                write!(f, "Too many batch items, maximum is {max}, got {actual}")
+            }
+            BatchError::Meme(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<Error> for BatchError {
+    fn from(err: Error) -> Self {
+        BatchError::Meme(err)
+    }
+}
+
+/// 批量制作表情的共享输入，每张图片只解码一次
+pub struct Batch {
+    images: Vec<Image>,
+    frames: SharedFrames,
+}
+
+impl Batch {
+    pub fn new(images: Vec<Image>) -> Self {
+        Self {
+            images,
+            frames: SharedFrames::new(),
+        }
+    }
+
+    /// 制作其中一项，可以在多个线程中同时调用
+    pub fn generate(&self, item: &BatchItem) -> Result<Vec<u8>, BatchError> {
+        let meme = get_meme(&item.key).ok_or_else(|| BatchError::MemeNotFound(item.key.clone()))?;
+
+        let images = match &item.images {
+            Some(indexes) => indexes
+                .iter()
+                .map(|index| {
+                    self.images
+                        .get(*index)
+                        .cloned()
+                        .ok_or(BatchError::ImageIndexOutOfRange(*index, self.images.len()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => {
+                let max_images = meme.info().params.max_images as usize;
+                self.images.iter().take(max_images).cloned().collect()
+            }
+        };
+
+        let (data, _) = with_shared_frames(&self.frames, || {
+            generate_with_cache(
+                meme.as_ref(),
+                images,
+                item.texts.clone(),
+                item.options.clone(),
+                item.seed,
+            )
+        })?;
+        Ok(data)
+    }
+}
+
+/// 使用相同的图片批量制作表情
+///
+/// - `images` 共享的图片列表
+/// - `items` 每一项的表情名、文字和选项
+/// - `concurrency` 同时制作的数量，为 0 时使用 CPU 核心数
+///
+/// 返回值与 `items` 一一对应，`items` 超过 [`MAX_BATCH_ITEMS`] 项时返回错误
+pub fn generate_batch(
+    images: Vec<Image>,
+    items: Vec<BatchItem>,
+    concurrency: usize,
+) -> Result<Vec<Result<Vec<u8>, BatchError>>, BatchError> {
+    if items.len() > MAX_BATCH_ITEMS {
+        return Err(BatchError::TooManyItems(MAX_BATCH_ITEMS, items.len()));
+    }
+    let batch = Batch::new(images);
+    Ok(map_parallel(&items, concurrency, |item| {
+        batch.generate(item)
+    }))
+}
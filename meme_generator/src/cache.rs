@@ -0,0 +1,469 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use meme_generator_core::{
+    config::MEME_HOME,
+    error::Error,
+    meme::{Image, Meme, OptionValue},
+};
+use meme_generator_utils::random::with_seed;
+
+use crate::{config::CONFIG, memes::is_external_meme};
+
+static CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| MEME_HOME.join("cache"));
+
+static MEMORY_CACHE: LazyLock<Mutex<MemoryCache>> =
+    LazyLock::new(|| Mutex::new(MemoryCache::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    /// 结果来自缓存
+    Hit,
+    /// 结果为新生成，并已写入缓存
+    Miss,
+    /// 未使用缓存
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Bypass => "bypass",
+        }
+    }
+}
+
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static CACHE_BYPASSES: AtomicU64 = AtomicU64::new(0);
+
+/// 缓存的累计命中情况
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub bypasses: u64,
+}
+
+/// 获取进程启动以来的缓存命中情况
+pub fn cache_stats() -> CacheStats {
+    CacheStats {
+        hits: CACHE_HITS.load(Ordering::Relaxed),
+        misses: CACHE_MISSES.load(Ordering::Relaxed),
+        bypasses: CACHE_BYPASSES.load(Ordering::Relaxed),
+    }
+}
+
+fn record_status(status: CacheStatus) -> CacheStatus {
+    let counter = match status {
+        CacheStatus::Hit => &CACHE_HITS,
+        CacheStatus::Miss => &CACHE_MISSES,
+        CacheStatus::Bypass => &CACHE_BYPASSES,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+    status
+}
+
+struct MemoryEntry {
+    data: Vec<u8>,
+    created_at: Instant,
+    last_used: u64,
+}
+
+struct MemoryCache {
+    entries: HashMap<String, MemoryEntry>,
+    size: usize,
+    tick: u64,
+}
+
+impl MemoryCache {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            size: 0,
+            tick: 0,
+        }
+    }
+
+    fn get(&mut self, key: &str, ttl: Duration) -> Option<Vec<u8>> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => entry.created_at.elapsed() > ttl,
+            None => return None,
+        };
+        if expired {
+            self.remove(key);
+            return None;
+        }
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.tick;
+        Some(entry.data.clone())
+    }
+
+    fn insert(&mut self, key: String, data: Vec<u8>, max_size: usize) {
+        if data.len() > max_size {
+            return;
+        }
+        self.remove(&key);
+        while self.size + data.len() > max_size {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => self.remove(&oldest),
+                None => break,
+            }
+        }
+        self.tick += 1;
+        self.size += data.len();
+        self.entries.insert(
+            key,
+            MemoryEntry {
+                data,
+                created_at: Instant::now(),
+                last_used: self.tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.size -= entry.data.len();
+        }
+    }
+}
+
+fn is_expired(modified: SystemTime, ttl: Duration) -> bool {
+    modified.elapsed().map(|age| age > ttl).unwrap_or(false)
+}
+
+fn disk_get(key: &str, ttl: Duration) -> Option<Vec<u8>> {
+    let path = CACHE_DIR.join(key);
+    let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+    if is_expired(modified, ttl) {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    fs::read(&path).ok()
+}
+
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// 写入缓存时使用的临时文件，每次写入各不相同，避免并发写入同一个键时互相覆盖
+fn temp_path(key: &str) -> PathBuf {
+    let n = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    CACHE_DIR.join(format!("{key}.{}-{n}.tmp", std::process::id()))
+}
+
+fn disk_insert(key: &str, data: &[u8], max_size: u64, ttl: Duration) {
+    if data.len() as u64 > max_size {
+        return;
+    }
+    if let Err(err) = fs::create_dir_all(&*CACHE_DIR) {
+        warn!("Failed to create cache directory: {err}");
+        return;
+    }
+    let temp_path = temp_path(key);
+    if let Err(err) =
+        fs::write(&temp_path, data).and_then(|_| fs::rename(&temp_path, CACHE_DIR.join(key)))
+    {
+        warn!("Failed to write cache file: {err}");
+        let _ = fs::remove_file(&temp_path);
+        return;
+    }
+    disk_prune(max_size, ttl);
+}
+
+/// 删除过期的缓存文件，并按修改时间从旧到新删除，直到总大小不超过限制
+///
+/// 临时文件只在过期时删除
+fn disk_prune(max_size: u64, ttl: Duration) {
+    let Ok(entries) = fs::read_dir(&*CACHE_DIR) else {
+        return;
+    };
+    let mut files = Vec::new();
+    let mut total_size = 0;
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let Ok(modified) = meta.modified() else {
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        if is_expired(modified, ttl) {
+            let _ = fs::remove_file(entry.path());
+            continue;
+        }
+        // 正在写入的临时文件
+        if entry.path().extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+        total_size += meta.len();
+        files.push((modified, meta.len(), entry.path()));
+    }
+    if total_size <= max_size {
+        return;
+    }
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, size, path) in files {
+        if total_size <= max_size {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total_size -= size;
+        }
+    }
+}
+
+fn cache_key(
+    key: &str,
+    images: &[Image],
+    texts: &[String],
+    options: &HashMap<String, OptionValue>,
+    seed: Option<u64>,
+) -> String {
+    fn update_str(hasher: &mut Sha256, value: &str) {
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    }
+
+    let mut hasher = Sha256::new();
+    update_str(&mut hasher, key);
+    hasher.update((images.len() as u64).to_le_bytes());
+    for image in images {
+        update_str(&mut hasher, &image.name);
+        hasher.update(Sha256::digest(&image.data));
+    }
+    hasher.update((texts.len() as u64).to_le_bytes());
+    for text in texts {
+        update_str(&mut hasher, text);
+    }
+    let options = options.iter().collect::<BTreeMap<_, _>>();
+    update_str(
+        &mut hasher,
+        &serde_json::to_string(&options).unwrap_or_default(),
+    );
+    match seed {
+        Some(seed) => {
+            hasher.update([1]);
+            hasher.update(seed.to_le_bytes());
+        }
+        None => hasher.update([0]),
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn lookup(key: &str) -> Option<Vec<u8>> {
+    let config = &CONFIG.cache;
+    let ttl = Duration::from_secs(config.ttl_secs);
+    if let Some(data) = MEMORY_CACHE.lock().unwrap().get(key, ttl) {
+        return Some(data);
+    }
+    if config.disk_enabled {
+        if let Some(data) = disk_get(key, ttl) {
+            MEMORY_CACHE.lock().unwrap().insert(
+                key.to_string(),
+                data.clone(),
+                config.memory_max_bytes,
+            );
+            return Some(data);
+        }
+    }
+    None
+}
+
+fn store(key: String, data: &[u8]) {
+    let config = &CONFIG.cache;
+    if config.disk_enabled {
+        let ttl = Duration::from_secs(config.ttl_secs);
+        disk_insert(&key, data, config.disk_max_bytes, ttl);
+    }
+    MEMORY_CACHE
+        .lock()
+        .unwrap()
+        .insert(key, data.to_vec(), config.memory_max_bytes);
+}
+
+/// 制作表情，并在启用缓存时复用相同请求的结果
+///
+/// - `seed` 随机数种子，不指定时使用了随机数的表情不会被缓存
+///
+/// 外部表情包中的表情无法确定是否使用了随机数，总是跳过缓存
+pub fn generate_with_cache(
+    meme: &dyn Meme,
+    images: Vec<Image>,
+    texts: Vec<String>,
+    options: HashMap<String, OptionValue>,
+    seed: Option<u64>,
+) -> Result<(Vec<u8>, CacheStatus), Error> {
+    let meme_key = meme.key();
+    if !CONFIG.cache.enabled || is_external_meme(&meme_key) {
+        let (result, _) = with_seed(seed, || meme.generate(images, texts, options));
+        return result.map(|data| (data, record_status(CacheStatus::Bypass)));
+    }
+
+    let key = cache_key(&meme_key, &images, &texts, &options, seed);
+    if let Some(data) = lookup(&key) {
+        return Ok((data, record_status(CacheStatus::Hit)));
+    }
+
+    let (result, used_random) = with_seed(seed, || meme.generate(images, texts, options));
+    let data = result?;
+    if used_random && seed.is_none() {
+        return Ok((data, record_status(CacheStatus::Bypass)));
+    }
+    store(key, &data);
+    Ok((data, record_status(CacheStatus::Miss)))
+}
+
+/// 清空内存与磁盘中的缓存
+pub fn clear_cache() {
+    let mut cache = MEMORY_CACHE.lock().unwrap();
+    cache.entries.clear();
+    cache.size = 0;
+    let _ = fs::remove_dir_all(&*CACHE_DIR);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(name: &str, data: &[u8]) -> Image {
+        Image {
+            name: name.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    fn options(options: &[(&str, OptionValue)]) -> HashMap<String, OptionValue> {
+        options
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn cache_key_is_stable() {
+        let images = [image("a", b"data")];
+        let texts = texts(&["hello"]);
+        let options = options(&[
+            ("circle", OptionValue::Boolean(true)),
+            ("number", OptionValue::Integer(3)),
+            ("name", OptionValue::String("x".to_string())),
+        ]);
+        let key = cache_key("petpet", &images, &texts, &options, Some(1));
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key("petpet", &images, &texts, &options, Some(1)));
+
+        // `HashMap` 的遍历顺序不影响结果
+        let mut reordered = HashMap::new();
+        for (name, value) in options.iter().collect::<BTreeMap<_, _>>().into_iter().rev() {
+            reordered.insert(name.clone(), value.clone());
+        }
+        assert_eq!(
+            key,
+            cache_key("petpet", &images, &texts, &reordered, Some(1))
+        );
+    }
+
+    #[test]
+    fn cache_key_covers_every_input() {
+        let images = [image("a", b"data")];
+        let texts = texts(&["hello"]);
+        let options = options(&[("circle", OptionValue::Boolean(true))]);
+        let base = cache_key("petpet", &images, &texts, &options, None);
+
+        let variants = [
+            cache_key("petpet2", &images, &texts, &options, None),
+            cache_key("petpet", &[image("b", b"data")], &texts, &options, None),
+            cache_key("petpet", &[image("a", b"other")], &texts, &options, None),
+            cache_key("petpet", &[], &texts, &options, None),
+            cache_key("petpet", &images, &self::texts(&["world"]), &options, None),
+            cache_key("petpet", &images, &[], &options, None),
+            cache_key(
+                "petpet",
+                &images,
+                &texts,
+                &self::options(&[("circle", OptionValue::Boolean(false))]),
+                None,
+            ),
+            cache_key("petpet", &images, &texts, &HashMap::new(), None),
+            cache_key("petpet", &images, &texts, &options, Some(0)),
+            cache_key("petpet", &images, &texts, &options, Some(1)),
+        ];
+        for variant in &variants {
+            assert_ne!(&base, variant);
+        }
+    }
+
+    #[test]
+    fn cache_key_separates_fields() {
+        let options = HashMap::new();
+        assert_ne!(
+            cache_key("a", &[], &texts(&["bc"]), &options, None),
+            cache_key("ab", &[], &texts(&["c"]), &options, None),
+        );
+        assert_ne!(
+            cache_key("a", &[], &texts(&["bc"]), &options, None),
+            cache_key("a", &[], &texts(&["b", "c"]), &options, None),
+        );
+        assert_ne!(
+            cache_key("a", &[], &texts(&["", ""]), &options, None),
+            cache_key("a", &[], &texts(&[""]), &options, None),
+        );
+        assert_ne!(
+            cache_key("a", &[image("", b"")], &[], &options, None),
+            cache_key("a", &[], &texts(&[""]), &options, None),
+        );
+    }
+
+    #[test]
+    fn temp_paths_are_unique() {
+        let first = temp_path("key");
+        let second = temp_path("key");
+        assert_ne!(first, second);
+        assert_eq!(first.extension().unwrap(), "tmp");
+    }
+
+    #[test]
+    fn memory_cache_evicts_least_recently_used() {
+        let ttl = Duration::from_secs(60);
+        let mut cache = MemoryCache::new();
+        cache.insert("a".to_string(), vec![0; 4], 10);
+        cache.insert("b".to_string(), vec![0; 4], 10);
+        assert!(cache.get("a", ttl).is_some());
+        cache.insert("c".to_string(), vec![0; 4], 10);
+        assert!(cache.get("a", ttl).is_some());
+        assert!(cache.get("b", ttl).is_none());
+        assert!(cache.get("c", ttl).is_some());
+        assert_eq!(cache.size, 8);
+
+        cache.insert("d".to_string(), vec![0; 11], 10);
+        assert!(cache.get("d", ttl).is_none());
+        assert_eq!(cache.size, 8);
+    }
+}
@@ -1,15 +1,16 @@
 use std::sync::LazyLock;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 use meme_generator_core::config::read_config_file;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub meme: MemeConfig,
     pub resource: ResourceConfig,
+    pub cache: CacheConfig,
 }
 
 impl Default for Config {
@@ -17,10 +18,11 @@ impl Default for Config {
         Config {
             meme: MemeConfig::default(),
             resource: ResourceConfig::default(),
+            cache: CacheConfig::default(),
         }
     }
 }
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct MemeConfig {
     pub load_builtin_memes: bool,
@@ -38,7 +40,7 @@ impl Default for MemeConfig {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ResourceConfig {
     pub resource_url: String,
@@ -54,6 +56,28 @@ impl Default for ResourceConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub memory_max_bytes: usize,
+    pub disk_enabled: bool,
+    pub disk_max_bytes: u64,
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            enabled: false,
+            memory_max_bytes: 64 * 1024 * 1024,
+            disk_enabled: false,
+            disk_max_bytes: 512 * 1024 * 1024,
+            ttl_secs: 10 * 60,
+        }
+    }
+}
+
 fn parse_config() -> Config {
     let config_content = read_config_file();
     if config_content.is_empty() {
@@ -0,0 +1,107 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use meme_generator_core::{config::MEME_HOME, error::Error};
+use meme_generator_utils::config::{CONFIG as UTILS_CONFIG, FONTS_DIR, IMAGES_DIR};
+pub use meme_generator_utils::text::{FontReport, FontSource, LocalFont, font_report};
+
+use crate::{
+    config::CONFIG,
+    memes::{get_meme, get_meme_keys},
+    parallel::map_parallel,
+    registry::LIBRARIES_DIR,
+};
+pub use crate::{memes::get_libraries, registry::LibraryStatus};
+
+/// 实际使用的路径
+#[derive(Debug, Clone, Serialize)]
+pub struct Paths {
+    pub meme_home: PathBuf,
+    pub config_file: PathBuf,
+    pub fonts_dir: PathBuf,
+    pub images_dir: PathBuf,
+    pub libraries_dir: PathBuf,
+}
+
+pub fn paths() -> Paths {
+    Paths {
+        meme_home: MEME_HOME.clone(),
+        config_file: MEME_HOME.join("config.toml"),
+        fonts_dir: FONTS_DIR.clone(),
+        images_dir: IMAGES_DIR.clone(),
+        libraries_dir: LIBRARIES_DIR.clone(),
+    }
+}
+
+/// 当前生效的配置（toml 格式），包括未在配置文件中设置的默认值
+///
+/// API 密钥会被隐藏
+pub fn effective_config() -> String {
+    let mut config = toml::Table::new();
+    for value in [
+        toml::Value::try_from(&*UTILS_CONFIG),
+        toml::Value::try_from(&*CONFIG),
+    ] {
+        if let Ok(toml::Value::Table(table)) = value {
+            config.extend(table);
+        }
+    }
+    if let Some(toml::Value::Table(api)) = config.get_mut("api") {
+        for (key, value) in api.iter_mut() {
+            if key.ends_with("apikey") {
+                *value = toml::Value::String("******".to_string());
+            }
+        }
+    }
+    toml::to_string_pretty(&config).unwrap_or_default()
+}
+
+/// 一个表情的预览制作结果
+#[derive(Debug)]
+pub struct SmokeResult {
+    pub key: String,
+    pub duration: Duration,
+    pub result: Result<(), Error>,
+}
+
+/// 所有表情的预览制作结果，以及制作完成后的字体加载情况
+#[derive(Debug)]
+pub struct SmokeReport {
+    /// 按表情名排序
+    pub results: Vec<SmokeResult>,
+    /// 包括所有表情请求过的字体族
+    pub fonts: FontReport,
+}
+
+/// 逐个制作所有表情的预览，检查哪些表情无法正常制作
+///
+/// - `concurrency` 同时制作的数量，为 0 时使用 CPU 核心数
+/// - `on_result` 每个表情制作完成后调用，可用于显示进度
+///
+/// 表情使用的字体族在制作时才会记录，因此字体在所有预览制作完成后检查
+pub fn smoke_render(concurrency: usize, on_result: impl Fn(&SmokeResult) + Sync) -> SmokeReport {
+    let mut keys = get_meme_keys();
+    keys.sort();
+    let results = map_parallel(&keys, concurrency, |key| {
+        let start = Instant::now();
+        let result = get_meme(key)
+            .unwrap()
+            .generate_preview(Default::default())
+            .map(|_| ());
+        let result = SmokeResult {
+            key: key.clone(),
+            duration: start.elapsed(),
+            result,
+        };
+        on_result(&result);
+        result
+    });
+    SmokeReport {
+        results,
+        fonts: font_report(),
+    }
+}
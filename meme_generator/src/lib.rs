@@ -4,12 +4,18 @@ mod registry;
 mod search;
 mod version;
 
+pub mod batch;
+pub mod cache;
+pub mod doctor;
+pub mod parallel;
+pub mod regression;
 pub mod resources;
 pub mod tools;
 pub use meme_generator_core::{
     config::{MEME_HOME, read_config_file},
     error, meme,
 };
+pub use meme_generator_utils::{bbcode, progress};
 pub use memes::{
     MemeSortBy, get_meme, get_meme_keys, get_meme_keys_sorted, get_memes, get_memes_sorted,
 };
@@ -1,13 +1,13 @@
-use std::{collections::HashMap, sync::LazyLock};
+use std::sync::LazyLock;
 
 use pinyin::{Pinyin, to_pinyin_vec};
 use serde::{Deserialize, Serialize};
 
 use meme_generator_core::meme::Meme;
 
-use crate::registry::load_memes;
+use crate::registry::{LibraryStatus, MemeRegistry, load_memes};
 
-static LOADED_MEMES: LazyLock<HashMap<String, Box<dyn Meme>>> = LazyLock::new(|| load_memes());
+static LOADED_MEMES: LazyLock<MemeRegistry> = LazyLock::new(|| load_memes());
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -20,7 +20,16 @@ pub enum MemeSortBy {
 }
 
 pub fn get_meme(key: &str) -> Option<&'static Box<dyn Meme>> {
-    LOADED_MEMES.get(key)
+    LOADED_MEMES.memes.get(key)
+}
+
+pub(crate) fn is_external_meme(key: &str) -> bool {
+    LOADED_MEMES.external_keys.contains(key)
+}
+
+/// 外部表情库的加载结果，未开启 `load_external_memes` 时为空
+pub fn get_libraries() -> Vec<LibraryStatus> {
+    LOADED_MEMES.libraries.clone()
 }
 
 fn sort_memes(memes: &mut Vec<&Box<dyn Meme>>, sort_by: &MemeSortBy, sort_reverse: bool) {
@@ -47,7 +56,7 @@ fn sort_memes(memes: &mut Vec<&Box<dyn Meme>>, sort_by: &MemeSortBy, sort_revers
 }
 
 pub fn get_memes_sorted(sort_by: MemeSortBy, sort_reverse: bool) -> Vec<&'static Box<dyn Meme>> {
-    let mut memes = LOADED_MEMES.values().into_iter().collect::<Vec<_>>();
+    let mut memes = LOADED_MEMES.memes.values().into_iter().collect::<Vec<_>>();
     sort_memes(&mut memes, &sort_by, sort_reverse);
     memes
 }
@@ -0,0 +1,46 @@
+use std::{sync::Mutex, thread};
+
+/// 在多个线程中逐项处理 `items`，返回值与 `items` 一一对应
+///
+/// `concurrency` 为 0 时使用 CPU 核心数
+pub fn map_parallel<T: Sync, R: Send>(
+    items: &[T],
+    concurrency: usize,
+    func: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let concurrency = match concurrency {
+        0 => thread::available_parallelism()
+            .map(|num| num.get())
+            .unwrap_or(1),
+        num => num,
+    }
+    .min(items.len().max(1));
+
+    let next = Mutex::new(0);
+    let results = Mutex::new(items.iter().map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| {
+                loop {
+                    let index = {
+                        let mut next = next.lock().unwrap();
+                        let index = *next;
+                        *next += 1;
+                        index
+                    };
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result = func(item);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect()
+}
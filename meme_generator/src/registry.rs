@@ -1,6 +1,14 @@
-use std::{collections::HashMap, fs::DirEntry, path::PathBuf, rc::Rc, sync::LazyLock};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::DirEntry,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::PathBuf,
+    rc::Rc,
+    sync::LazyLock,
+};
 
 use libloading::Library;
+use serde::Serialize;
 
 use meme_generator_core::{
     config::MEME_HOME,
@@ -12,16 +20,33 @@ use tracing::{info, warn};
 
 use crate::config::CONFIG;
 
-const LIBRARIES_DIR: LazyLock<PathBuf> = LazyLock::new(|| MEME_HOME.join("libraries"));
+pub static LIBRARIES_DIR: LazyLock<PathBuf> = LazyLock::new(|| MEME_HOME.join("libraries"));
+
+/// 外部表情库的加载结果
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryStatus {
+    pub file: PathBuf,
+    pub loaded: bool,
+    /// 注册的表情数量
+    pub memes: usize,
+    /// 加载失败的原因
+    pub error: Option<String>,
+    /// 编译库时使用的 rustc 版本与当前不一致等警告
+    pub warnings: Vec<String>,
+}
 
-struct MemeRegistry {
-    memes: HashMap<String, Box<dyn Meme>>,
+pub(crate) struct MemeRegistry {
+    pub(crate) memes: HashMap<String, Box<dyn Meme>>,
+    pub(crate) external_keys: HashSet<String>,
+    pub(crate) libraries: Vec<LibraryStatus>,
 }
 
 impl MemeRegistry {
     fn new() -> Self {
         Self {
             memes: HashMap::default(),
+            external_keys: HashSet::default(),
+            libraries: Vec::new(),
         }
     }
 }
@@ -31,7 +56,40 @@ impl meme_generator_core::registry::MemeRegistry for MemeRegistry {
         if CONFIG.meme.meme_disabled_list.contains(&key.to_string()) {
             return;
         }
-        self.memes.insert(key.to_string(), meme);
+        self.memes
+            .insert(key.to_string(), Box::new(GuardedMeme { meme }));
+    }
+}
+
+/// 捕获表情制作中的 panic，转换为 `Error::InternalError`
+struct GuardedMeme {
+    meme: Box<dyn Meme>,
+}
+
+impl Meme for GuardedMeme {
+    fn key(&self) -> String {
+        self.meme.key()
+    }
+
+    fn info(&self) -> MemeInfo {
+        self.meme.info()
+    }
+
+    fn generate(
+        &self,
+        images: Vec<Image>,
+        texts: Vec<String>,
+        options: HashMap<String, OptionValue>,
+    ) -> Result<Vec<u8>, Error> {
+        catch_unwind(AssertUnwindSafe(|| {
+            self.meme.generate(images, texts, options)
+        }))
+        .unwrap_or_else(|payload| Err(Error::from_panic(&self.meme.key(), payload)))
+    }
+
+    fn generate_preview(&self, options: HashMap<String, OptionValue>) -> Result<Vec<u8>, Error> {
+        catch_unwind(AssertUnwindSafe(|| self.meme.generate_preview(options)))
+            .unwrap_or_else(|payload| Err(Error::from_panic(&self.meme.key(), payload)))
     }
 }
 
@@ -95,17 +153,33 @@ impl meme_generator_core::registry::MemeRegistry for ExternalMemeRegistry {
     }
 }
 
+/// 检查表情库编译时使用的 meme_generator_core 版本，版本不一致时，
+/// 库与当前程序之间传递的类型（如 `Error`）的内存布局可能不同
+fn check_core_version(core_version: &str) -> Result<(), String> {
+    if core_version != CORE_VERSION {
+        return Err(format!(
+            "meme_generator_core version mismatch: library {core_version}, current {CORE_VERSION}"
+        ));
+    }
+    Ok(())
+}
+
 unsafe fn load_library(
     library_path: &DirEntry,
-) -> Result<Option<HashMap<String, ExternalMeme>>, libloading::Error> {
-    let library = Rc::new(unsafe { Library::new(library_path.path()) }?);
+    warnings: &mut Vec<String>,
+) -> Result<HashMap<String, ExternalMeme>, String> {
+    let library =
+        Rc::new(unsafe { Library::new(library_path.path()) }.map_err(|err| err.to_string())?);
 
     let declaration = unsafe {
         library
-            .get::<*mut MemePackDeclaration>(b"MEME_PACK_DECLARATION")?
+            .get::<*mut MemePackDeclaration>(b"MEME_PACK_DECLARATION")
+            .map_err(|err| err.to_string())?
             .read()
     };
 
+    check_core_version(declaration.core_version)?;
+
     if declaration.rustc_version != RUSTC_VERSION {
         warn!(
             "Library {:?} is compiled with rustc {}, but meme_generator_core is compiled with {}, it may not work correctly.",
@@ -113,20 +187,16 @@ unsafe fn load_library(
             declaration.rustc_version,
             RUSTC_VERSION,
         );
-    }
-    if declaration.core_version != CORE_VERSION {
-        warn!(
-            "Library {:?} is compiled with meme_generator_core {}, but current version is {}, it may not work correctly.",
-            library_path.file_name(),
-            declaration.core_version,
-            CORE_VERSION,
-        );
+        warnings.push(format!(
+            "rustc version mismatch: library {}, current {}",
+            declaration.rustc_version, RUSTC_VERSION
+        ));
     }
 
     let mut registry = ExternalMemeRegistry::new(library);
     unsafe { (declaration.register)(&mut registry) };
 
-    Ok(Some(registry.memes))
+    Ok(registry.memes)
 }
 
 fn load_external_memes(registry: &mut MemeRegistry) -> Result<(), std::io::Error> {
@@ -143,27 +213,39 @@ fn load_external_memes(registry: &mut MemeRegistry) -> Result<(), std::io::Error
         if !["dll", "so", "dylib"].contains(&ext) {
             continue;
         }
-        match unsafe { load_library(&entry) } {
-            Ok(Some(memes)) => {
+        let mut status = LibraryStatus {
+            file: path,
+            loaded: false,
+            memes: 0,
+            error: None,
+            warnings: Vec::new(),
+        };
+        match unsafe { load_library(&entry, &mut status.warnings) } {
+            Ok(memes) => {
                 info!(
                     "Loaded library {:?} with {} memes",
                     entry.file_name(),
                     memes.len()
                 );
+                status.loaded = true;
+                status.memes = memes.len();
                 for (key, meme) in memes {
-                    registry.memes.insert(key, Box::new(meme));
+                    registry.external_keys.insert(key.clone());
+                    let meme = Box::new(meme);
+                    registry.memes.insert(key, Box::new(GuardedMeme { meme }));
                 }
             }
-            Ok(None) => {}
             Err(err) => {
                 warn!("Failed to load library {:?}: {}", entry.file_name(), err);
+                status.error = Some(err.to_string());
             }
         }
+        registry.libraries.push(status);
     }
     Ok(())
 }
 
-pub(crate) fn load_memes() -> HashMap<String, Box<dyn Meme>> {
+pub(crate) fn load_memes() -> MemeRegistry {
     let mut registry = MemeRegistry::new();
 
     if CONFIG.meme.load_builtin_memes {
@@ -176,5 +258,19 @@ pub(crate) fn load_memes() -> HashMap<String, Box<dyn Meme>> {
         }
     }
 
-    registry.memes
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_of_other_core_versions_are_refused() {
+        assert_eq!(check_core_version(CORE_VERSION), Ok(()));
+        // built before `ImageLimitExceeded` and `InternalError` were added
+        let error = check_core_version("0.0.5").unwrap_err();
+        assert!(error.contains("library 0.0.5"));
+        assert!(error.contains(CORE_VERSION));
+    }
 }
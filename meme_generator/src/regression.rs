@@ -0,0 +1,478 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use skia_safe::{AlphaType, Codec, ColorType, Data, EncodedImageFormat, ImageInfo, images};
+
+use meme_generator_core::error::Error;
+use meme_generator_utils::{decoder::CodecExt, encoder::encode_png, random::with_seed};
+
+use crate::{
+    memes::{get_meme, get_meme_keys, is_external_meme},
+    parallel::map_parallel,
+    tools::image_operations::{RgbaImage, image_to_rgba},
+};
+
+/// 参考图片可能的扩展名
+const EXTENSIONS: [&str; 4] = ["png", "gif", "jpg", "webp"];
+
+/// YIQ 颜色空间中两个颜色的最大差异，见 [`color_delta`]
+const MAX_DELTA: f32 = 35215.0;
+
+/// 表情预览的回归测试配置
+#[derive(Debug, Clone)]
+pub struct RegressionConfig {
+    /// 参考图片所在目录，文件名为 `{表情名}.{扩展名}`
+    pub golden_dir: PathBuf,
+    /// 与参考图片不一致时，实际结果和差异图片的保存目录
+    pub diff_dir: PathBuf,
+    /// 要测试的表情，为空时测试所有表情
+    pub keys: Vec<String>,
+    /// 只测试外部表情库中的表情
+    pub external_only: bool,
+    /// 制作预览时使用的随机数种子
+    pub seed: u64,
+    /// 单个像素的颜色差异阈值，范围为 0~1，超过时视为不同的像素
+    pub threshold: f32,
+    /// 允许的不同像素比例，范围为 0~1
+    pub max_diff_ratio: f32,
+    /// 用实际结果替换缺失或不一致的参考图片
+    pub bless: bool,
+    /// 同时制作的数量，为 0 时使用 CPU 核心数
+    pub concurrency: usize,
+}
+
+impl Default for RegressionConfig {
+    fn default() -> Self {
+        Self {
+            golden_dir: PathBuf::from("golden"),
+            diff_dir: PathBuf::from("golden_diff"),
+            keys: Vec::new(),
+            external_only: false,
+            seed: 42,
+            threshold: 0.1,
+            max_diff_ratio: 0.001,
+            bless: false,
+            concurrency: 0,
+        }
+    }
+}
+
+/// 图片的尺寸和帧数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape {
+    pub width: u32,
+    pub height: u32,
+    pub frames: usize,
+}
+
+#[derive(Debug)]
+pub enum RenderStatus {
+    /// 与参考图片一致
+    Passed { diff_ratio: f32 },
+    /// 不同像素的比例超过了允许的范围
+    Mismatch { diff_ratio: f32 },
+    /// 尺寸或帧数与参考图片不同
+    ShapeMismatch { expected: Shape, actual: Shape },
+    /// 没有参考图片
+    Missing,
+    /// 已用实际结果替换参考图片
+    Blessed,
+    /// 制作预览失败
+    RenderFailed(Error),
+    /// 读写文件失败、参考图片无法解码等
+    Error(String),
+}
+
+impl RenderStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, RenderStatus::Passed { .. } | RenderStatus::Blessed)
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderTestResult {
+    pub key: String,
+    pub status: RenderStatus,
+    pub duration: Duration,
+}
+
+struct Decoded {
+    format: EncodedImageFormat,
+    frames: Vec<RgbaImage>,
+}
+
+impl Decoded {
+    fn shape(&self) -> Shape {
+        Shape {
+            width: self.frames[0].width,
+            height: self.frames[0].height,
+            frames: self.frames.len(),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.format {
+            EncodedImageFormat::GIF => "gif",
+            EncodedImageFormat::JPEG => "jpg",
+            EncodedImageFormat::WEBP => "webp",
+            _ => "png",
+        }
+    }
+}
+
+fn decode(data: &[u8]) -> Result<Decoded, Error> {
+    // 不经过 `decoder` 的限制，避免参考图片被缩小或抽帧
+    let mut codec = Codec::from_data(Data::new_copy(data))
+        .ok_or(Error::ImageDecodeError("Skia decode error".to_string()))?;
+    let format = codec.encoded_format();
+    let frames = (0..codec.get_frame_count().max(1))
+        .map(|index| image_to_rgba(&codec.get_frame(index)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Decoded { format, frames })
+}
+
+/// 两个像素在 YIQ 颜色空间中的差异，透明像素先与白色混合
+///
+/// 参考 pixelmatch 的感知颜色差异算法
+fn color_delta(a: &[u8], b: &[u8]) -> f32 {
+    let yiq = |pixel: &[u8]| {
+        let alpha = pixel[3] as f32 / 255.0;
+        let blend = |value: u8| 255.0 + (value as f32 - 255.0) * alpha;
+        let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+        (
+            r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+            r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+            r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+        )
+    };
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    0.5053 * (y1 - y2).powi(2) + 0.299 * (i1 - i2).powi(2) + 0.1957 * (q1 - q2).powi(2)
+}
+
+fn is_different(a: &[u8], b: &[u8], threshold: f32) -> bool {
+    color_delta(a, b) > MAX_DELTA * threshold * threshold
+}
+
+fn count_diff_pixels(expected: &RgbaImage, actual: &RgbaImage, threshold: f32) -> usize {
+    expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+        .filter(|(a, b)| is_different(a, b, threshold))
+        .count()
+}
+
+/// 差异图片：不同的像素标为红色，其余像素为淡化的参考图片
+fn diff_image(expected: &RgbaImage, actual: &RgbaImage, threshold: f32) -> RgbaImage {
+    let mut pixels = Vec::with_capacity(expected.pixels.len());
+    for (a, b) in expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        if is_different(a, b, threshold) {
+            pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let alpha = a[3] as f32 / 255.0;
+            let gray = a[0] as f32 * 0.299 + a[1] as f32 * 0.587 + a[2] as f32 * 0.114;
+            let value = (255.0 - (255.0 - gray) * alpha * 0.1) as u8;
+            pixels.extend_from_slice(&[value, value, value, 255]);
+        }
+    }
+    RgbaImage {
+        width: expected.width,
+        height: expected.height,
+        pixels,
+    }
+}
+
+fn encode_rgba(image: &RgbaImage) -> Result<Vec<u8>, Error> {
+    let image_info = ImageInfo::new(
+        (image.width as i32, image.height as i32),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let data = Data::new_copy(&image.pixels);
+    let image = images::raster_from_data(&image_info, data, image.width as usize * 4)
+        .ok_or(Error::ImageEncodeError("Skia raster error".to_string()))?;
+    encode_png(image)
+}
+
+/// 逐帧比较，返回比较结果和不同像素最多的一帧的差异图片
+fn compare(
+    expected: &Decoded,
+    actual: &Decoded,
+    config: &RegressionConfig,
+) -> (RenderStatus, Option<RgbaImage>) {
+    if expected.shape() != actual.shape() {
+        let status = RenderStatus::ShapeMismatch {
+            expected: expected.shape(),
+            actual: actual.shape(),
+        };
+        return (status, None);
+    }
+    let counts = expected
+        .frames
+        .iter()
+        .zip(&actual.frames)
+        .map(|(expected, actual)| count_diff_pixels(expected, actual, config.threshold))
+        .collect::<Vec<_>>();
+    let shape = actual.shape();
+    let total = shape.width as usize * shape.height as usize * shape.frames;
+    let diff_ratio = counts.iter().sum::<usize>() as f32 / total.max(1) as f32;
+    if diff_ratio <= config.max_diff_ratio {
+        return (RenderStatus::Passed { diff_ratio }, None);
+    }
+    let worst = (0..counts.len())
+        .max_by_key(|index| counts[*index])
+        .unwrap();
+    let diff = diff_image(
+        &expected.frames[worst],
+        &actual.frames[worst],
+        config.threshold,
+    );
+    (RenderStatus::Mismatch { diff_ratio }, Some(diff))
+}
+
+fn golden_path(dir: &Path, key: &str) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(format!("{key}.{extension}")))
+        .find(|path| path.exists())
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+    }
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// 替换参考图片，格式变化时删除原有的参考图片
+fn bless(key: &str, data: &[u8], extension: &str, config: &RegressionConfig) -> Result<(), String> {
+    if let Some(path) = golden_path(&config.golden_dir, key) {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {e}", path.display()))?;
+    }
+    write_file(&config.golden_dir.join(format!("{key}.{extension}")), data)
+}
+
+/// 删除上次测试留下的实际结果和差异图片
+fn remove_diff_files(key: &str, config: &RegressionConfig) {
+    let mut paths = EXTENSIONS
+        .iter()
+        .map(|extension| config.diff_dir.join(format!("{key}.actual.{extension}")))
+        .collect::<Vec<_>>();
+    paths.push(config.diff_dir.join(format!("{key}.diff.png")));
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn check_result(key: &str, data: &[u8], config: &RegressionConfig) -> Result<RenderStatus, String> {
+    let actual = decode(data).map_err(|e| format!("Failed to decode result: {e}"))?;
+    let (status, diff) = match golden_path(&config.golden_dir, key) {
+        Some(path) => {
+            let golden =
+                fs::read(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+            let expected =
+                decode(&golden).map_err(|e| format!("Failed to decode {}: {e}", path.display()))?;
+            compare(&expected, &actual, config)
+        }
+        None => (RenderStatus::Missing, None),
+    };
+
+    remove_diff_files(key, config);
+    if status.is_ok() {
+        return Ok(status);
+    }
+    if config.bless {
+        bless(key, data, actual.extension(), config)?;
+        return Ok(RenderStatus::Blessed);
+    }
+    let actual_path = config
+        .diff_dir
+        .join(format!("{key}.actual.{}", actual.extension()));
+    write_file(&actual_path, data)?;
+    if let Some(diff) = diff {
+        let diff = encode_rgba(&diff).map_err(|e| format!("Failed to encode diff image: {e}"))?;
+        write_file(&config.diff_dir.join(format!("{key}.diff.png")), &diff)?;
+    }
+    Ok(status)
+}
+
+fn test_meme(key: &str, config: &RegressionConfig) -> RenderStatus {
+    let Some(meme) = get_meme(key) else {
+        return RenderStatus::Error(format!("Meme not found: {key}"));
+    };
+    let (result, _) = with_seed(Some(config.seed), || meme.generate_preview(HashMap::new()));
+    match result {
+        Ok(data) => check_result(key, &data, config).unwrap_or_else(RenderStatus::Error),
+        Err(err) => RenderStatus::RenderFailed(err),
+    }
+}
+
+/// 使用固定的随机数种子制作表情预览，并与参考图片比较
+///
+/// - 不一致时在 `diff_dir` 中保存实际结果 `{表情名}.actual.{扩展名}`
+///   和差异图片 `{表情名}.diff.png`
+/// - 开启 `bless` 时用实际结果替换缺失或不一致的参考图片
+/// - `on_result` 每个表情测试完成后调用，可用于显示进度
+///
+/// 返回值按表情名排序
+pub fn run_render_tests(
+    config: &RegressionConfig,
+    on_result: impl Fn(&RenderTestResult) + Sync,
+) -> Vec<RenderTestResult> {
+    let mut keys = if config.keys.is_empty() {
+        get_meme_keys()
+    } else {
+        config.keys.clone()
+    };
+    if config.external_only {
+        keys.retain(|key| is_external_meme(key));
+    }
+    keys.sort();
+    map_parallel(&keys, config.concurrency, |key| {
+        let start = Instant::now();
+        let status = test_meme(key, config);
+        let result = RenderTestResult {
+            key: key.clone(),
+            status,
+            duration: start.elapsed(),
+        };
+        on_result(&result);
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+        RgbaImage {
+            width,
+            height,
+            pixels: pixel.repeat((width * height) as usize),
+        }
+    }
+
+    fn png(image: &RgbaImage) -> Vec<u8> {
+        encode_rgba(image).unwrap()
+    }
+
+    fn test_config(name: &str) -> RegressionConfig {
+        let dir = env::temp_dir().join(format!("meme_regression_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        RegressionConfig {
+            golden_dir: dir.join("golden"),
+            diff_dir: dir.join("diff"),
+            concurrency: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn color_delta_of_pixels() {
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        let transparent = [0, 0, 0, 0];
+        assert_eq!(color_delta(&white, &white), 0.0);
+        assert_eq!(color_delta(&transparent, &white), 0.0);
+        let delta = color_delta(&black, &white);
+        assert!(delta > 0.0 && delta <= MAX_DELTA);
+        assert!(is_different(&black, &white, 0.1));
+        assert!(!is_different(&[250, 250, 250, 255], &white, 0.1));
+    }
+
+    #[test]
+    fn compare_frames() {
+        let config = RegressionConfig::default();
+        let decoded = |image: RgbaImage| Decoded {
+            format: EncodedImageFormat::PNG,
+            frames: vec![image],
+        };
+        let expected = decoded(solid(10, 10, [255, 255, 255, 255]));
+
+        let (status, diff) = compare(&expected, &decoded(solid(10, 10, [255; 4])), &config);
+        assert!(matches!(status, RenderStatus::Passed { diff_ratio } if diff_ratio == 0.0));
+        assert!(diff.is_none());
+
+        let mut actual = solid(10, 10, [255; 4]);
+        actual.pixels[..4].copy_from_slice(&[0, 0, 0, 255]);
+        let (status, diff) = compare(&expected, &decoded(actual), &config);
+        assert!(matches!(status, RenderStatus::Mismatch { diff_ratio } if diff_ratio == 0.01));
+        let diff = diff.unwrap();
+        assert_eq!(&diff.pixels[..4], &[255, 0, 0, 255]);
+        assert_eq!(&diff.pixels[4..8], &[255, 255, 255, 255]);
+
+        let (status, diff) = compare(&expected, &decoded(solid(10, 5, [255; 4])), &config);
+        assert!(matches!(
+            status,
+            RenderStatus::ShapeMismatch { expected, actual }
+                if expected.height == 10 && actual.height == 5
+        ));
+        assert!(diff.is_none());
+    }
+
+    #[test]
+    fn bless_missing_and_changed_golden() {
+        let mut config = test_config("bless");
+        let white = png(&solid(4, 4, [255; 4]));
+        let black = png(&solid(4, 4, [0, 0, 0, 255]));
+
+        let status = check_result("meme", &white, &config).unwrap();
+        assert!(matches!(status, RenderStatus::Missing));
+        assert!(config.diff_dir.join("meme.actual.png").exists());
+
+        config.bless = true;
+        let status = check_result("meme", &white, &config).unwrap();
+        assert!(matches!(status, RenderStatus::Blessed));
+        assert!(config.golden_dir.join("meme.png").exists());
+        assert!(!config.diff_dir.join("meme.actual.png").exists());
+
+        config.bless = false;
+        let status = check_result("meme", &white, &config).unwrap();
+        assert!(matches!(status, RenderStatus::Passed { .. }));
+
+        let status = check_result("meme", &black, &config).unwrap();
+        assert!(matches!(status, RenderStatus::Mismatch { .. }));
+        assert!(config.diff_dir.join("meme.actual.png").exists());
+        assert!(config.diff_dir.join("meme.diff.png").exists());
+
+        config.bless = true;
+        let status = check_result("meme", &black, &config).unwrap();
+        assert!(matches!(status, RenderStatus::Blessed));
+        assert_eq!(fs::read(config.golden_dir.join("meme.png")).unwrap(), black);
+        assert!(!config.diff_dir.join("meme.diff.png").exists());
+
+        let _ = fs::remove_dir_all(config.golden_dir.parent().unwrap());
+    }
+
+    #[test]
+    fn run_render_tests_on_keys() {
+        let mut config = test_config("run");
+        config.keys = vec!["missing_b".to_string(), "missing_a".to_string()];
+        let results = run_render_tests(&config, |_| {});
+        let keys = results
+            .iter()
+            .map(|result| result.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["missing_a", "missing_b"]);
+        assert!(
+            results
+                .iter()
+                .all(|result| matches!(result.status, RenderStatus::Error(_)))
+        );
+        assert!(!config.golden_dir.exists());
+    }
+}
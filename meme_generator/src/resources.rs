@@ -1,8 +1,8 @@
-use std::{fs, path::Path, sync::Arc};
+use std::{fs, io, path::Path, sync::Arc};
 
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::{
     fs::File,
@@ -13,24 +13,51 @@ use tokio::{
 };
 use tracing::{info, warn};
 
-use meme_generator_utils::config::{FONTS_DIR, IMAGES_DIR};
+use meme_generator_utils::{
+    config::{CONFIG as UTILS_CONFIG, FONTS_DIR, IMAGES_DIR},
+    text::init_fonts,
+};
 
 use crate::config::CONFIG;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct FileWithHash {
     file: String,
     hash: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct Resources {
     fonts: Vec<FileWithHash>,
     images: Vec<FileWithHash>,
 }
 
+/// 本地资源的状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceStatus {
+    /// 加载的本地字体数量
+    pub fonts: usize,
+    /// 字体是否可用，不使用本地字体时总是可用
+    pub fonts_ready: bool,
+    /// 图片资源目录是否存在且不为空
+    pub images_ready: bool,
+}
+
+/// 检查本地资源，首次调用时会初始化字体
+pub fn resource_status() -> ResourceStatus {
+    let fonts = init_fonts();
+    let images_ready = fs::read_dir(&*IMAGES_DIR)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    ResourceStatus {
+        fonts,
+        fonts_ready: fonts > 0 || !UTILS_CONFIG.font.use_local_fonts,
+        images_ready,
+    }
+}
+
 fn resource_url(base_url: &str, name: &str) -> String {
     format!("{base_url}v{VERSION}/resources/{name}")
 }
@@ -39,8 +66,11 @@ pub async fn check_resources(base_url: Option<String>) {
     let base_url = base_url.unwrap_or(CONFIG.resource.resource_url.clone());
     let client = Client::new();
     let resources = match fetch_resource_list(&client, &base_url).await {
-        Some(resources) => resources,
-        None => return,
+        Ok(resources) => resources,
+        Err(err) => {
+            warn!("{err}");
+            return;
+        }
     };
 
     if CONFIG.resource.download_fonts {
@@ -59,22 +89,161 @@ pub fn check_resources_in_background(base_url: Option<String>) {
     });
 }
 
-async fn fetch_resource_list(client: &Client, base_url: &str) -> Option<Resources> {
+async fn fetch_resource_list(client: &Client, base_url: &str) -> Result<Resources, String> {
     let url = resource_url(base_url, "resources.json");
-    let resp = match client.get(&url).send().await {
-        Ok(resp) => resp,
-        Err(e) => {
-            warn!("Failed to download {url}: {e}");
-            return None;
-        }
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    resp.json::<Resources>()
+        .await
+        .map_err(|e| format!("Failed to parse resources.json: {e}"))
+}
+
+/// 资源文件的问题
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceProblem {
+    /// 文件不存在
+    Missing,
+    /// 文件的哈希值与 `resources.json` 中的不一致
+    Corrupt,
+}
+
+/// 本地资源与 `resources.json` 的比对结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceVerification {
+    /// 检查的文件数量
+    pub checked: usize,
+    /// 有问题的文件，路径相对于资源目录，如 `images/xxx.png`
+    pub problems: Vec<(String, ResourceProblem)>,
+}
+
+async fn verify_resource_list(resources: &Resources) -> ResourceVerification {
+    let mut groups = vec![("images", &*IMAGES_DIR, &resources.images)];
+    if CONFIG.resource.download_fonts {
+        groups.push(("fonts", &*FONTS_DIR, &resources.fonts));
+    }
+    let mut verification = ResourceVerification {
+        checked: 0,
+        problems: Vec::new(),
     };
-    match resp.json::<Resources>().await {
-        Ok(resources) => Some(resources),
-        Err(e) => {
-            warn!("Failed to parse resources.json: {e}");
-            None
+    for (resource_type, resources_dir, files) in groups {
+        for res in files {
+            let file_path = resources_dir.join(&res.file);
+            let name = format!("{resource_type}/{}", res.file);
+            verification.checked += 1;
+            if !file_path.exists() {
+                verification.problems.push((name, ResourceProblem::Missing));
+            } else if !is_file_hash_equal(&file_path, &res.hash).await {
+                verification.problems.push((name, ResourceProblem::Corrupt));
+            }
         }
     }
+    verification
+}
+
+/// 下载 `resources.json`，逐个检查本地字体和图片文件的哈希值
+///
+/// 不下载字体时（`download_fonts = false`）不检查字体
+pub async fn verify_resources(base_url: Option<String>) -> Result<ResourceVerification, String> {
+    let base_url = base_url.unwrap_or(CONFIG.resource.resource_url.clone());
+    let client = Client::new();
+    let resources = fetch_resource_list(&client, &base_url).await?;
+    Ok(verify_resource_list(&resources).await)
+}
+
+pub fn verify_resources_sync(base_url: Option<String>) -> Result<ResourceVerification, String> {
+    Runtime::new().unwrap().block_on(verify_resources(base_url))
+}
+
+/// 与 [`verify_resources_sync`] 相同，但使用本地的 `resources.json`，无需联网
+pub fn verify_resources_with_file(path: &Path) -> Result<ResourceVerification, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let resources = serde_json::from_str::<Resources>(&content)
+        .map_err(|e| format!("Failed to parse resources.json: {e}"))?;
+    Ok(Runtime::new()
+        .unwrap()
+        .block_on(verify_resource_list(&resources)))
+}
+
+fn file_hash(path: &Path) -> Result<String, String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 递归列出目录中的文件，路径相对于 `base_dir`，以 `/` 分隔
+fn list_files(base_dir: &Path, dir: &Path, recursive: bool) -> Result<Vec<FileWithHash>, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read {}: {e}", dir.display()))?
+            .path();
+        if path.is_dir() {
+            if recursive {
+                files.extend(list_files(base_dir, &path, recursive)?);
+            }
+            continue;
+        }
+        let file = path
+            .strip_prefix(base_dir)
+            .unwrap()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push(FileWithHash {
+            file,
+            hash: file_hash(&path)?,
+        });
+    }
+    files.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(files)
+}
+
+/// 把 JSON 中的非 ASCII 字符转义为 `\uXXXX`，与 Python 的 `json.dump` 的输出一致
+///
+/// 非 ASCII 字符只会出现在字符串中，因此可以直接逐字符替换
+fn escape_non_ascii(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    escaped
+}
+
+/// 根据资源目录中的 `fonts` 和 `images` 重新生成 `resources.json`
+///
+/// 返回记录的文件数量
+///
+/// CI 中为避免编译 skia 使用 `scripts/update_resources.py`，修改输出格式时需同步修改该脚本
+pub fn update_resource_list(resources_dir: &Path) -> Result<usize, String> {
+    let fonts_dir = resources_dir.join("fonts");
+    let images_dir = resources_dir.join("images");
+    let resources = Resources {
+        fonts: list_files(&fonts_dir, &fonts_dir, false)?,
+        images: list_files(&images_dir, &images_dir, true)?,
+    };
+    let content = serde_json::to_string_pretty(&resources)
+        .map(|content| escape_non_ascii(&content))
+        .map_err(|e| format!("Failed to serialize resources.json: {e}"))?;
+    let path = resources_dir.join("resources.json");
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(resources.fonts.len() + resources.images.len())
 }
 
 async fn download_resources(
@@ -210,3 +379,42 @@ async fn download_file(client: &Client, url: &str, file_path: &Path) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_like_python_json() {
+        assert_eq!(
+            escape_non_ascii(r#"{"file": "a.png"}"#),
+            r#"{"file": "a.png"}"#
+        );
+        assert_eq!(
+            escape_non_ascii(r#"{"file": "表情/é.png"}"#),
+            r#"{"file": "\u8868\u60c5/\u00e9.png"}"#
+        );
+        assert_eq!(escape_non_ascii("\"😀\""), r#""\ud83d\ude00""#);
+    }
+
+    #[test]
+    fn list_files_sorted_with_slashes() {
+        let dir = std::env::temp_dir().join(format!("meme_resources_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("b/c.png"), b"c").unwrap();
+        fs::write(dir.join("a.png"), b"a").unwrap();
+
+        let files = list_files(&dir, &dir, true).unwrap();
+        let names = files
+            .iter()
+            .map(|file| file.file.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a.png", "b/c.png"]);
+        assert_eq!(files[0].hash, format!("{:x}", Sha256::digest(b"a")));
+
+        let files = list_files(&dir, &dir, false).unwrap();
+        assert_eq!(files.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
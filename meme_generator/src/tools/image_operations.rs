@@ -1,17 +1,24 @@
 use meme_generator_core::{error::Error, meme};
 use meme_generator_utils::{
     builder::InputImage,
-    decoder::CodecExt,
+    decoder::{CodecExt, FrameCache, check_input_bytes},
     encoder::{GifEncoder, encode_png, make_png_or_gif},
     image::{Fit, ImageExt},
     tools::new_surface,
 };
 use serde::{Deserialize, Serialize};
-use skia_safe::{Codec, Data, IRect, Image};
+use skia_safe::{
+    AlphaType, Codec, Color, ColorType, Data, EncodedImageFormat, IRect, Image,
+    ImageInfo as SkImageInfo, image::CachingHint,
+};
 
-fn decode_image(data: Vec<u8>) -> Result<Codec<'static>, Error> {
+/// 解码输入图片，与制作表情时一样受 `decoder` 中的限制约束
+pub(crate) fn decode_image(data: Vec<u8>) -> Result<FrameCache<'static>, Error> {
+    check_input_bytes(data.len())?;
     let data = Data::new_copy(&data);
-    Codec::from_data(data).ok_or(Error::ImageDecodeError("Skia decode error".to_string()))
+    let codec =
+        Codec::from_data(data).ok_or(Error::ImageDecodeError("Skia decode error".to_string()))?;
+    FrameCache::new(codec)
 }
 
 fn input_image(data: Vec<u8>) -> Result<InputImage<'static>, Error> {
@@ -35,7 +42,7 @@ pub fn inspect(image: Vec<u8>) -> Result<ImageInfo, Error> {
     let mut codec = decode_image(image)?;
     let is_multi_frame = codec.is_multi_frame();
     let frame_count = if is_multi_frame {
-        Some(codec.get_frame_count() as i32)
+        Some(codec.source_frame_count() as i32)
     } else {
         None
     };
@@ -44,9 +51,10 @@ pub fn inspect(image: Vec<u8>) -> Result<ImageInfo, Error> {
     } else {
         None
     };
+    let dimensions = codec.source_dimensions();
     Ok(ImageInfo {
-        width: codec.dimensions().width,
-        height: codec.dimensions().height,
+        width: dimensions.width,
+        height: dimensions.height,
         is_multi_frame,
         frame_count,
         average_duration,
@@ -252,3 +260,115 @@ pub fn gif_change_duration(image: Vec<u8>, duration: f32) -> Result<Vec<u8>, Err
     }
     Ok(encoder.finish()?)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Gif,
+}
+
+impl ImageFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Gif => "gif",
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "image/png" => Some(ImageFormat::Png),
+            "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+            "image/webp" => Some(ImageFormat::Webp),
+            "image/gif" => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+/// 将图片转换为指定格式
+///
+/// 动图转换为静图格式时只保留第一帧；格式相同时直接返回原图
+pub fn convert_format(image: Vec<u8>, format: ImageFormat) -> Result<Vec<u8>, Error> {
+    let mut codec = decode_image(image.clone())?;
+    let source_format = match codec.encoded_format() {
+        EncodedImageFormat::PNG => Some(ImageFormat::Png),
+        EncodedImageFormat::JPEG => Some(ImageFormat::Jpeg),
+        EncodedImageFormat::WEBP => Some(ImageFormat::Webp),
+        EncodedImageFormat::GIF => Some(ImageFormat::Gif),
+        _ => None,
+    };
+    if source_format == Some(format) {
+        return Ok(image);
+    }
+
+    let frame = codec.first_frame()?;
+    let encode = |image: Image, format: EncodedImageFormat, quality: u32| {
+        image
+            .encode(None, format, quality)
+            .map(|data| data.as_bytes().to_vec())
+            .ok_or(Error::ImageEncodeError("Skia encode error".to_string()))
+    };
+    match format {
+        ImageFormat::Png => encode_png(frame),
+        ImageFormat::Jpeg => encode(
+            frame.with_background(Color::WHITE),
+            EncodedImageFormat::JPEG,
+            90,
+        ),
+        ImageFormat::Webp => encode(frame, EncodedImageFormat::WEBP, 90),
+        ImageFormat::Gif => {
+            let mut encoder = GifEncoder::new();
+            encoder.add_frame(frame, 0.1)?;
+            encoder.finish()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RgbaImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pixels: Vec<u8>,
+}
+
+pub(crate) fn image_to_rgba(image: &Image) -> Result<RgbaImage, Error> {
+    let image_info = SkImageInfo::new(
+        image.dimensions(),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let row_bytes = image_info.min_row_bytes();
+    let mut pixels = vec![0u8; image_info.compute_min_byte_size()];
+    if !image.read_pixels(
+        &image_info,
+        &mut pixels,
+        row_bytes,
+        (0, 0),
+        CachingHint::Allow,
+    ) {
+        return Err(Error::ImageDecodeError(
+            "Skia read pixels error".to_string(),
+        ));
+    }
+    Ok(RgbaImage {
+        width: image.width() as u32,
+        height: image.height() as u32,
+        pixels,
+    })
+}
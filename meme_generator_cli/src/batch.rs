@@ -0,0 +1,350 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use clap::ArgMatches;
+use serde::Deserialize;
+use unicode_width::UnicodeWidthStr;
+
+use meme_generator::{error::Error, get_meme, meme::OptionValue, parallel::map_parallel};
+
+use crate::{
+    cli::error_message,
+    input::{STDIN, read_data_file, read_image},
+};
+
+/// 批量制作的清单
+///
+/// 相对路径（图片和输出文件）均相对于清单文件所在的目录
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+    /// 未指定 `output` 的任务保存到该目录，默认为清单所在目录
+    #[serde(default)]
+    output_dir: Option<PathBuf>,
+    jobs: Vec<Job>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Job {
+    key: String,
+    /// 图片路径或链接
+    #[serde(default)]
+    images: Vec<String>,
+    /// 图片名，第 n 个名字对应第 n 张图片
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    texts: Vec<String>,
+    #[serde(default)]
+    options: HashMap<String, OptionValue>,
+    /// 输出文件路径，未指定时为 `{序号}_{表情名}.{扩展名}`
+    #[serde(default)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+enum JobError {
+    MemeNotFound(String),
+    ImageLoad(String, String),
+    Meme(Error),
+    Save(PathBuf, std::io::Error),
+}
+
+impl JobError {
+    fn kind(&self) -> &'static str {
+        match self {
+            JobError::MemeNotFound(_) => "表情不存在",
+            JobError::ImageLoad(..) => "图片读取失败",
+            JobError::Meme(_) => "制作失败",
+            JobError::Save(..) => "保存失败",
+        }
+    }
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::MemeNotFound(key) => write!(f, "表情 `{key}` 不存在"),
+            JobError::ImageLoad(source, err) => write!(f, "{source}：{err}"),
+            JobError::Meme(err) => write!(f, "{}", error_message(err)),
+            JobError::Save(path, err) => write!(f, "{path:?}：{err}"),
+        }
+    }
+}
+
+struct JobResult {
+    output: Result<PathBuf, JobError>,
+    duration: Duration,
+}
+
+fn generate_job(
+    index: usize,
+    job: &Job,
+    base_dir: &Path,
+    output_dir: &Path,
+) -> Result<PathBuf, JobError> {
+    let meme = get_meme(&job.key).ok_or_else(|| JobError::MemeNotFound(job.key.clone()))?;
+    let mut images = job
+        .images
+        .iter()
+        .map(|source| {
+            read_image(source, base_dir).map_err(|err| JobError::ImageLoad(source.to_string(), err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (image, name) in images.iter_mut().zip(&job.names) {
+        image.name = name.clone();
+    }
+    let data = meme
+        .generate(images, job.texts.clone(), job.options.clone())
+        .map_err(JobError::Meme)?;
+
+    let path = match &job.output {
+        Some(output) => base_dir.join(output),
+        None => {
+            let extension = infer::get(&data)
+                .map(|kind| kind.extension())
+                .unwrap_or("png");
+            output_dir.join(format!("{}_{}.{extension}", index + 1, job.key))
+        }
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| JobError::Save(path.clone(), err))?;
+    }
+    fs::write(&path, data).map_err(|err| JobError::Save(path.clone(), err))?;
+    Ok(path)
+}
+
+/// 在制作前检查清单
+///
+/// 任务是并行制作的，无法共用标准输入；输出路径相同的任务会互相覆盖。
+/// 未指定输出路径的任务的扩展名在制作后才能确定，因此只比较文件名
+fn check_manifest(jobs: &[Job], base_dir: &Path, output_dir: &Path) -> Result<(), String> {
+    let mut defaults = HashMap::new();
+    for (index, job) in jobs.iter().enumerate() {
+        if job.images.iter().any(|source| source == STDIN) {
+            return Err(format!(
+                "第 {} 项：批量制作时图片不能从标准输入读取",
+                index + 1
+            ));
+        }
+        if job.output.is_none() {
+            defaults.insert(output_dir.join(format!("{}_{}", index + 1, job.key)), index);
+        }
+    }
+
+    let mut outputs = HashMap::new();
+    for (index, job) in jobs.iter().enumerate() {
+        let Some(output) = &job.output else {
+            continue;
+        };
+        let path = base_dir.join(output);
+        let other = outputs
+            .get(&path)
+            .or_else(|| defaults.get(&path.with_extension("")));
+        if let Some(other) = other {
+            return Err(format!(
+                "第 {} 项与第 {} 项的输出路径相同：{path:?}",
+                other + 1,
+                index + 1
+            ));
+        }
+        outputs.insert(path, index);
+    }
+    Ok(())
+}
+
+fn run_job(index: usize, job: &Job, base_dir: &Path, output_dir: &Path) -> JobResult {
+    let start = Instant::now();
+    let output = generate_job(index, job, base_dir, output_dir);
+    JobResult {
+        output,
+        duration: start.elapsed(),
+    }
+}
+
+fn run_jobs(
+    jobs: &[Job],
+    base_dir: &Path,
+    output_dir: &Path,
+    concurrency: usize,
+) -> Vec<JobResult> {
+    let items = jobs.iter().enumerate().collect::<Vec<_>>();
+    map_parallel(&items, concurrency, |&(index, job)| {
+        let result = run_job(index, job, base_dir, output_dir);
+        match &result.output {
+            Ok(path) => eprintln!("[{}/{}] {} 完成：{path:?}", index + 1, jobs.len(), job.key),
+            Err(err) => eprintln!("[{}/{}] {} 失败：{err}", index + 1, jobs.len(), job.key),
+        }
+        result
+    })
+}
+
+/// 终端显示宽度，中日韩等全角字符按两列计算
+pub(crate) fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+pub(crate) fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths = headers
+        .iter()
+        .map(|header| display_width(header))
+        .collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| {
+                let padding = width - display_width(cell);
+                format!("{cell}{}", " ".repeat(padding))
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(headers.to_vec()));
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("  ")
+    );
+    for row in rows {
+        println!(
+            "{}",
+            format_row(row.iter().map(|cell| cell.as_str()).collect())
+        );
+    }
+}
+
+/// 有任务失败时退出码为 1，清单无法读取或有误时为 2
+pub(crate) fn handle_batch(sub_matches: &ArgMatches) -> ExitCode {
+    let manifest_path = sub_matches.get_one::<PathBuf>("MANIFEST").unwrap();
+    let concurrency = sub_matches.get_one::<usize>("jobs").copied().unwrap_or(0);
+    let manifest = match read_data_file::<Manifest>(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            eprintln!("清单{err}");
+            return ExitCode::from(2);
+        }
+    };
+    let base_dir = manifest_path
+        .parent()
+        .map(|parent| parent.to_path_buf())
+        .unwrap_or_default();
+    let output_dir = match &manifest.output_dir {
+        Some(output_dir) => base_dir.join(output_dir),
+        None => base_dir.clone(),
+    };
+    if let Err(err) = check_manifest(&manifest.jobs, &base_dir, &output_dir) {
+        eprintln!("清单有误：{err}");
+        return ExitCode::from(2);
+    }
+
+    let start = Instant::now();
+    let results = run_jobs(&manifest.jobs, &base_dir, &output_dir, concurrency);
+    let elapsed = start.elapsed();
+
+    let rows = manifest
+        .jobs
+        .iter()
+        .zip(&results)
+        .enumerate()
+        .map(|(index, (job, result))| {
+            let (status, detail) = match &result.output {
+                Ok(path) => ("成功".to_string(), path.display().to_string()),
+                Err(err) => (err.kind().to_string(), err.to_string()),
+            };
+            vec![
+                (index + 1).to_string(),
+                job.key.clone(),
+                status,
+                format!("{:.2}s", result.duration.as_secs_f32()),
+                detail,
+            ]
+        })
+        .collect::<Vec<_>>();
+    println!();
+    print_table(&["序号", "表情名", "状态", "耗时", "输出/错误"], &rows);
+
+    let failed = results
+        .iter()
+        .filter(|result| result.output.is_err())
+        .count();
+    println!(
+        "\n共 {} 项，成功 {}，失败 {failed}，用时 {:.2}s",
+        results.len(),
+        results.len() - failed,
+        elapsed.as_secs_f32()
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(key: &str, images: &[&str], output: Option<&str>) -> Job {
+        Job {
+            key: key.to_string(),
+            images: images.iter().map(|image| image.to_string()).collect(),
+            names: Vec::new(),
+            texts: Vec::new(),
+            options: HashMap::new(),
+            output: output.map(PathBuf::from),
+        }
+    }
+
+    fn check(jobs: &[Job]) -> Result<(), String> {
+        check_manifest(jobs, Path::new("base"), Path::new("base/out"))
+    }
+
+    #[test]
+    fn manifest_rejects_stdin() {
+        assert!(check(&[job("petpet", &["a.png"], None)]).is_ok());
+        assert!(check(&[job("petpet", &["a.png", "-"], None)]).is_err());
+    }
+
+    #[test]
+    fn manifest_rejects_duplicate_outputs() {
+        assert!(
+            check(&[
+                job("petpet", &[], Some("a.gif")),
+                job("petpet", &[], Some("b.gif")),
+                job("petpet", &[], None),
+                job("petpet", &[], None),
+            ])
+            .is_ok()
+        );
+        assert!(
+            check(&[
+                job("petpet", &[], Some("a.gif")),
+                job("petpet", &[], Some("./a.gif")),
+            ])
+            .is_err()
+        );
+        // 与未指定输出路径的第 2 项冲突
+        assert!(
+            check(&[
+                job("petpet", &[], Some("out/2_petpet.gif")),
+                job("petpet", &[], None),
+            ])
+            .is_err()
+        );
+    }
+}
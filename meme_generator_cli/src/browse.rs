@@ -0,0 +1,805 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::ArgMatches;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::{
+        cursor::MoveTo,
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        queue,
+        terminal::window_size,
+    },
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use meme_generator::{
+    MemeSortBy, get_meme, get_meme_keys_sorted,
+    meme::{MemeOption, OptionValue},
+    search_memes,
+};
+
+use crate::{
+    batch::display_width,
+    cli::{error_message, meme_info_text},
+    graphics::{GraphicsProtocol, decode_rgba},
+    input::{STDIN, read_image},
+};
+
+/// 选中表情后停留一段时间才生成预览，避免快速翻动列表时生成大量预览
+const PREVIEW_DELAY: Duration = Duration::from_millis(200);
+
+const TICK: Duration = Duration::from_millis(100);
+
+enum Message {
+    Preview(String, Result<Vec<u8>, String>),
+    Generated(String, Result<(PathBuf, Vec<u8>), String>),
+}
+
+enum FieldKind {
+    Image,
+    Text,
+    Option(MemeOption),
+    Output,
+}
+
+fn range_hint(description: &Option<String>, min: Option<String>, max: Option<String>) -> String {
+    let description = description.as_deref().unwrap_or("");
+    if min.is_none() && max.is_none() {
+        return description.to_string();
+    }
+    let min = min.unwrap_or_default();
+    let max = max.unwrap_or_default();
+    format!("{description}（范围：{min}~{max}）")
+}
+
+struct Field {
+    label: String,
+    kind: FieldKind,
+    value: String,
+}
+
+impl Field {
+    fn hint(&self) -> String {
+        match &self.kind {
+            FieldKind::Image => "图片路径或链接".to_string(),
+            FieldKind::Output => "默认为 <表情名>.<扩展名>".to_string(),
+            FieldKind::Text => String::new(),
+            FieldKind::Option(option) => match option {
+                MemeOption::Boolean { description, .. } => {
+                    let description = description.as_deref().unwrap_or("");
+                    format!("{description}（空格切换）")
+                }
+                MemeOption::String {
+                    description,
+                    choices,
+                    ..
+                } => {
+                    let description = description.as_deref().unwrap_or("");
+                    match choices {
+                        Some(choices) => {
+                            format!("{description}（←→ 切换：{}）", choices.join("、"))
+                        }
+                        None => description.to_string(),
+                    }
+                }
+                MemeOption::Integer {
+                    description,
+                    minimum,
+                    maximum,
+                    ..
+                } => range_hint(
+                    description,
+                    minimum.map(|v| v.to_string()),
+                    maximum.map(|v| v.to_string()),
+                ),
+                MemeOption::Float {
+                    description,
+                    minimum,
+                    maximum,
+                    ..
+                } => range_hint(
+                    description,
+                    minimum.map(|v| v.to_string()),
+                    maximum.map(|v| v.to_string()),
+                ),
+            },
+        }
+    }
+
+    /// 布尔值取反，有可选项的字符串切换到上一个或下一个选项
+    fn cycle(&mut self, forward: bool) {
+        let FieldKind::Option(option) = &self.kind else {
+            return;
+        };
+        match option {
+            MemeOption::Boolean { .. } => {
+                self.value = (self.value != "true").to_string();
+            }
+            MemeOption::String {
+                choices: Some(choices),
+                ..
+            } if !choices.is_empty() => {
+                let len = choices.len();
+                let index = choices.iter().position(|choice| *choice == self.value);
+                let index = match (index, forward) {
+                    (Some(index), true) => (index + 1) % len,
+                    (Some(index), false) => (index + len - 1) % len,
+                    (None, _) => 0,
+                };
+                self.value = choices[index].clone();
+            }
+            _ => {}
+        }
+    }
+
+    fn is_toggle(&self) -> bool {
+        matches!(self.kind, FieldKind::Option(MemeOption::Boolean { .. }))
+    }
+}
+
+/// 制作表情所需的输入
+struct Request {
+    key: String,
+    images: Vec<String>,
+    texts: Vec<String>,
+    options: HashMap<String, OptionValue>,
+    output: Option<PathBuf>,
+}
+
+/// 填写图片、文字和参数以制作表情的表单
+struct Form {
+    key: String,
+    fields: Vec<Field>,
+    focus: usize,
+    result: Option<Vec<u8>>,
+}
+
+impl Form {
+    fn new(key: &str) -> Self {
+        let params = get_meme(key).unwrap().info().params;
+        let mut fields = Vec::new();
+        for index in 0..params.max_images {
+            let optional = if index < params.min_images {
+                ""
+            } else {
+                "（可选）"
+            };
+            fields.push(Field {
+                label: format!("图片 {}{optional}", index + 1),
+                kind: FieldKind::Image,
+                value: String::new(),
+            });
+        }
+        for index in 0..params.max_texts {
+            let optional = if index < params.min_texts {
+                ""
+            } else {
+                "（可选）"
+            };
+            fields.push(Field {
+                label: format!("文字 {}{optional}", index + 1),
+                kind: FieldKind::Text,
+                value: params
+                    .default_texts
+                    .get(index as usize)
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+        for option in params.options {
+            let (label, value) = match &option {
+                MemeOption::Boolean { name, default, .. } => {
+                    (name.clone(), default.unwrap_or(false).to_string())
+                }
+                MemeOption::String { name, default, .. } => {
+                    (name.clone(), default.clone().unwrap_or_default())
+                }
+                MemeOption::Integer { name, default, .. } => (
+                    name.clone(),
+                    default.map(|v| v.to_string()).unwrap_or_default(),
+                ),
+                MemeOption::Float { name, default, .. } => (
+                    name.clone(),
+                    default.map(|v| v.to_string()).unwrap_or_default(),
+                ),
+            };
+            fields.push(Field {
+                label,
+                kind: FieldKind::Option(option),
+                value,
+            });
+        }
+        fields.push(Field {
+            label: "输出文件".to_string(),
+            kind: FieldKind::Output,
+            value: String::new(),
+        });
+        Self {
+            key: key.to_string(),
+            fields,
+            focus: 0,
+            result: None,
+        }
+    }
+
+    fn request(&self) -> Result<Request, String> {
+        let mut images = Vec::new();
+        let mut texts = Vec::new();
+        let mut options = HashMap::new();
+        let mut output = None;
+        for field in &self.fields {
+            let value = field.value.trim();
+            match &field.kind {
+                FieldKind::Image => {
+                    if value == STDIN {
+                        return Err("浏览模式下不支持从标准输入读取图片".to_string());
+                    }
+                    if !value.is_empty() {
+                        images.push(value.to_string());
+                    }
+                }
+                FieldKind::Text => texts.push(field.value.clone()),
+                FieldKind::Output => {
+                    if !value.is_empty() {
+                        output = Some(PathBuf::from(value));
+                    }
+                }
+                FieldKind::Option(option) => {
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let invalid = |kind: &str| format!("参数 `{}` 不是有效的{kind}", field.label);
+                    let value = match option {
+                        MemeOption::Boolean { .. } => OptionValue::Boolean(value == "true"),
+                        MemeOption::String { .. } => OptionValue::String(value.to_string()),
+                        MemeOption::Integer { .. } => {
+                            OptionValue::Integer(value.parse().map_err(|_| invalid("整数"))?)
+                        }
+                        MemeOption::Float { .. } => {
+                            OptionValue::Float(value.parse().map_err(|_| invalid("数字"))?)
+                        }
+                    };
+                    options.insert(field.label.clone(), value);
+                }
+            }
+        }
+        // 末尾留空的文字视为未填写
+        while texts.last().is_some_and(|text| text.is_empty()) {
+            texts.pop();
+        }
+        Ok(Request {
+            key: self.key.clone(),
+            images,
+            texts,
+            options,
+            output,
+        })
+    }
+}
+
+fn generate(request: Request) -> Result<(PathBuf, Vec<u8>), String> {
+    let meme = get_meme(&request.key).unwrap();
+    let images = request
+        .images
+        .iter()
+        .map(|source| read_image(source, Path::new("")).map_err(|err| format!("{source}：{err}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let data = meme
+        .generate(images, request.texts, request.options)
+        .map_err(|err| error_message(&err))?;
+    let path = match request.output {
+        Some(output) => output,
+        None => {
+            let extension = infer::get(&data)
+                .map(|kind| kind.extension())
+                .unwrap_or("png");
+            PathBuf::from(format!("{}.{extension}", request.key))
+        }
+    };
+    fs::write(&path, &data).map_err(|err| format!("图片保存失败：{err}"))?;
+    Ok((path, data))
+}
+
+/// 终端单个字符的像素大小，无法获取时按 8×16 估算
+fn cell_size() -> (i32, i32) {
+    match window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns) as i32,
+            (size.height / size.rows) as i32,
+        ),
+        _ => (8, 16),
+    }
+}
+
+struct App {
+    keys: Vec<String>,
+    labels: HashMap<String, String>,
+    infos: HashMap<String, String>,
+    query: String,
+    results: Vec<String>,
+    list_state: ListState,
+    selected_at: Instant,
+    previews: HashMap<String, Result<Vec<u8>, String>>,
+    pending: HashSet<String>,
+    form: Option<Form>,
+    generating: bool,
+    status: String,
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    protocol: Option<GraphicsProtocol>,
+    image_area: Rect,
+    /// 当前显示的图片及其位置
+    shown: Option<(String, Rect)>,
+    needs_clear: bool,
+    quit: bool,
+}
+
+impl App {
+    fn new(protocol: Option<GraphicsProtocol>, query: String) -> Self {
+        let keys = get_meme_keys_sorted(MemeSortBy::KeywordsPinyin, false);
+        let labels = keys
+            .iter()
+            .map(|key| {
+                let keywords = get_meme(key).unwrap().info().keywords.join("/");
+                (key.clone(), format!("{key} ({keywords})"))
+            })
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        let mut app = Self {
+            keys,
+            labels,
+            infos: HashMap::new(),
+            query,
+            results: Vec::new(),
+            list_state: ListState::default(),
+            selected_at: Instant::now(),
+            previews: HashMap::new(),
+            pending: HashSet::new(),
+            form: None,
+            generating: false,
+            status: String::new(),
+            sender,
+            receiver,
+            protocol,
+            image_area: Rect::default(),
+            shown: None,
+            needs_clear: false,
+            quit: false,
+        };
+        app.filter();
+        app
+    }
+
+    /// 按关键词模糊搜索表情名、关键词、快捷指令和标签
+    fn filter(&mut self) {
+        self.results = if self.query.trim().is_empty() {
+            self.keys.clone()
+        } else {
+            search_memes(self.query.trim(), true)
+        };
+        self.select(0);
+    }
+
+    fn select(&mut self, index: usize) {
+        if self.results.is_empty() {
+            self.list_state.select(None);
+        } else {
+            self.list_state
+                .select(Some(index.min(self.results.len() - 1)));
+        }
+        self.selected_at = Instant::now();
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let current = self.list_state.selected().unwrap_or(0);
+        self.select(current.saturating_add_signed(delta));
+    }
+
+    fn current_key(&self) -> Option<&String> {
+        match &self.form {
+            Some(form) => Some(&form.key),
+            None => self
+                .list_state
+                .selected()
+                .and_then(|index| self.results.get(index)),
+        }
+    }
+
+    /// 表单中有制作结果时显示结果，否则显示表情预览
+    fn current_image(&self) -> Option<(String, &[u8])> {
+        if let Some(form) = &self.form
+            && let Some(data) = &form.result
+        {
+            return Some((format!("result:{}", form.key), data));
+        }
+        let key = self.current_key()?;
+        match self.previews.get(key) {
+            Some(Ok(data)) => Some((format!("preview:{key}"), data)),
+            _ => None,
+        }
+    }
+
+    fn request_preview(&mut self) {
+        if self.selected_at.elapsed() < PREVIEW_DELAY {
+            return;
+        }
+        let Some(key) = self.current_key().cloned() else {
+            return;
+        };
+        if self.previews.contains_key(&key) || self.pending.contains(&key) {
+            return;
+        }
+        self.pending.insert(key.clone());
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = get_meme(&key)
+                .unwrap()
+                .generate_preview(HashMap::new())
+                .map_err(|err| error_message(&err));
+            let _ = sender.send(Message::Preview(key, result));
+        });
+    }
+
+    fn submit(&mut self) {
+        let Some(form) = &self.form else {
+            return;
+        };
+        if self.generating {
+            return;
+        }
+        let request = match form.request() {
+            Ok(request) => request,
+            Err(err) => {
+                self.status = err;
+                return;
+            }
+        };
+        self.generating = true;
+        self.status = "表情制作中…".to_string();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let key = request.key.clone();
+            let result = generate(request);
+            let _ = sender.send(Message::Generated(key, result));
+        });
+    }
+
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Preview(key, result) => {
+                self.pending.remove(&key);
+                self.previews.insert(key, result);
+            }
+            Message::Generated(key, result) => {
+                self.generating = false;
+                match result {
+                    Ok((path, data)) => {
+                        self.status =
+                            format!("表情制作成功！生成的表情文件为 `{}`", path.display());
+                        if let Some(form) = &mut self.form
+                            && form.key == key
+                        {
+                            form.result = Some(data);
+                        }
+                    }
+                    Err(err) => self.status = format!("表情制作失败：{err}"),
+                }
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        if self.form.is_some() {
+            self.handle_form_key(key);
+        } else {
+            self.handle_list_key(key);
+        }
+    }
+
+    fn handle_list_key(&mut self, key: KeyEvent) {
+        let page = self.image_area.height.max(1) as isize;
+        match key.code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-page),
+            KeyCode::PageDown => self.move_selection(page),
+            KeyCode::Home => self.select(0),
+            KeyCode::End => self.select(self.results.len().saturating_sub(1)),
+            KeyCode::Enter => {
+                if let Some(key) = self.current_key().cloned() {
+                    self.form = Some(Form::new(&key));
+                    self.status.clear();
+                }
+            }
+            KeyCode::Backspace => {
+                if self.query.pop().is_some() {
+                    self.filter();
+                }
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_form_key(&mut self, key: KeyEvent) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        let len = form.fields.len();
+        let field = &mut form.fields[form.focus];
+        match key.code {
+            KeyCode::Esc => {
+                self.form = None;
+                self.status.clear();
+            }
+            KeyCode::Up | KeyCode::BackTab => form.focus = (form.focus + len - 1) % len,
+            KeyCode::Down | KeyCode::Tab => form.focus = (form.focus + 1) % len,
+            KeyCode::Left => field.cycle(false),
+            KeyCode::Right => field.cycle(true),
+            KeyCode::Char(' ') if field.is_toggle() => field.cycle(true),
+            KeyCode::Char(c) if !field.is_toggle() => field.value.push(c),
+            KeyCode::Backspace if !field.is_toggle() => {
+                field.value.pop();
+            }
+            KeyCode::Enter => self.submit(),
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let [detail, image] =
+            Layout::vertical([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(right);
+        self.draw_list(frame, left);
+        if self.form.is_some() {
+            self.draw_form(frame, detail);
+        } else {
+            self.draw_info(frame, detail);
+        }
+        self.draw_image(frame, image);
+
+        let help_text = if !self.status.is_empty() {
+            self.status.clone()
+        } else if self.form.is_some() {
+            "↑↓/Tab 切换输入项  ←→/空格 切换选项  Enter 制作  Esc 返回".to_string()
+        } else {
+            "输入关键词搜索  ↑↓ 选择  Enter 填写并制作  Esc 退出".to_string()
+        };
+        frame.render_widget(Paragraph::new(help_text).dim(), help);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let [search, list] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+        frame.render_widget(
+            Paragraph::new(self.query.as_str()).block(Block::bordered().title("搜索")),
+            search,
+        );
+        if self.form.is_none() {
+            frame.set_cursor_position((
+                search.x + 1 + display_width(&self.query) as u16,
+                search.y + 1,
+            ));
+        }
+
+        let items = self
+            .results
+            .iter()
+            .map(|key| ListItem::new(self.labels.get(key).cloned().unwrap_or(key.clone())))
+            .collect::<Vec<_>>();
+        let title = format!("表情（{}/{}）", self.results.len(), self.keys.len());
+        let list_widget = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list_widget, list, &mut self.list_state);
+    }
+
+    fn draw_info(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("详情");
+        let Some(key) = self.current_key().cloned() else {
+            frame.render_widget(Paragraph::new("未找到相关表情").block(block), area);
+            return;
+        };
+        let info = self
+            .infos
+            .entry(key.clone())
+            .or_insert_with(|| meme_info_text(get_meme(&key).unwrap().as_ref()));
+        frame.render_widget(
+            Paragraph::new(info.trim_end())
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    fn draw_form(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(form) = &self.form else {
+            return;
+        };
+        let block = Block::bordered().title(format!("制作 {}", form.key));
+        let inner = block.inner(area);
+        let lines = form
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let label = Span::from(format!("{}：", field.label)).bold();
+                let value = Span::from(field.value.clone());
+                let hint = Span::from(format!("  {}", field.hint())).dim();
+                let line = Line::from(vec![label, value, hint]);
+                if index == form.focus {
+                    line.reversed()
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+        let scroll = form
+            .focus
+            .saturating_sub(inner.height.saturating_sub(1) as usize);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .scroll((scroll as u16, 0)),
+            area,
+        );
+
+        let field = &form.fields[form.focus];
+        if !field.is_toggle() {
+            let x = display_width(&field.label) + 2 + display_width(&field.value);
+            frame.set_cursor_position((
+                (inner.x + x as u16).min(inner.right().saturating_sub(1)),
+                inner.y + (form.focus - scroll) as u16,
+            ));
+        }
+    }
+
+    fn draw_image(&mut self, frame: &mut Frame, area: Rect) {
+        let has_result = self.form.as_ref().is_some_and(|form| form.result.is_some());
+        let block = Block::bordered().title(if has_result { "结果" } else { "预览" });
+        self.image_area = block.inner(area);
+
+        let message = if has_result || self.current_image().is_some() {
+            match self.protocol {
+                Some(_) => String::new(),
+                None => "当前终端不支持显示图片，可通过 `--graphics` 指定图形协议".to_string(),
+            }
+        } else {
+            match self.current_key().and_then(|key| self.previews.get(key)) {
+                Some(Err(err)) => format!("预览生成失败：{err}"),
+                Some(Ok(_)) => String::new(),
+                None if self.current_key().is_some() => "预览生成中…".to_string(),
+                None => String::new(),
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(message)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    /// 图片通过图形协议直接输出到终端，界面重绘不会覆盖，需要在图片变化时单独处理
+    fn show_image(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let Some(protocol) = self.protocol else {
+            return Ok(());
+        };
+        let target = self
+            .current_image()
+            .map(|(id, _)| (id, self.image_area))
+            .filter(|(_, area)| area.width > 0 && area.height > 0);
+        if target == self.shown {
+            return Ok(());
+        }
+        if self.shown.is_some() {
+            if protocol == GraphicsProtocol::Sixel {
+                // sixel 图片只能通过重绘界面清除，下一轮重绘后再显示新图片
+                self.needs_clear = true;
+                return Ok(());
+            }
+            write!(terminal.backend_mut(), "{}", protocol.clear_sequence())?;
+            self.shown = None;
+        }
+        if let Some((id, area)) = target {
+            let data = self.current_image().unwrap().1;
+            let (cell_width, cell_height) = cell_size();
+            let max_width = area.width as i32 * cell_width;
+            let max_height = area.height as i32 * cell_height;
+            match decode_rgba(data, max_width, max_height) {
+                Some(image) => {
+                    let backend = terminal.backend_mut();
+                    queue!(backend, MoveTo(area.x, area.y))?;
+                    write!(backend, "{}", protocol.encode(&image))?;
+                }
+                None => self.status = "图片显示失败：图片解码失败".to_string(),
+            }
+            self.shown = Some((id, area));
+        }
+        terminal.backend_mut().flush()
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.quit {
+        app.request_preview();
+        while let Ok(message) = app.receiver.try_recv() {
+            app.handle_message(message);
+        }
+        if app.needs_clear {
+            terminal.clear()?;
+            app.shown = None;
+            app.needs_clear = false;
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+        app.show_image(terminal)?;
+
+        if event::poll(TICK)? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                Event::Resize(..) => app.needs_clear = true,
+                _ => {}
+            }
+        }
+    }
+    if let Some(protocol) = app.protocol {
+        write!(terminal.backend_mut(), "{}", protocol.clear_sequence())?;
+    }
+    Ok(())
+}
+
+pub(crate) fn handle_browse(sub_matches: &ArgMatches) -> ExitCode {
+    let protocol = match sub_matches
+        .get_one::<String>("graphics")
+        .map(|s| s.as_str())
+    {
+        Some("auto") | None => GraphicsProtocol::detect(),
+        Some(name) => GraphicsProtocol::from_name(name),
+    };
+    let query = sub_matches
+        .get_one::<String>("QUERY")
+        .cloned()
+        .unwrap_or_default();
+    let mut app = App::new(protocol, query);
+
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            eprintln!("终端界面初始化失败：{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("终端界面运行失败：{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -2,8 +2,9 @@
 use std::net::IpAddr;
 use std::{
     collections::HashMap,
-    fs::{read, write},
-    path::PathBuf,
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
 
 use clap::{
@@ -11,19 +12,25 @@ use clap::{
     builder::{PossibleValue, ValueParser},
     value_parser,
 };
+use clap_complete::{Shell, generate};
+use clap_mangen::Man;
 
 use meme_generator::{
-    VERSION,
+    MemeSortBy, VERSION,
     error::Error,
     get_meme, get_meme_keys, get_memes,
-    meme::{Image, MemeOption, OptionValue},
+    meme::{Image, Meme, MemeOption, OptionValue},
     resources::check_resources_sync,
     search_memes,
 };
 #[cfg(feature = "server")]
 use meme_generator_server::run_server_sync;
 
-use crate::tools::{handle_gif, handle_image};
+use crate::{
+    input::{STDIN, read_image},
+    output::{CliError, OutputOptions, finish, output_args},
+    tools::{handle_gif, handle_image, handle_render_list, handle_statistics},
+};
 
 fn build_arg(option: MemeOption) -> Arg {
     match option {
@@ -51,10 +58,10 @@ fn build_arg(option: MemeOption) -> Arg {
                 arg = arg.long(&name);
             }
             for alias in parser_flags.short_aliases {
-                arg = arg.short_alias(alias);
+                arg = arg.visible_short_alias(alias);
             }
             for alias in parser_flags.long_aliases {
-                arg = arg.alias(alias);
+                arg = arg.visible_alias(alias);
             }
             arg
         }
@@ -87,10 +94,10 @@ fn build_arg(option: MemeOption) -> Arg {
                 arg = arg.long(&name);
             }
             for alias in parser_flags.short_aliases {
-                arg = arg.short_alias(alias);
+                arg = arg.visible_short_alias(alias);
             }
             for alias in parser_flags.long_aliases {
-                arg = arg.alias(alias);
+                arg = arg.visible_alias(alias);
             }
             arg
         }
@@ -127,10 +134,10 @@ fn build_arg(option: MemeOption) -> Arg {
                 arg = arg.long(&name);
             }
             for alias in parser_flags.short_aliases {
-                arg = arg.short_alias(alias);
+                arg = arg.visible_short_alias(alias);
             }
             for alias in parser_flags.long_aliases {
-                arg = arg.alias(alias);
+                arg = arg.visible_alias(alias);
             }
             arg = arg.allow_hyphen_values(true);
             arg
@@ -190,10 +197,10 @@ fn build_arg(option: MemeOption) -> Arg {
                 arg = arg.long(&name);
             }
             for alias in parser_flags.short_aliases {
-                arg = arg.short_alias(alias);
+                arg = arg.visible_short_alias(alias);
             }
             for alias in parser_flags.long_aliases {
-                arg = arg.alias(alias);
+                arg = arg.visible_alias(alias);
             }
             arg = arg.allow_hyphen_values(true);
             arg
@@ -201,6 +208,27 @@ fn build_arg(option: MemeOption) -> Arg {
     }
 }
 
+fn sort_by_parser() -> ValueParser {
+    ValueParser::new(|s: &str| match s {
+        "key" => Ok(MemeSortBy::Key),
+        "keywords" => Ok(MemeSortBy::Keywords),
+        "keywords_pinyin" => Ok(MemeSortBy::KeywordsPinyin),
+        "date_created" => Ok(MemeSortBy::DateCreated),
+        "date_modified" => Ok(MemeSortBy::DateModified),
+        _ => Err(clap::Error::raw(
+            clap::error::ErrorKind::InvalidValue,
+            "可选值：key、keywords、keywords_pinyin、date_created、date_modified\n",
+        )),
+    })
+}
+
+fn root_arg() -> Arg {
+    arg!(--root <DIR> "仓库目录")
+        .overrides_with("root")
+        .default_value(".")
+        .value_parser(value_parser!(PathBuf))
+}
+
 pub(crate) fn build_command() -> Command {
     let mut sub_commands: Vec<Command> = Vec::new();
     for meme in get_memes() {
@@ -211,8 +239,8 @@ pub(crate) fn build_command() -> Command {
         let mut command = Command::new(key)
             .about(keywords)
             .arg(
-                arg!(--images [IMAGES] "图片路径")
-                    .value_parser(value_parser!(PathBuf))
+                arg!(--images [IMAGES] "图片路径或链接，`-` 表示从标准输入读取")
+                    .value_parser(value_parser!(String))
                     .num_args(1..),
             )
             .arg(arg!(--names [NAMES] "图片名").num_args(1..))
@@ -253,6 +281,16 @@ pub(crate) fn build_command() -> Command {
                 .arg(arg!(<KEYWORD> "关键词").value_parser(value_parser!(String)))
                 .arg_required_else_help(true),
         )
+        .subcommand(
+            Command::new("browse")
+                .about("在终端界面中浏览、搜索和制作表情")
+                .arg(arg!([QUERY] "初始搜索关键词").value_parser(value_parser!(String)))
+                .arg(
+                    arg!(--graphics <PROTOCOL> "显示图片使用的终端图形协议，默认根据终端自动检测")
+                        .value_parser(["auto", "kitty", "sixel", "none"])
+                        .default_value("auto"),
+                ),
+        )
         .subcommand(
             Command::new("preview")
                 .about("生成表情预览")
@@ -264,15 +302,31 @@ pub(crate) fn build_command() -> Command {
                             .collect::<Vec<PossibleValue>>(),
                     ),
                 )
+                .args(output_args())
                 .arg_required_else_help(true),
         )
         .subcommand(
             Command::new("generate")
                 .alias("make")
                 .about("制作表情")
+                .args(output_args().map(|arg| arg.global(true)))
                 .subcommands(sub_commands)
                 .subcommand_required(true),
         )
+        .subcommand(
+            Command::new("batch")
+                .about("根据清单批量制作表情")
+                .arg(
+                    arg!(<MANIFEST> "清单文件路径，支持 toml 和 json 格式")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(-j --jobs <JOBS> "同时制作的数量，默认为 CPU 核心数")
+                        .overrides_with("jobs")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg_required_else_help(true),
+        )
         .subcommand(
             Command::new("download").about("下载表情包所需的资源").arg(
                 arg!(--url <URL> "资源链接")
@@ -280,6 +334,64 @@ pub(crate) fn build_command() -> Command {
                     .value_parser(value_parser!(String)),
             ),
         )
+        .subcommand(
+            Command::new("doctor")
+                .about("诊断运行环境，检查路径、配置、字体、资源文件和外部表情库")
+                .arg(
+                    arg!(--resources <FILE> "使用本地的 resources.json 检查资源文件，默认从资源链接下载")
+                        .overrides_with("resources")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(--"no-render" "跳过表情预览的制作"))
+                .arg(
+                    arg!(-j --jobs <JOBS> "同时制作的数量，默认为 CPU 核心数")
+                        .overrides_with("jobs")
+                        .value_parser(value_parser!(usize)),
+                )
+                .arg(arg!(--json "以 JSON 格式输出诊断结果")),
+        )
+        .subcommand(
+            Command::new("test-render")
+                .about("将表情预览与参考图片比较，用于检查表情制作结果的变化")
+                .arg(
+                    arg!([KEYS]... "要测试的表情名，默认测试所有表情")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    arg!(--golden <DIR> "参考图片所在目录")
+                        .overrides_with("golden")
+                        .default_value("golden")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--"diff-dir" <DIR> "不一致时实际结果和差异图片的保存目录")
+                        .overrides_with("diff-dir")
+                        .default_value("golden_diff")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(--bless "用实际结果替换缺失或不一致的参考图片"))
+                .arg(arg!(--"external-only" "只测试外部表情库中的表情"))
+                .arg(
+                    arg!(--seed <SEED> "随机数种子，默认为 42")
+                        .overrides_with("seed")
+                        .value_parser(value_parser!(u64)),
+                )
+                .arg(
+                    arg!(--threshold <THRESHOLD> "单个像素的颜色差异阈值，范围为 0~1，默认为 0.1")
+                        .overrides_with("threshold")
+                        .value_parser(value_parser!(f32)),
+                )
+                .arg(
+                    arg!(--"max-diff" <PERCENT> "允许的不同像素百分比，默认为 0.1")
+                        .overrides_with("max-diff")
+                        .value_parser(value_parser!(f32)),
+                )
+                .arg(
+                    arg!(-j --jobs <JOBS> "同时制作的数量，默认为 CPU 核心数")
+                        .overrides_with("jobs")
+                        .value_parser(value_parser!(usize)),
+                ),
+        )
         .subcommand(
             Command::new("tools")
                 .about("工具箱")
@@ -302,6 +414,7 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .required(true),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("flip_v")
                                 .about("竖直翻转")
@@ -310,6 +423,7 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .required(true),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("rotate")
                                 .about("旋转")
@@ -323,6 +437,7 @@ pub(crate) fn build_command() -> Command {
                                         .overrides_with("degrees")
                                         .value_parser(value_parser!(f32)),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("resize")
                                 .about("调整大小")
@@ -341,6 +456,7 @@ pub(crate) fn build_command() -> Command {
                                         .overrides_with("height")
                                         .value_parser(value_parser!(i32)),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("crop")
                                 .about("裁剪")
@@ -369,6 +485,7 @@ pub(crate) fn build_command() -> Command {
                                         .overrides_with("bottom")
                                         .value_parser(value_parser!(i32)),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("grayscale")
                                 .about("灰度化")
@@ -377,6 +494,7 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .required(true),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("invert")
                                 .about("反色")
@@ -385,6 +503,7 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .required(true),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("merge_h")
                                 .about("水平拼接")
@@ -393,6 +512,7 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .num_args(2..),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                             Command::new("merge_v")
                                 .about("竖直拼接")
@@ -401,6 +521,7 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .num_args(2..),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                         ])
                         .subcommand_required(true),
@@ -416,6 +537,12 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .required(true),
                                 )
+                                .arg(
+                                    arg!(--"output-dir" <DIR> "输出目录，默认为 result")
+                                        .overrides_with("output-dir")
+                                        .value_parser(value_parser!(PathBuf)),
+                                )
+                                .arg(arg!(--json "以 JSON 格式输出结果信息和错误"))
                                 .arg_required_else_help(true),
                             Command::new("merge")
                                 .about("合并")
@@ -429,12 +556,17 @@ pub(crate) fn build_command() -> Command {
                                         .overrides_with("duration")
                                         .value_parser(value_parser!(f32)),
                                 )
+                                .args(output_args())
+                                .arg_required_else_help(true),
+                            Command::new("reverse")
+                                .about("反转")
+                                .arg(
+                                    arg!(<IMAGE> "gif路径")
+                                        .value_parser(value_parser!(PathBuf))
+                                        .required(true),
+                                )
+                                .args(output_args())
                                 .arg_required_else_help(true),
-                            Command::new("reverse").about("反转").arg(
-                                arg!(<IMAGE> "gif路径")
-                                    .value_parser(value_parser!(PathBuf))
-                                    .required(true),
-                            ),
                             Command::new("duration")
                                 .about("调整帧间隔时间")
                                 .arg(
@@ -447,12 +579,103 @@ pub(crate) fn build_command() -> Command {
                                         .value_parser(value_parser!(PathBuf))
                                         .required(true),
                                 )
+                                .args(output_args())
                                 .arg_required_else_help(true),
                         ])
                         .subcommand_required(true),
                 )
+                .subcommand(
+                    Command::new("render-list")
+                        .about("生成表情列表图片")
+                        .arg(
+                            arg!(--"sort-by" <SORT_BY> "排序方式")
+                                .overrides_with("sort-by")
+                                .value_parser(sort_by_parser()),
+                        )
+                        .arg(arg!(--reverse "倒序排列"))
+                        .arg(
+                            arg!(--exclude <KEYS> "不显示的表情")
+                                .value_parser(value_parser!(String))
+                                .num_args(1..),
+                        )
+                        .arg(
+                            arg!(--properties <FILE> "表情属性文件，json 或 toml 格式，格式为 `表情名 = { disabled, hot, new }`")
+                                .overrides_with("properties")
+                                .value_parser(value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            arg!(--"text-template" <TEMPLATE> "文字模板，可用变量：{index}、{key}、{keywords}、{shortcuts}、{tags}")
+                                .overrides_with("text-template")
+                                .value_parser(value_parser!(String)),
+                        )
+                        .arg(arg!(--"no-category-icon" "不显示分类图标"))
+                        .args(output_args()),
+                )
+                .subcommand(
+                    Command::new("statistics")
+                        .about("生成表情调用统计图")
+                        .arg(
+                            arg!(<DATA> "统计数据文件，csv 格式每行为 `名称,数量`，json 格式为 `[[名称, 数量], ...]`")
+                                .value_parser(value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            arg!(--title <TITLE> "标题")
+                                .overrides_with("title")
+                                .value_parser(value_parser!(String)),
+                        )
+                        .arg(
+                            arg!(--type <TYPE> "统计类型：按表情统计为柱状图，按时间统计为折线图")
+                                .overrides_with("type")
+                                .value_parser(["meme_count", "time_count"])
+                                .default_value("meme_count"),
+                        )
+                        .args(output_args())
+                        .arg_required_else_help(true),
+                )
                 .subcommand_required(true),
         );
+    command = command
+        .subcommand(
+            Command::new("new")
+                .about("在仓库中创建新表情，生成表情模块，提供底图时创建资源目录")
+                .arg(arg!(<KEY> "表情名，只能包含小写字母、数字和下划线"))
+                .arg(
+                    arg!(--kind <KIND> "表情类型：static 静态图片，gif 固定帧数的 GIF，text 纯文字；不指定时询问")
+                        .overrides_with("kind")
+                        .value_parser(["static", "gif", "text"]),
+                )
+                .arg(arg!(--keywords <KEYWORDS> "关键词；不指定时询问").num_args(1..))
+                .arg(
+                    arg!(--frames <FILES> "复制到资源目录的底图，GIF 表情可提供多帧，帧数即为图片数量；纯文字表情不使用底图")
+                        .num_args(1..)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(root_arg())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("update-resources")
+                .about("重新计算资源文件的哈希值，更新 resources/resources.json")
+                .arg(root_arg()),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("生成命令补全脚本，包括所有表情及其选项")
+                .long_about(
+                    "生成命令补全脚本，包括所有表情及其选项\n\n\
+                     补全脚本根据当前加载的表情生成，添加表情或外部表情库后需重新生成\n\n\
+                     示例：meme completions bash > ~/.local/share/bash-completion/completions/meme",
+                )
+                .arg(arg!(<SHELL> "shell 类型").value_parser(value_parser!(Shell)))
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("man").about("生成 man 手册").arg(
+                arg!(--dir <DIR> "将所有子命令的手册写入目录，默认只输出 meme 的手册到标准输出")
+                    .overrides_with("dir")
+                    .value_parser(value_parser!(PathBuf)),
+            ),
+        );
     #[cfg(feature = "server")]
     {
         command = command.subcommand(
@@ -490,9 +713,29 @@ pub(crate) fn handle_list() {
     println!("表情列表：\n{list}");
 }
 
-pub(crate) fn handle_info(sub_matches: &ArgMatches) {
+fn find_meme(key: &str) -> Result<&'static dyn Meme, CliError> {
+    get_meme(key)
+        .map(|meme| meme.as_ref())
+        .ok_or_else(|| CliError::Input(format!("表情 `{key}` 不存在")))
+}
+
+pub(crate) fn handle_info(sub_matches: &ArgMatches) -> ExitCode {
     let key = sub_matches.get_one::<String>("KEY").unwrap();
-    let meme = get_meme(key).expect(format!("表情 `{key}` 不存在").as_str());
+    match find_meme(key) {
+        Ok(meme) => {
+            println!("{}", meme_info_text(meme));
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            error.exit_code()
+        }
+    }
+}
+
+/// 表情的详细信息，包括关键词、快捷指令、标签、图片和文字数目、其他参数等
+pub(crate) fn meme_info_text(meme: &dyn Meme) -> String {
+    let key = meme.key();
     let info = meme.info();
     let options = info.params.options;
     let options = options
@@ -614,7 +857,7 @@ pub(crate) fn handle_info(sub_matches: &ArgMatches) {
     if !options.is_empty() {
         output += &format!("其他参数：\n{options}\n");
     }
-    println!("{output}");
+    output
 }
 
 pub(crate) fn handle_search(sub_matches: &ArgMatches) {
@@ -645,41 +888,53 @@ pub(crate) fn handle_search(sub_matches: &ArgMatches) {
     }
 }
 
-pub(crate) fn handle_preview(sub_matches: &ArgMatches) {
+pub(crate) fn handle_preview(sub_matches: &ArgMatches) -> ExitCode {
     let key = sub_matches.get_one::<String>("KEY").unwrap();
-    let meme = get_meme(key).expect(format!("表情 `{key}` 不存在").as_str());
-    let result = meme.generate_preview(HashMap::new());
-    handle_result(result)
+    let options = OutputOptions::from_matches(sub_matches);
+    let result = find_meme(key).and_then(|meme| {
+        meme.generate_preview(HashMap::new())
+            .map_err(CliError::from)
+    });
+    finish(key, result, &options)
 }
 
-pub(crate) fn handle_generate(sub_matches: &ArgMatches) {
-    let (key, sub_matches) = sub_matches.subcommand().unwrap();
-    let meme = get_meme(key).expect(format!("表情 `{key}` 不存在").as_str());
-    let mut images = sub_matches
-        .get_many::<PathBuf>("images")
+fn read_images(sub_matches: &ArgMatches) -> Result<Vec<Image>, CliError> {
+    let sources = sub_matches
+        .get_many::<String>("images")
         .into_iter()
         .flatten()
-        .map(|path| {
-            let data = read(path).expect(format!("文件读取失败：{path:?}").as_str());
-            let name = if let Some(file_stem) = path.file_stem() {
-                file_stem.to_string_lossy().to_string()
-            } else {
-                "".to_string()
-            };
-            Image { name, data }
-        })
         .collect::<Vec<_>>();
+    if sources.iter().filter(|source| **source == STDIN).count() > 1 {
+        return Err(CliError::Input("只能有一张图片从标准输入读取".to_string()));
+    }
+    let mut images = sources
+        .into_iter()
+        .map(|source| {
+            read_image(source, Path::new(""))
+                .map_err(|err| CliError::Input(format!("{source}：{err}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
     let names = sub_matches
         .get_many::<String>("names")
         .into_iter()
-        .flatten()
-        .map(|text| text.to_string())
-        .collect::<Vec<_>>();
-    for (i, name) in names.into_iter().enumerate() {
-        if i < images.len() {
-            images[i].name = name;
-        }
+        .flatten();
+    for (image, name) in images.iter_mut().zip(names) {
+        image.name = name.clone();
     }
+    Ok(images)
+}
+
+pub(crate) fn handle_generate(sub_matches: &ArgMatches) -> ExitCode {
+    let (key, sub_matches) = sub_matches.subcommand().unwrap();
+    let output_options = OutputOptions::from_matches(sub_matches);
+    let meme = match find_meme(key) {
+        Ok(meme) => meme,
+        Err(error) => return finish(key, Err(error), &output_options),
+    };
+    let images = match read_images(sub_matches) {
+        Ok(images) => images,
+        Err(error) => return finish(key, Err(error), &output_options),
+    };
     let texts = sub_matches
         .get_many::<String>("texts")
         .into_iter()
@@ -712,58 +967,38 @@ pub(crate) fn handle_generate(sub_matches: &ArgMatches) {
         }
     }
     let result = meme.generate(images, texts, options);
-    handle_result(result)
+    finish(key, result.map_err(CliError::from), &output_options)
 }
 
-fn handle_result(result: Result<Vec<u8>, Error>) {
-    match result {
-        Err(Error::ImageDecodeError(err)) => {
-            eprintln!("图片解码失败：{err}");
-        }
-        Err(Error::ImageEncodeError(err)) => {
-            eprintln!("图片编码失败：{err}");
-        }
-        Err(Error::ImageAssetMissing(path)) => {
-            eprintln!("图片资源缺失：{path}");
-        }
-        Err(Error::DeserializeError(err)) => {
-            eprintln!("反序列化失败：{err}");
-        }
-        Err(Error::ImageNumberMismatch(min, max, actual)) => {
-            let range = {
-                if min == max {
-                    min.to_string()
-                } else {
-                    format!("{min}~{max}")
-                }
-            };
-            eprintln!("图片数量不符，应为 {range}，实际传入 {actual}");
-        }
-        Err(Error::TextNumberMismatch(min, max, actual)) => {
-            let range = {
-                if min == max {
-                    min.to_string()
-                } else {
-                    format!("{min}~{max}")
-                }
-            };
-            eprintln!("文本数量不符，应为 {range}，实际传入 {actual}");
-        }
-        Err(Error::TextOverLength(text)) => {
-            eprintln!("文字过长：{text}");
+fn range_text(min: u8, max: u8) -> String {
+    if min == max {
+        min.to_string()
+    } else {
+        format!("{min}~{max}")
+    }
+}
+
+pub(crate) fn error_message(error: &Error) -> String {
+    match error {
+        Error::ImageDecodeError(err) => format!("图片解码失败：{err}"),
+        Error::ImageEncodeError(err) => format!("图片编码失败：{err}"),
+        Error::ImageAssetMissing(path) => format!("图片资源缺失：{path}"),
+        Error::ImageLimitExceeded(limit, max, actual) => {
+            format!("图片超出限制：{limit} 为 {actual}，最大为 {max}")
         }
-        Err(Error::MemeFeedback(feedback)) => {
-            eprintln!("{feedback}");
+        Error::DeserializeError(err) => format!("反序列化失败：{err}"),
+        Error::ImageNumberMismatch(min, max, actual) => {
+            let range = range_text(*min, *max);
+            format!("图片数量不符，应为 {range}，实际传入 {actual}")
         }
-        Ok(result) => {
-            let kind = infer::get(&result).unwrap();
-            let extension = kind.extension();
-            let filename_string = format!("result.{extension}");
-            let filename = filename_string.as_str();
-            write(filename, result).expect("图片保存失败");
-            println!("表情制作成功！生成的表情文件为 `{filename}`");
+        Error::TextNumberMismatch(min, max, actual) => {
+            let range = range_text(*min, *max);
+            format!("文本数量不符，应为 {range}，实际传入 {actual}")
         }
-    };
+        Error::TextOverLength(text) => format!("文字过长：{text}"),
+        Error::MemeFeedback(feedback) => feedback.clone(),
+        Error::InternalError(key, message) => format!("表情 `{key}` 内部错误：{message}"),
+    }
 }
 
 pub(crate) fn handle_download(sub_matches: &ArgMatches) {
@@ -771,16 +1006,44 @@ pub(crate) fn handle_download(sub_matches: &ArgMatches) {
     check_resources_sync(resource_url.cloned());
 }
 
-pub(crate) fn handle_tools(sub_matches: &ArgMatches) {
+pub(crate) fn handle_completions(sub_matches: &ArgMatches) {
+    let shell = *sub_matches.get_one::<Shell>("SHELL").unwrap();
+    let mut command = build_command();
+    generate(shell, &mut command, "meme", &mut io::stdout());
+}
+
+pub(crate) fn handle_man(sub_matches: &ArgMatches) -> ExitCode {
+    let command = build_command();
+    let result = match sub_matches.get_one::<PathBuf>("dir") {
+        Some(dir) => fs::create_dir_all(dir).and_then(|_| clap_mangen::generate_to(command, dir)),
+        None => Man::new(command).render(&mut io::stdout()),
+    };
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("生成手册失败：{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub(crate) fn handle_tools(sub_matches: &ArgMatches) -> ExitCode {
     match sub_matches.subcommand() {
         Some(("image", sub_matches)) => {
-            handle_image(sub_matches);
+            return handle_image(sub_matches);
         }
         Some(("gif", sub_matches)) => {
-            handle_gif(sub_matches);
+            return handle_gif(sub_matches);
+        }
+        Some(("render-list", sub_matches)) => {
+            return handle_render_list(sub_matches);
+        }
+        Some(("statistics", sub_matches)) => {
+            return handle_statistics(sub_matches);
         }
         _ => {}
     }
+    ExitCode::SUCCESS
 }
 
 #[cfg(feature = "server")]
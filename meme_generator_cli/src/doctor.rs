@@ -0,0 +1,307 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use clap::ArgMatches;
+use serde_json::{Value, json};
+
+use meme_generator::{
+    doctor::{
+        FontReport, FontSource, LibraryStatus, SmokeReport, SmokeResult, effective_config,
+        font_report, get_libraries, paths, smoke_render,
+    },
+    get_meme_keys, read_config_file,
+    resources::{
+        ResourceProblem, ResourceVerification, verify_resources_sync, verify_resources_with_file,
+    },
+};
+
+use crate::cli::error_message;
+
+/// 目录中的文件数量，目录不存在时返回 `None`
+fn file_count(dir: &Path) -> Option<usize> {
+    fs::read_dir(dir).ok().map(|entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .count()
+    })
+}
+
+fn dir_text(dir: &Path) -> String {
+    match file_count(dir) {
+        Some(count) => format!("{}（{count} 个文件）", dir.display()),
+        None => format!("{}（不存在）", dir.display()),
+    }
+}
+
+fn source_text(source: FontSource) -> &'static str {
+    match source {
+        FontSource::Local => "本地字体",
+        FontSource::System => "系统字体",
+        FontSource::Missing => "未找到，将回退到其他字体",
+    }
+}
+
+fn problem_text(problem: ResourceProblem) -> &'static str {
+    match problem {
+        ResourceProblem::Missing => "缺失",
+        ResourceProblem::Corrupt => "哈希值不一致",
+    }
+}
+
+fn print_paths() {
+    let paths = paths();
+    println!("== 路径 ==");
+    println!("MEME_HOME：{}", paths.meme_home.display());
+    let config_state = if paths.config_file.exists() {
+        ""
+    } else {
+        "（不存在）"
+    };
+    println!("配置文件：{}{config_state}", paths.config_file.display());
+    println!("字体目录：{}", dir_text(&paths.fonts_dir));
+    println!("图片目录：{}", dir_text(&paths.images_dir));
+    println!("外部表情库目录：{}", dir_text(&paths.libraries_dir));
+    println!();
+}
+
+/// 配置文件解析失败时会静默使用默认配置，这里单独检查
+fn config_error() -> Option<String> {
+    let content = read_config_file();
+    toml::from_str::<toml::Table>(&content)
+        .err()
+        .map(|err| err.to_string())
+}
+
+fn print_config(config_error: &Option<String>) {
+    println!("== 生效的配置 ==");
+    if let Some(err) = config_error {
+        println!("配置文件解析失败，已使用默认配置：{err}");
+    }
+    println!("{}", effective_config().trim_end());
+    println!();
+}
+
+fn print_libraries(libraries: &[LibraryStatus]) {
+    println!("== 外部表情库 ==");
+    if libraries.is_empty() {
+        println!(
+            "未加载外部表情库（需开启 `meme.load_external_memes` 并将库文件放入外部表情库目录）"
+        );
+    }
+    for library in libraries {
+        let name = library
+            .file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match &library.error {
+            Some(err) => println!("[失败] {name}：{err}"),
+            None => println!("[正常] {name}：{} 个表情", library.memes),
+        }
+        for warning in &library.warnings {
+            println!("       警告：{warning}");
+        }
+    }
+    println!();
+}
+
+fn print_resources(verification: &Result<ResourceVerification, String>) {
+    println!("== 资源文件 ==");
+    match verification {
+        Ok(verification) => {
+            println!(
+                "检查了 {} 个文件，{} 个有问题",
+                verification.checked,
+                verification.problems.len()
+            );
+            for (file, problem) in &verification.problems {
+                println!("  {file}：{}", problem_text(*problem));
+            }
+            if !verification.problems.is_empty() {
+                println!("可运行 `meme download` 重新下载资源");
+            }
+        }
+        Err(err) => println!("无法获取资源列表：{err}"),
+    }
+    println!();
+}
+
+fn print_smoke_results(results: &[SmokeResult]) {
+    println!("== 表情预览 ==");
+    let failed = results
+        .iter()
+        .filter(|result| result.result.is_err())
+        .collect::<Vec<_>>();
+    println!(
+        "制作了 {} 个表情的预览，{} 个失败",
+        results.len(),
+        failed.len()
+    );
+    for result in &failed {
+        if let Err(err) = &result.result {
+            println!("  {}：{}", result.key, error_message(err));
+        }
+    }
+    println!();
+}
+
+fn print_fonts(report: &FontReport, rendered: bool) {
+    println!("== 字体 ==");
+    if report.use_local_fonts {
+        println!("加载的本地字体（{} 个）：", report.local_fonts.len());
+        for font in &report.local_fonts {
+            let file = font
+                .file
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            println!("  {}（{file}）", font.family);
+        }
+        if !report.failed_files.is_empty() {
+            println!("无法加载的字体文件：");
+            for file in &report.failed_files {
+                println!("  {}", file.display());
+            }
+        }
+    } else {
+        println!("未使用本地字体（`font.use_local_fonts = false`）");
+    }
+    if rendered {
+        println!("默认字体和表情使用的字体：");
+    } else {
+        println!("默认字体（跳过了表情预览，未统计表情使用的字体）：");
+    }
+    for (family, source) in &report.families {
+        println!("  {family}：{}", source_text(*source));
+    }
+    println!();
+}
+
+struct Diagnosis {
+    config_error: Option<String>,
+    libraries: Vec<LibraryStatus>,
+    verification: Result<ResourceVerification, String>,
+    smoke_results: Vec<SmokeResult>,
+    fonts: FontReport,
+}
+
+impl Diagnosis {
+    fn problems(&self) -> usize {
+        let resource_problems = match &self.verification {
+            Ok(verification) => verification.problems.len(),
+            Err(_) => 1,
+        };
+        self.config_error.iter().count()
+            + self
+                .libraries
+                .iter()
+                .filter(|library| library.error.is_some())
+                .count()
+            + resource_problems
+            + self
+                .smoke_results
+                .iter()
+                .filter(|result| result.result.is_err())
+                .count()
+            + self.fonts.failed_files.len()
+            + self
+                .fonts
+                .families
+                .iter()
+                .filter(|(_, source)| *source == FontSource::Missing)
+                .count()
+    }
+
+    fn to_json(&self) -> Value {
+        let resources = match &self.verification {
+            Ok(verification) => json!(verification),
+            Err(err) => json!({ "error": err }),
+        };
+        let failed_memes = self
+            .smoke_results
+            .iter()
+            .filter_map(|result| {
+                let err = result.result.as_ref().err()?;
+                Some(json!({ "key": result.key, "error": error_message(err) }))
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "paths": paths(),
+            "config": effective_config(),
+            "config_error": self.config_error,
+            "libraries": self.libraries,
+            "resources": resources,
+            "rendered_memes": self.smoke_results.len(),
+            "failed_memes": failed_memes,
+            "fonts": self.fonts,
+            "problems": self.problems(),
+        })
+    }
+}
+
+/// 逐个制作表情预览，在标准错误中显示进度
+fn run_smoke_render(concurrency: usize) -> SmokeReport {
+    let total = get_meme_keys().len();
+    let done = AtomicUsize::new(0);
+    let report = smoke_render(concurrency, |_| {
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        eprint!("\r正在制作表情预览 {done}/{total}");
+    });
+    eprintln!();
+    report
+}
+
+/// 发现问题时退出码为 1
+pub(crate) fn handle_doctor(sub_matches: &ArgMatches) -> ExitCode {
+    let render = !sub_matches.get_flag("no-render");
+    let concurrency = sub_matches.get_one::<usize>("jobs").copied().unwrap_or(0);
+
+    eprintln!("正在检查资源文件…");
+    let verification = match sub_matches.get_one::<PathBuf>("resources") {
+        Some(path) => verify_resources_with_file(path),
+        None => verify_resources_sync(None),
+    };
+    let (smoke_results, fonts) = if render {
+        let report = run_smoke_render(concurrency);
+        (report.results, report.fonts)
+    } else {
+        (Vec::new(), font_report())
+    };
+    let diagnosis = Diagnosis {
+        config_error: config_error(),
+        libraries: get_libraries(),
+        verification,
+        smoke_results,
+        fonts,
+    };
+    let problems = diagnosis.problems();
+
+    if sub_matches.get_flag("json") {
+        println!("{}", diagnosis.to_json());
+    } else {
+        print_paths();
+        print_config(&diagnosis.config_error);
+        print_libraries(&diagnosis.libraries);
+        print_resources(&diagnosis.verification);
+        if render {
+            print_smoke_results(&diagnosis.smoke_results);
+        }
+        print_fonts(&diagnosis.fonts, render);
+        if problems == 0 {
+            println!("未发现问题");
+        } else {
+            println!("共发现 {problems} 个问题");
+        }
+    }
+
+    if problems == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,202 @@
+use std::{collections::HashMap, env, fmt::Write};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use skia_safe::{
+    AlphaType, ColorType, Data, FilterMode, Image, ImageInfo, MipmapMode, Pixmap, SamplingOptions,
+    image::CachingHint,
+};
+
+/// 解码为 RGBA 像素的图片
+#[derive(Debug, Clone)]
+pub(crate) struct RgbaImage {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pixels: Vec<u8>,
+}
+
+/// 取图片的第一帧，等比缩小到不超过指定尺寸后解码为 RGBA 像素
+pub(crate) fn decode_rgba(data: &[u8], max_width: i32, max_height: i32) -> Option<RgbaImage> {
+    let image = Image::from_encoded(Data::new_copy(data))?;
+    let scale = (max_width as f32 / image.width() as f32)
+        .min(max_height as f32 / image.height() as f32)
+        .min(1.0);
+    let width = ((image.width() as f32 * scale) as i32).max(1);
+    let height = ((image.height() as f32 * scale) as i32).max(1);
+    let info = ImageInfo::new(
+        (width, height),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let row_bytes = info.min_row_bytes();
+    let mut pixels = vec![0u8; info.compute_min_byte_size()];
+    {
+        let pixmap = Pixmap::new(&info, &mut pixels, row_bytes)?;
+        let sampling = SamplingOptions::new(FilterMode::Linear, MipmapMode::Linear);
+        if !image.scale_pixels(&pixmap, sampling, CachingHint::Disallow) {
+            return None;
+        }
+    }
+    Some(RgbaImage {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+    })
+}
+
+/// 终端图形协议，用于在终端中直接显示图片
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GraphicsProtocol {
+    Kitty,
+    Sixel,
+}
+
+impl GraphicsProtocol {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "kitty" => Some(GraphicsProtocol::Kitty),
+            "sixel" => Some(GraphicsProtocol::Sixel),
+            _ => None,
+        }
+    }
+
+    /// 根据环境变量推断终端支持的图形协议，无法确定时返回 `None`
+    pub(crate) fn detect() -> Option<Self> {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let term_program = var("TERM_PROGRAM");
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || ["WezTerm", "ghostty"].contains(&term_program.as_str())
+        {
+            return Some(GraphicsProtocol::Kitty);
+        }
+        if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || term.starts_with("contour")
+            || term_program == "iTerm.app"
+            || env::var_os("WT_SESSION").is_some()
+        {
+            return Some(GraphicsProtocol::Sixel);
+        }
+        None
+    }
+
+    /// 在光标处显示图片的转义序列，显示后光标位置不变
+    pub(crate) fn encode(&self, image: &RgbaImage) -> String {
+        match self {
+            GraphicsProtocol::Kitty => kitty_sequence(image),
+            GraphicsProtocol::Sixel => sixel_sequence(image),
+        }
+    }
+
+    /// 清除已显示的图片的转义序列
+    ///
+    /// sixel 图片直接画在字符上，需要重绘界面才能清除，因此返回空字符串
+    pub(crate) fn clear_sequence(&self) -> &'static str {
+        match self {
+            GraphicsProtocol::Kitty => "\x1b_Ga=d,d=A,q=2\x1b\\",
+            GraphicsProtocol::Sixel => "",
+        }
+    }
+}
+
+/// kitty 图形协议，直接传输 RGBA 像素，每段不超过 4096 字节
+fn kitty_sequence(image: &RgbaImage) -> String {
+    let encoded = STANDARD.encode(&image.pixels);
+    let chunks = encoded.as_bytes().chunks(4096).collect::<Vec<_>>();
+    let mut output = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = if index + 1 < chunks.len() { 1 } else { 0 };
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        if index == 0 {
+            let _ = write!(
+                output,
+                "\x1b_Ga=T,f=32,s={},v={},C=1,q=2,m={more};{chunk}\x1b\\",
+                image.width, image.height
+            );
+        } else {
+            let _ = write!(output, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    output
+}
+
+/// 颜色量化为 6×6×6 的调色板，透明度低于一半的像素视为透明
+fn palette_index(pixel: &[u8]) -> Option<u8> {
+    if pixel[3] < 128 {
+        return None;
+    }
+    let level = |value: u8| (value as u16 * 5 + 127) / 255;
+    Some((level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])) as u8)
+}
+
+fn push_sixel_run(output: &mut String, sixel: u8, count: usize) {
+    let char = (63 + sixel) as char;
+    if count > 3 {
+        let _ = write!(output, "!{count}{char}");
+    } else {
+        for _ in 0..count {
+            output.push(char);
+        }
+    }
+}
+
+/// sixel 图形协议，每 6 行像素为一个条带，条带内逐个颜色输出
+fn sixel_sequence(image: &RgbaImage) -> String {
+    let width = image.width as usize;
+    let height = image.height as usize;
+    let indexes = image
+        .pixels
+        .chunks_exact(4)
+        .map(palette_index)
+        .collect::<Vec<_>>();
+
+    let mut output = String::from("\x1bP0;1;0q");
+    let _ = write!(output, "\"1;1;{width};{height}");
+    for index in 0..216u16 {
+        let percent = |level: u16| level * 100 / 5;
+        let _ = write!(
+            output,
+            "#{index};2;{};{};{}",
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        );
+    }
+
+    for top in (0..height).step_by(6) {
+        let rows = (height - top).min(6);
+        let mut bands: HashMap<u8, Vec<u8>> = HashMap::new();
+        for dy in 0..rows {
+            for x in 0..width {
+                if let Some(color) = indexes[(top + dy) * width + x] {
+                    bands.entry(color).or_insert_with(|| vec![0; width])[x] |= 1 << dy;
+                }
+            }
+        }
+        let mut colors = bands.keys().copied().collect::<Vec<_>>();
+        colors.sort();
+        for color in colors {
+            let sixels = &bands[&color];
+            let _ = write!(output, "#{color}");
+            let mut run = (sixels[0], 0);
+            for &sixel in sixels {
+                if sixel == run.0 {
+                    run.1 += 1;
+                } else {
+                    push_sixel_run(&mut output, run.0, run.1);
+                    run = (sixel, 1);
+                }
+            }
+            push_sixel_run(&mut output, run.0, run.1);
+            output.push('$');
+        }
+        output.push('-');
+    }
+    output.push_str("\x1b\\");
+    output
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use serde::de::DeserializeOwned;
+
+use meme_generator::meme::Image;
+
+pub(crate) const STDIN: &str = "-";
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map(|data| data.to_vec())
+        .map_err(|err| format!("图片下载失败：{err}"))
+}
+
+/// 图片名取文件名去掉扩展名的部分
+fn file_stem(name: &str) -> String {
+    Path::new(name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// 读取图片，`source` 可以是文件路径、http(s) 链接，或 `-` 表示标准输入
+///
+/// 相对路径相对于 `base_dir`
+pub(crate) fn read_image(source: &str, base_dir: &Path) -> Result<Image, String> {
+    if source == STDIN {
+        let mut data = Vec::new();
+        io::stdin()
+            .lock()
+            .read_to_end(&mut data)
+            .map_err(|err| format!("标准输入读取失败：{err}"))?;
+        return Ok(Image {
+            name: String::new(),
+            data,
+        });
+    }
+    if is_url(source) {
+        let data = download(source)?;
+        let name = source
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .map(file_stem)
+            .unwrap_or_default();
+        return Ok(Image { name, data });
+    }
+    let path = base_dir.join(source);
+    let data = fs::read(&path).map_err(|err| format!("文件读取失败：{err}"))?;
+    Ok(Image {
+        name: file_stem(source),
+        data,
+    })
+}
+
+/// 读取 json 或 toml 文件，按扩展名区分格式，其他扩展名按 toml 解析
+pub(crate) fn read_data_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!("文件读取失败：{err}"))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if is_json {
+        serde_json::from_str(&content).map_err(|err| format!("文件解析失败：{err}"))
+    } else {
+        toml::from_str(&content).map_err(|err| format!("文件解析失败：{err}"))
+    }
+}
@@ -1,18 +1,33 @@
+mod batch;
+mod browse;
 mod cli;
+mod doctor;
+mod graphics;
+mod input;
+mod output;
+mod scaffold;
+mod test_render;
 mod tools;
 
+use std::{io, process::ExitCode};
+
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
+use batch::handle_batch;
+use browse::handle_browse;
 #[cfg(feature = "server")]
 use cli::handle_run;
 use cli::{
-    build_command, handle_download, handle_generate, handle_info, handle_list, handle_preview,
-    handle_search, handle_tools,
+    build_command, handle_completions, handle_download, handle_generate, handle_info, handle_list,
+    handle_man, handle_preview, handle_search, handle_tools,
 };
+use doctor::handle_doctor;
+use scaffold::{handle_new, handle_update_resources};
+use test_render::handle_test_render;
 
-fn main() {
+fn main() -> ExitCode {
     tracing_subscriber::registry()
-        .with(fmt::layer())
+        .with(fmt::layer().with_writer(io::stderr))
         .with(EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("info")))
         .init();
 
@@ -23,22 +38,46 @@ fn main() {
             handle_list();
         }
         Some(("info", sub_matches)) => {
-            handle_info(sub_matches);
+            return handle_info(sub_matches);
         }
         Some(("search", sub_matches)) => {
             handle_search(sub_matches);
         }
+        Some(("browse", sub_matches)) => {
+            return handle_browse(sub_matches);
+        }
         Some(("preview", sub_matches)) => {
-            handle_preview(sub_matches);
+            return handle_preview(sub_matches);
         }
         Some(("generate", sub_matches)) => {
-            handle_generate(sub_matches);
+            return handle_generate(sub_matches);
+        }
+        Some(("batch", sub_matches)) => {
+            return handle_batch(sub_matches);
         }
         Some(("download", sub_matches)) => {
             handle_download(sub_matches);
         }
+        Some(("doctor", sub_matches)) => {
+            return handle_doctor(sub_matches);
+        }
+        Some(("test-render", sub_matches)) => {
+            return handle_test_render(sub_matches);
+        }
+        Some(("new", sub_matches)) => {
+            return handle_new(sub_matches);
+        }
+        Some(("update-resources", sub_matches)) => {
+            return handle_update_resources(sub_matches);
+        }
+        Some(("completions", sub_matches)) => {
+            handle_completions(sub_matches);
+        }
+        Some(("man", sub_matches)) => {
+            return handle_man(sub_matches);
+        }
         Some(("tools", sub_matches)) => {
-            handle_tools(sub_matches);
+            return handle_tools(sub_matches);
         }
         #[cfg(feature = "server")]
         Some(("run", sub_matches)) => {
@@ -46,4 +85,5 @@ fn main() {
         }
         _ => {}
     }
+    ExitCode::SUCCESS
 }
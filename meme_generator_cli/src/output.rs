@@ -0,0 +1,230 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+use clap::{Arg, ArgAction, ArgMatches, builder::PossibleValuesParser};
+use serde_json::{Value, json};
+
+use meme_generator::{
+    error::Error,
+    tools::image_operations::{ImageFormat, convert_format, inspect},
+};
+
+use crate::{cli::error_message, input::STDIN};
+
+/// 命令执行失败的原因，决定进程的退出码：
+///
+/// - `1` 表情制作失败
+/// - `2` 输入有误，如图片读取失败
+/// - `3` 结果输出失败
+#[derive(Debug)]
+pub(crate) enum CliError {
+    Input(String),
+    Meme(Error),
+    Output(String),
+}
+
+impl CliError {
+    pub(crate) fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Meme(_) => ExitCode::from(1),
+            CliError::Input(_) => ExitCode::from(2),
+            CliError::Output(_) => ExitCode::from(3),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            CliError::Input(_) => "input",
+            CliError::Output(_) => "output",
+            CliError::Meme(error) => match error {
+                Error::ImageDecodeError(_) => "image_decode",
+                Error::ImageEncodeError(_) => "image_encode",
+                Error::ImageAssetMissing(_) => "image_asset_missing",
+                Error::ImageLimitExceeded(..) => "image_limit_exceeded",
+                Error::DeserializeError(_) => "deserialize",
+                Error::ImageNumberMismatch(..) => "image_number_mismatch",
+                Error::TextNumberMismatch(..) => "text_number_mismatch",
+                Error::TextOverLength(_) => "text_over_length",
+                Error::MemeFeedback(_) => "meme_feedback",
+                Error::InternalError(..) => "internal",
+            },
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Input(message) | CliError::Output(message) => write!(f, "{message}"),
+            CliError::Meme(error) => write!(f, "{}", error_message(error)),
+        }
+    }
+}
+
+impl From<Error> for CliError {
+    fn from(error: Error) -> Self {
+        CliError::Meme(error)
+    }
+}
+
+pub(crate) fn output_args() -> [Arg; 3] {
+    [
+        Arg::new("output")
+            .long("output")
+            .value_name("PATH")
+            .help("输出文件路径，`-` 表示输出到标准输出，默认为 result.<扩展名>"),
+        Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .help("输出格式，默认根据输出文件的扩展名确定")
+            .value_parser(PossibleValuesParser::new(["png", "jpg", "webp", "gif"])),
+        Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help("以 JSON 格式输出结果信息和错误"),
+    ]
+}
+
+fn format_from_name(name: &str) -> Option<ImageFormat> {
+    match name.to_ascii_lowercase().as_str() {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "webp" => Some(ImageFormat::Webp),
+        "gif" => Some(ImageFormat::Gif),
+        _ => None,
+    }
+}
+
+pub(crate) struct OutputOptions {
+    output: Option<String>,
+    format: Option<ImageFormat>,
+    json: bool,
+}
+
+impl OutputOptions {
+    pub(crate) fn from_matches(matches: &ArgMatches) -> Self {
+        let output = matches.get_one::<String>("output").cloned();
+        let format = matches
+            .get_one::<String>("format")
+            .and_then(|format| format_from_name(format))
+            .or_else(|| {
+                let output = output.as_deref().filter(|output| *output != STDIN)?;
+                let extension = Path::new(output).extension()?;
+                format_from_name(&extension.to_string_lossy())
+            });
+        Self {
+            output,
+            format,
+            json: matches.get_flag("json"),
+        }
+    }
+
+    fn is_stdout(&self) -> bool {
+        self.output.as_deref() == Some(STDIN)
+    }
+
+    /// 结果信息输出到标准输出，图片占用标准输出时改为标准错误
+    fn print(&self, message: &str) {
+        if self.is_stdout() {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    }
+}
+
+fn write_image(data: &[u8], options: &OutputOptions) -> Result<String, CliError> {
+    if options.is_stdout() {
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(data)
+            .and_then(|_| stdout.flush())
+            .map_err(|err| CliError::Output(format!("写入标准输出失败：{err}")))?;
+        return Ok(STDIN.to_string());
+    }
+    let path = match &options.output {
+        Some(output) => output.clone(),
+        None => {
+            let extension = infer::get(data)
+                .map(|kind| kind.extension())
+                .unwrap_or("png");
+            format!("result.{extension}")
+        }
+    };
+    fs::write(&path, data).map_err(|err| CliError::Output(format!("图片保存失败：{err}")))?;
+    Ok(path)
+}
+
+fn save_result(key: &str, data: Vec<u8>, options: &OutputOptions) -> Result<Value, CliError> {
+    let data = match options.format {
+        Some(format) => convert_format(data, format)?,
+        None => data,
+    };
+    let info = inspect(data.clone())?;
+    let output = write_image(&data, options)?;
+    Ok(json!({
+        "key": key,
+        "output": output,
+        "mime_type": infer::get(&data).map(|kind| kind.mime_type()),
+        "size": data.len(),
+        "width": info.width,
+        "height": info.height,
+        "frame_count": info.frame_count.unwrap_or(1),
+    }))
+}
+
+/// 输出制作结果或错误信息，返回进程退出码
+pub(crate) fn finish(
+    key: &str,
+    result: Result<Vec<u8>, CliError>,
+    options: &OutputOptions,
+) -> ExitCode {
+    report(
+        key,
+        result.and_then(|data| save_result(key, data, options)),
+        options.json,
+        |message| options.print(message),
+        |metadata| {
+            let output = metadata["output"].as_str().unwrap_or_default();
+            (output != STDIN).then(|| format!("表情制作成功！生成的表情文件为 `{output}`"))
+        },
+    )
+}
+
+/// 用 `print` 输出结果信息或错误信息，返回进程退出码
+///
+/// 不是 JSON 格式时，结果信息为 `message` 的返回值，错误信息输出到标准错误
+pub(crate) fn report(
+    key: &str,
+    result: Result<Value, CliError>,
+    json: bool,
+    print: impl Fn(&str),
+    message: impl FnOnce(&Value) -> Option<String>,
+) -> ExitCode {
+    match result {
+        Ok(metadata) => {
+            if json {
+                print(&metadata.to_string());
+            } else if let Some(message) = message(&metadata) {
+                print(&message);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            if json {
+                let message = json!({
+                    "key": key,
+                    "error": { "kind": error.kind(), "message": error.to_string() },
+                });
+                print(&message.to_string());
+            } else {
+                eprintln!("{error}");
+            }
+            error.exit_code()
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use chrono::{Datelike, Local};
+use clap::ArgMatches;
+
+use meme_generator::resources::update_resource_list;
+
+const STATIC_TEMPLATE: &str = include_str!("../templates/static.rs.tmpl");
+const GIF_TEMPLATE: &str = include_str!("../templates/gif.rs.tmpl");
+const TEXT_TEMPLATE: &str = include_str!("../templates/text.rs.tmpl");
+
+/// 没有提供帧图片时 GIF 表情的默认帧数
+const DEFAULT_FRAME_NUM: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemeKind {
+    /// 静态图片，输入 GIF 时逐帧处理
+    Static,
+    /// 固定帧数的 GIF
+    Gif,
+    /// 纯文字
+    Text,
+}
+
+impl MemeKind {
+    const ALL: [MemeKind; 3] = [MemeKind::Static, MemeKind::Gif, MemeKind::Text];
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "static" => Some(MemeKind::Static),
+            "gif" => Some(MemeKind::Gif),
+            "text" => Some(MemeKind::Text),
+            _ => None,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            MemeKind::Static => "静态图片（输入 GIF 时逐帧处理）",
+            MemeKind::Gif => "固定帧数的 GIF",
+            MemeKind::Text => "纯文字",
+        }
+    }
+
+    /// 是否使用资源目录中的底图
+    fn uses_images(&self) -> bool {
+        !matches!(self, MemeKind::Text)
+    }
+
+    fn template(&self) -> &'static str {
+        match self {
+            MemeKind::Static => STATIC_TEMPLATE,
+            MemeKind::Gif => GIF_TEMPLATE,
+            MemeKind::Text => TEXT_TEMPLATE,
+        }
+    }
+}
+
+/// 仓库中的相关路径
+struct Repo {
+    memes_file: PathBuf,
+    memes_dir: PathBuf,
+    resources_dir: PathBuf,
+}
+
+impl Repo {
+    fn new(root: &Path) -> Result<Self, String> {
+        let memes_file = root.join("meme_generator_memes/src/memes.rs");
+        if !memes_file.exists() {
+            return Err(format!(
+                "未找到 {}，请在仓库根目录运行或使用 `--root` 指定仓库目录",
+                memes_file.display()
+            ));
+        }
+        Ok(Self {
+            memes_file,
+            memes_dir: root.join("meme_generator_memes/src/memes"),
+            resources_dir: root.join("resources"),
+        })
+    }
+
+    fn module_file(&self, key: &str) -> PathBuf {
+        self.memes_dir.join(format!("{key}.rs"))
+    }
+
+    fn images_dir(&self, key: &str) -> PathBuf {
+        self.resources_dir.join("images").join(key)
+    }
+}
+
+/// 表情名会用作模块名和函数名，只允许小写字母、数字和下划线
+fn check_key(key: &str) -> Result<(), String> {
+    let valid = key.starts_with(|c: char| c.is_ascii_lowercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "表情名 `{key}` 无效，只能包含小写字母、数字和下划线，且以字母开头"
+        ))
+    }
+}
+
+fn prompt(message: &str) -> Result<String, String> {
+    print!("{message}");
+    io::stdout().flush().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    Ok(line.trim().to_string())
+}
+
+fn ask_kind() -> Result<MemeKind, String> {
+    if !io::stdin().is_terminal() {
+        return Err("未指定表情类型，请使用 `--kind` 指定".to_string());
+    }
+    println!("表情类型：");
+    for (index, kind) in MemeKind::ALL.iter().enumerate() {
+        println!("  {}. {}", index + 1, kind.description());
+    }
+    loop {
+        let answer = prompt("请选择 [1-3]：")?;
+        if let Some(kind) = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| MemeKind::ALL.get(index.wrapping_sub(1)))
+        {
+            return Ok(*kind);
+        }
+    }
+}
+
+fn ask_keywords(key: &str) -> Result<Vec<String>, String> {
+    if !io::stdin().is_terminal() {
+        return Ok(vec![key.to_string()]);
+    }
+    let answer = prompt(&format!("关键词，多个关键词用空格分隔 [{key}]："))?;
+    if answer.is_empty() {
+        return Ok(vec![key.to_string()]);
+    }
+    Ok(answer.split_whitespace().map(|s| s.to_string()).collect())
+}
+
+/// 按字母顺序在 `memes.rs` 中插入模块声明，返回修改前的内容
+fn add_mod_declaration(memes_file: &Path, key: &str) -> Result<String, String> {
+    let content = fs::read_to_string(memes_file)
+        .map_err(|e| format!("读取 {} 失败：{e}", memes_file.display()))?;
+    let declaration = format!("mod {key};");
+    let mut lines = content.lines().collect::<Vec<_>>();
+    if lines.contains(&declaration.as_str()) {
+        return Ok(content);
+    }
+    let index = lines
+        .iter()
+        .position(|line| {
+            line.strip_prefix("mod ")
+                .and_then(|name| name.strip_suffix(';'))
+                .is_some_and(|name| name > key)
+        })
+        .unwrap_or(lines.len());
+    lines.insert(index, &declaration);
+    fs::write(memes_file, lines.join("\n") + "\n")
+        .map_err(|e| format!("写入 {} 失败：{e}", memes_file.display()))?;
+    Ok(content)
+}
+
+/// 帧图片的扩展名，没有帧图片时为 `png`
+fn frames_extension(frames: &[PathBuf]) -> Result<String, String> {
+    let extension = frames
+        .first()
+        .and_then(|path| path.extension())
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or("png".to_string());
+    for frame in frames {
+        let current = frame
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        if current.as_deref() != Some(extension.as_str()) {
+            return Err(format!(
+                "帧图片的格式需一致，{} 不是 {extension} 文件",
+                frame.display()
+            ));
+        }
+    }
+    Ok(extension)
+}
+
+/// 创建资源目录并将帧图片复制进去，依次命名为 `0.png`、`1.png`…
+fn copy_frames(frames: &[PathBuf], images_dir: &Path, extension: &str) -> Result<(), String> {
+    fs::create_dir_all(images_dir)
+        .map_err(|e| format!("创建 {} 失败：{e}", images_dir.display()))?;
+    for (index, frame) in frames.iter().enumerate() {
+        let target = images_dir.join(format!("{index}.{extension}"));
+        fs::copy(frame, &target).map_err(|e| format!("复制 {} 失败：{e}", frame.display()))?;
+    }
+    Ok(())
+}
+
+/// 创建表情时写入的文件，出错时据此撤销
+#[derive(Default)]
+struct Created {
+    images_dir: Option<PathBuf>,
+    module_file: Option<PathBuf>,
+    /// `memes.rs` 的路径和修改前的内容
+    memes_file: Option<(PathBuf, String)>,
+}
+
+impl Created {
+    fn rollback(self) {
+        if let Some(images_dir) = self.images_dir {
+            let _ = fs::remove_dir_all(images_dir);
+        }
+        if let Some(module_file) = self.module_file {
+            let _ = fs::remove_file(module_file);
+        }
+        if let Some((memes_file, content)) = self.memes_file {
+            let _ = fs::write(memes_file, content);
+        }
+    }
+}
+
+/// 写入资源、模块和模块声明，返回 `resources.json` 记录的文件数量
+fn create_files(
+    repo: &Repo,
+    key: &str,
+    frames: &[PathBuf],
+    extension: &str,
+    content: String,
+    created: &mut Created,
+) -> Result<usize, String> {
+    if !frames.is_empty() {
+        let images_dir = repo.images_dir(key);
+        created.images_dir = Some(images_dir.clone());
+        copy_frames(frames, &images_dir, extension)?;
+    }
+    let module_file = repo.module_file(key);
+    created.module_file = Some(module_file.clone());
+    fs::write(&module_file, content)
+        .map_err(|e| format!("写入 {} 失败：{e}", module_file.display()))?;
+    // 表情名较长时函数签名会超出行宽，有 rustfmt 时顺便格式化
+    let _ = Command::new("rustfmt")
+        .args(["--edition", "2024"])
+        .arg(&module_file)
+        .status();
+    let original = add_mod_declaration(&repo.memes_file, key)?;
+    created.memes_file = Some((repo.memes_file.clone(), original));
+    update_resource_list(&repo.resources_dir)
+}
+
+fn render_template(
+    kind: MemeKind,
+    key: &str,
+    keywords: &[String],
+    extension: &str,
+    frame_num: usize,
+) -> String {
+    let quote = |s: &String| format!("{s:?}");
+    let today = Local::now();
+    let date = format!("{}, {}, {}", today.year(), today.month(), today.day());
+    kind.template()
+        .replace("{{key}}", key)
+        .replace(
+            "{{keywords}}",
+            &keywords.iter().map(quote).collect::<Vec<_>>().join(", "),
+        )
+        .replace("{{default_text}}", &quote(&keywords[0]))
+        .replace("{{ext}}", extension)
+        .replace("{{frame_num}}", &frame_num.to_string())
+        .replace("{{date}}", &date)
+}
+
+fn new_meme(sub_matches: &ArgMatches) -> Result<(), String> {
+    let key = sub_matches.get_one::<String>("KEY").unwrap();
+    check_key(key)?;
+    let repo = Repo::new(sub_matches.get_one::<PathBuf>("root").unwrap())?;
+    let module_file = repo.module_file(key);
+    let images_dir = repo.images_dir(key);
+    if module_file.exists() {
+        return Err(format!("{} 已存在", module_file.display()));
+    }
+    if images_dir.exists() {
+        return Err(format!("{} 已存在", images_dir.display()));
+    }
+
+    let kind = match sub_matches.get_one::<String>("kind") {
+        Some(name) => MemeKind::from_name(name).unwrap(),
+        None => ask_kind()?,
+    };
+    let keywords = match sub_matches.get_many::<String>("keywords") {
+        Some(keywords) => keywords.cloned().collect(),
+        None => ask_keywords(key)?,
+    };
+    let frames = sub_matches
+        .get_many::<PathBuf>("frames")
+        .map(|frames| frames.cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !kind.uses_images() && !frames.is_empty() {
+        return Err("纯文字表情不需要帧图片".to_string());
+    }
+    if kind != MemeKind::Gif && frames.len() > 1 {
+        return Err("只有 GIF 表情可以提供多张帧图片".to_string());
+    }
+
+    let extension = frames_extension(&frames)?;
+    let frame_num = if frames.is_empty() {
+        DEFAULT_FRAME_NUM
+    } else {
+        frames.len()
+    };
+    let content = render_template(kind, key, &keywords, &extension, frame_num);
+    let mut created = Created::default();
+    let count = match create_files(&repo, key, &frames, &extension, content, &mut created) {
+        Ok(count) => count,
+        Err(err) => {
+            created.rollback();
+            return Err(err);
+        }
+    };
+
+    println!("已创建表情 `{key}`（{}）", kind.description());
+    println!("  模块：{}", module_file.display());
+    if kind.uses_images() {
+        println!("  资源目录：{}", images_dir.display());
+    }
+    println!("  resources.json：已更新，共 {count} 个文件");
+    if kind.uses_images() && frames.is_empty() {
+        let names = match kind {
+            MemeKind::Gif => format!("0.{extension} ~ {}.{extension}", frame_num - 1),
+            _ => format!("0.{extension}"),
+        };
+        println!("请创建资源目录并放入图片 {names}，然后运行 `meme update-resources` 更新哈希值");
+    }
+    Ok(())
+}
+
+/// 在仓库中创建表情模块和资源目录，并更新 `resources.json`
+pub(crate) fn handle_new(sub_matches: &ArgMatches) -> ExitCode {
+    match new_meme(sub_matches) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// 重新计算资源文件的哈希值，写入 `resources.json`
+pub(crate) fn handle_update_resources(sub_matches: &ArgMatches) -> ExitCode {
+    let root = sub_matches.get_one::<PathBuf>("root").unwrap();
+    match update_resource_list(&root.join("resources")) {
+        Ok(count) => {
+            println!("已更新 resources.json，共 {count} 个文件");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mod_declaration_is_sorted_and_rolled_back() {
+        let dir = std::env::temp_dir().join(format!("meme-scaffold-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let memes_file = dir.join("memes.rs");
+        let content = "mod alpha;\nmod gamma;\n";
+        fs::write(&memes_file, content).unwrap();
+
+        assert_eq!(add_mod_declaration(&memes_file, "beta").unwrap(), content);
+        assert_eq!(
+            fs::read_to_string(&memes_file).unwrap(),
+            "mod alpha;\nmod beta;\nmod gamma;\n"
+        );
+
+        // 出错时恢复 `memes.rs` 并删除创建的文件
+        let module_file = dir.join("beta.rs");
+        let images_dir = dir.join("images/beta");
+        fs::write(&module_file, "").unwrap();
+        fs::create_dir_all(&images_dir).unwrap();
+        let created = Created {
+            images_dir: Some(images_dir.clone()),
+            module_file: Some(module_file.clone()),
+            memes_file: Some((memes_file.clone(), content.to_string())),
+        };
+        created.rollback();
+        assert_eq!(fs::read_to_string(&memes_file).unwrap(), content);
+        assert!(!module_file.exists());
+        assert!(!images_dir.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn frames_share_one_extension() {
+        assert_eq!(frames_extension(&[]).unwrap(), "png");
+        let frames = [PathBuf::from("0.JPG"), PathBuf::from("1.jpg")];
+        assert_eq!(frames_extension(&frames).unwrap(), "jpg");
+        let frames = [PathBuf::from("0.jpg"), PathBuf::from("1.png")];
+        assert!(frames_extension(&frames).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    path::PathBuf,
+    process::ExitCode,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use clap::ArgMatches;
+
+use meme_generator::regression::{
+    RegressionConfig, RenderStatus, RenderTestResult, Shape, run_render_tests,
+};
+
+use crate::{batch::print_table, cli::error_message};
+
+fn shape_text(shape: &Shape) -> String {
+    if shape.frames > 1 {
+        format!("{}×{}，{} 帧", shape.width, shape.height, shape.frames)
+    } else {
+        format!("{}×{}", shape.width, shape.height)
+    }
+}
+
+fn status_text(status: &RenderStatus) -> (&'static str, String) {
+    match status {
+        RenderStatus::Passed { diff_ratio } => {
+            ("通过", format!("不同像素 {:.3}%", diff_ratio * 100.0))
+        }
+        RenderStatus::Mismatch { diff_ratio } => {
+            ("不一致", format!("不同像素 {:.3}%", diff_ratio * 100.0))
+        }
+        RenderStatus::ShapeMismatch { expected, actual } => (
+            "不一致",
+            format!(
+                "参考图片为 {}，实际为 {}",
+                shape_text(expected),
+                shape_text(actual)
+            ),
+        ),
+        RenderStatus::Missing => ("缺少参考图片", String::new()),
+        RenderStatus::Blessed => ("已更新", String::new()),
+        RenderStatus::RenderFailed(err) => ("制作失败", error_message(err)),
+        RenderStatus::Error(err) => ("出错", err.clone()),
+    }
+}
+
+fn run_with_progress(config: &RegressionConfig) -> Vec<RenderTestResult> {
+    let done = AtomicUsize::new(0);
+    let results = run_render_tests(config, |_| {
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        eprint!("\r已测试 {done} 个表情");
+    });
+    eprintln!();
+    results
+}
+
+/// 有表情不一致、缺少参考图片或制作失败时退出码为 1
+pub(crate) fn handle_test_render(sub_matches: &ArgMatches) -> ExitCode {
+    let default = RegressionConfig::default();
+    let config = RegressionConfig {
+        golden_dir: sub_matches.get_one::<PathBuf>("golden").unwrap().clone(),
+        diff_dir: sub_matches.get_one::<PathBuf>("diff-dir").unwrap().clone(),
+        keys: sub_matches
+            .get_many::<String>("KEYS")
+            .map(|keys| keys.cloned().collect())
+            .unwrap_or_default(),
+        external_only: sub_matches.get_flag("external-only"),
+        seed: sub_matches
+            .get_one::<u64>("seed")
+            .copied()
+            .unwrap_or(default.seed),
+        threshold: sub_matches
+            .get_one::<f32>("threshold")
+            .copied()
+            .unwrap_or(default.threshold),
+        max_diff_ratio: sub_matches
+            .get_one::<f32>("max-diff")
+            .map(|percent| percent / 100.0)
+            .unwrap_or(default.max_diff_ratio),
+        bless: sub_matches.get_flag("bless"),
+        concurrency: sub_matches.get_one::<usize>("jobs").copied().unwrap_or(0),
+    };
+
+    let start = Instant::now();
+    let results = run_with_progress(&config);
+    let elapsed = start.elapsed();
+
+    // 只列出需要关注的表情，全部通过时不输出表格
+    let rows = results
+        .iter()
+        .filter(|result| !matches!(result.status, RenderStatus::Passed { .. }))
+        .map(|result| {
+            let (status, detail) = status_text(&result.status);
+            vec![
+                result.key.clone(),
+                status.to_string(),
+                format!("{:.2}s", result.duration.as_secs_f32()),
+                detail,
+            ]
+        })
+        .collect::<Vec<_>>();
+    if !rows.is_empty() {
+        println!();
+        print_table(&["表情名", "状态", "耗时", "详情"], &rows);
+    }
+
+    let count = |predicate: fn(&RenderStatus) -> bool| {
+        results
+            .iter()
+            .filter(|result| predicate(&result.status))
+            .count()
+    };
+    let passed = count(|status| matches!(status, RenderStatus::Passed { .. }));
+    let blessed = count(|status| matches!(status, RenderStatus::Blessed));
+    let failed = count(|status| !status.is_ok());
+    println!(
+        "\n共 {} 个表情，通过 {passed}，更新 {blessed}，失败 {failed}，用时 {:.2}s",
+        results.len(),
+        elapsed.as_secs_f32()
+    );
+    if failed > 0 && !config.bless {
+        println!(
+            "实际结果和差异图片已保存到 {}，确认无误后可使用 `--bless` 更新参考图片",
+            config.diff_dir.display()
+        );
+    }
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -1,241 +1,286 @@
 use std::{
-    fs::{create_dir, read, write},
+    collections::HashMap,
+    fs::{create_dir_all, read, read_to_string, write},
     path::{Path, PathBuf},
+    process::ExitCode,
 };
 
 use clap::ArgMatches;
+use serde_json::json;
 
 use meme_generator::{
+    MemeSortBy,
     error::Error,
-    tools::image_operations::{
-        crop, flip_horizontal, flip_vertical, gif_change_duration, gif_merge, gif_reverse,
-        gif_split, grayscale, inspect, invert, merge_horizontal, merge_vertical, resize, rotate,
+    tools::{
+        MemeProperties, MemeStatisticsType, RenderMemeListParams, RenderMemeStatisticsParams,
+        image_operations::{
+            crop, flip_horizontal, flip_vertical, gif_change_duration, gif_merge, gif_reverse,
+            gif_split, grayscale, inspect, invert, merge_horizontal, merge_vertical, resize,
+            rotate,
+        },
+        render_meme_list, render_meme_statistics,
     },
 };
 
-pub(crate) fn handle_image(sub_matches: &ArgMatches) {
+use crate::{
+    input::read_data_file,
+    output::{CliError, OutputOptions, finish, report},
+};
+
+pub(crate) fn handle_image(sub_matches: &ArgMatches) -> ExitCode {
     match sub_matches.subcommand() {
-        Some(("inspect", sub_matches)) => {
-            handle_image_inspect(sub_matches);
-        }
-        Some(("flip_h", sub_matches)) => {
-            handle_image_flip_h(sub_matches);
-        }
-        Some(("flip_v", sub_matches)) => {
-            handle_image_flip_v(sub_matches);
-        }
+        Some(("inspect", sub_matches)) => handle_image_inspect(sub_matches),
+        Some(("flip_h", sub_matches)) => handle_image_op("flip_h", sub_matches, flip_horizontal),
+        Some(("flip_v", sub_matches)) => handle_image_op("flip_v", sub_matches, flip_vertical),
         Some(("rotate", sub_matches)) => {
-            handle_image_rotate(sub_matches);
+            let angle = sub_matches.get_one::<f32>("degrees").cloned();
+            handle_image_op("rotate", sub_matches, |data| rotate(data, angle))
         }
         Some(("resize", sub_matches)) => {
-            handle_image_resize(sub_matches);
+            let width = sub_matches.get_one::<i32>("width").cloned();
+            let height = sub_matches.get_one::<i32>("height").cloned();
+            handle_image_op("resize", sub_matches, |data| resize(data, width, height))
         }
         Some(("crop", sub_matches)) => {
-            handle_image_crop(sub_matches);
-        }
-        Some(("grayscale", sub_matches)) => {
-            handle_image_grayscale(sub_matches);
-        }
-        Some(("invert", sub_matches)) => {
-            handle_image_invert(sub_matches);
+            let left = sub_matches.get_one::<i32>("left").cloned();
+            let top = sub_matches.get_one::<i32>("top").cloned();
+            let right = sub_matches.get_one::<i32>("right").cloned();
+            let bottom = sub_matches.get_one::<i32>("bottom").cloned();
+            handle_image_op("crop", sub_matches, |data| {
+                crop(data, left, top, right, bottom)
+            })
         }
+        Some(("grayscale", sub_matches)) => handle_image_op("grayscale", sub_matches, grayscale),
+        Some(("invert", sub_matches)) => handle_image_op("invert", sub_matches, invert),
         Some(("merge_h", sub_matches)) => {
-            handle_image_merge_h(sub_matches);
+            handle_images_op("merge_h", sub_matches, merge_horizontal)
         }
-        Some(("merge_v", sub_matches)) => {
-            handle_image_merge_v(sub_matches);
-        }
-        _ => {}
+        Some(("merge_v", sub_matches)) => handle_images_op("merge_v", sub_matches, merge_vertical),
+        _ => ExitCode::SUCCESS,
     }
 }
 
-pub(crate) fn handle_gif(sub_matches: &ArgMatches) {
+pub(crate) fn handle_gif(sub_matches: &ArgMatches) -> ExitCode {
     match sub_matches.subcommand() {
-        Some(("split", sub_matches)) => {
-            handle_gif_split(sub_matches);
-        }
+        Some(("split", sub_matches)) => handle_gif_split(sub_matches),
         Some(("merge", sub_matches)) => {
-            handle_gif_merge(sub_matches);
-        }
-        Some(("reverse", sub_matches)) => {
-            handle_gif_reverse(sub_matches);
+            let duration = sub_matches.get_one::<f32>("duration").cloned();
+            handle_images_op("merge", sub_matches, |images| gif_merge(images, duration))
         }
+        Some(("reverse", sub_matches)) => handle_image_op("reverse", sub_matches, gif_reverse),
         Some(("duration", sub_matches)) => {
-            handle_gif_duration(sub_matches);
+            let duration = *sub_matches.get_one::<f32>("DURATION").unwrap();
+            handle_image_op("duration", sub_matches, |data| {
+                gif_change_duration(data, duration)
+            })
         }
-        _ => {}
+        _ => ExitCode::SUCCESS,
     }
 }
 
-fn read_image(path: &PathBuf) -> Vec<u8> {
-    read(path).expect(format!("文件读取失败：{path:?}").as_str())
+fn read_image(path: &Path) -> Result<Vec<u8>, CliError> {
+    read(path).map_err(|err| CliError::Input(format!("{path:?}：文件读取失败：{err}")))
 }
 
-fn parse_image(sub_matches: &ArgMatches) -> Vec<u8> {
+fn parse_image(sub_matches: &ArgMatches) -> Result<Vec<u8>, CliError> {
     read_image(sub_matches.get_one::<PathBuf>("IMAGE").unwrap())
 }
 
-fn parse_images(sub_matches: &ArgMatches) -> Vec<Vec<u8>> {
+fn parse_images(sub_matches: &ArgMatches) -> Result<Vec<Vec<u8>>, CliError> {
     sub_matches
         .get_many::<PathBuf>("IMAGES")
         .into_iter()
         .flatten()
-        .map(|path| read_image(&PathBuf::from(path)))
+        .map(|path| read_image(path))
         .collect()
 }
 
-fn handle_result(result: Result<Vec<u8>, Error>) {
-    match result {
-        Err(Error::ImageDecodeError(err)) => {
-            eprintln!("图片解码失败：{err}");
-        }
-        Err(Error::ImageEncodeError(err)) => {
-            eprintln!("图片编码失败：{err}");
-        }
-        Ok(result) => {
-            let kind = infer::get(&result).unwrap();
-            let extension = kind.extension();
-            let filename_string = format!("result.{extension}");
-            let filename = filename_string.as_str();
-            write(filename, result).expect("图片保存失败");
-            println!("操作成功，生成的文件为 `{filename}`");
-        }
-        _ => {}
-    };
+/// 对单张图片进行操作，输出结果
+fn handle_image_op(
+    name: &str,
+    sub_matches: &ArgMatches,
+    op: impl FnOnce(Vec<u8>) -> Result<Vec<u8>, Error>,
+) -> ExitCode {
+    let result = parse_image(sub_matches).and_then(|data| op(data).map_err(CliError::from));
+    finish(name, result, &OutputOptions::from_matches(sub_matches))
 }
 
-fn handle_results(results: Result<Vec<Vec<u8>>, Error>) {
-    match results {
-        Err(Error::ImageDecodeError(err)) => {
-            eprintln!("图片解码失败：{err}");
-        }
-        Err(Error::ImageEncodeError(err)) => {
-            eprintln!("图片编码失败：{err}");
-        }
-        Ok(results) => {
-            let output_dir = Path::new("result");
-            if !output_dir.exists() {
-                create_dir(output_dir).expect("创建输出目录失败");
-            }
-            for (i, result) in results.iter().enumerate() {
-                let kind = infer::get(&result).unwrap();
-                let extension = kind.extension();
-                let filename_string = format!("result/{i}.{extension}");
-                let filename = filename_string.as_str();
-                write(filename, result).expect(&format!("图片 `{filename}` 保存失败"));
-            }
-            println!("操作成功，生成的文件保存在 `result` 目录中");
-        }
-        _ => {}
-    };
+/// 对多张图片进行操作，输出结果
+fn handle_images_op(
+    name: &str,
+    sub_matches: &ArgMatches,
+    op: impl FnOnce(Vec<Vec<u8>>) -> Result<Vec<u8>, Error>,
+) -> ExitCode {
+    let result = parse_images(sub_matches).and_then(|images| op(images).map_err(CliError::from));
+    finish(name, result, &OutputOptions::from_matches(sub_matches))
 }
 
-fn handle_image_inspect(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = inspect(data);
+fn handle_image_inspect(sub_matches: &ArgMatches) -> ExitCode {
+    let result = parse_image(sub_matches).and_then(|data| inspect(data).map_err(CliError::from));
     match result {
-        Err(Error::ImageDecodeError(err)) => {
-            eprintln!("图片解码失败：{err}");
-        }
-        Err(Error::ImageEncodeError(err)) => {
-            eprintln!("图片编码失败：{err}");
-        }
         Ok(result) => {
             println!("图片信息：");
             println!("宽度：{}", result.width);
             println!("高度：{}", result.height);
             if result.is_multi_frame {
-                println!("帧数：{}", result.frame_count.unwrap());
-                println!("平均帧间隔：{:.2} 秒", result.average_duration.unwrap());
+                println!("帧数：{}", result.frame_count.unwrap_or(1));
+                println!(
+                    "平均帧间隔：{:.2} 秒",
+                    result.average_duration.unwrap_or_default()
+                );
             }
+            ExitCode::SUCCESS
         }
-        _ => {}
-    };
-}
-
-fn handle_image_flip_h(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = flip_horizontal(data);
-    handle_result(result)
-}
-
-fn handle_image_flip_v(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = flip_vertical(data);
-    handle_result(result)
-}
-
-fn handle_image_rotate(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let angle = sub_matches.get_one::<f32>("degrees").cloned();
-    let result = rotate(data, angle);
-    handle_result(result)
-}
-
-fn handle_image_resize(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let width = sub_matches.get_one::<i32>("width").cloned();
-    let height = sub_matches.get_one::<i32>("height").cloned();
-    let result = resize(data, width, height);
-    handle_result(result)
-}
-
-fn handle_image_crop(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let left = sub_matches.get_one::<i32>("left").cloned();
-    let top = sub_matches.get_one::<i32>("top").cloned();
-    let right = sub_matches.get_one::<i32>("right").cloned();
-    let bottom = sub_matches.get_one::<i32>("bottom").cloned();
-    let result = crop(data, left, top, right, bottom);
-    handle_result(result)
-}
-
-fn handle_image_grayscale(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = grayscale(data);
-    handle_result(result)
-}
-
-fn handle_image_invert(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = invert(data);
-    handle_result(result)
+        Err(error) => {
+            eprintln!("{error}");
+            error.exit_code()
+        }
+    }
 }
 
-fn handle_image_merge_h(sub_matches: &ArgMatches) {
-    let images = parse_images(sub_matches);
-    let result = merge_horizontal(images);
-    handle_result(result)
+/// 把拆分得到的每一帧保存到输出目录中，返回保存的文件路径
+fn save_frames(frames: Vec<Vec<u8>>, output_dir: &Path) -> Result<Vec<String>, CliError> {
+    create_dir_all(output_dir)
+        .map_err(|err| CliError::Output(format!("创建输出目录失败：{err}")))?;
+    let mut paths = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let extension = infer::get(frame)
+            .map(|kind| kind.extension())
+            .unwrap_or("png");
+        let path = output_dir.join(format!("{i}.{extension}"));
+        write(&path, frame)
+            .map_err(|err| CliError::Output(format!("图片 {path:?} 保存失败：{err}")))?;
+        paths.push(path.to_string_lossy().into_owned());
+    }
+    Ok(paths)
 }
 
-fn handle_image_merge_v(sub_matches: &ArgMatches) {
-    let images = parse_images(sub_matches);
-    let result = merge_vertical(images);
-    handle_result(result)
+fn handle_gif_split(sub_matches: &ArgMatches) -> ExitCode {
+    let output_dir = sub_matches
+        .get_one::<PathBuf>("output-dir")
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("result"));
+    let result = parse_image(sub_matches)
+        .and_then(|data| gif_split(data).map_err(CliError::from))
+        .and_then(|frames| save_frames(frames, &output_dir))
+        .map(|paths| {
+            json!({
+                "key": "split",
+                "output": output_dir.to_string_lossy(),
+                "files": paths,
+            })
+        });
+    report(
+        "split",
+        result,
+        sub_matches.get_flag("json"),
+        |message| println!("{message}"),
+        |metadata| {
+            Some(format!(
+                "操作成功，生成的文件保存在 `{}` 目录中",
+                metadata["output"].as_str().unwrap_or_default()
+            ))
+        },
+    )
 }
 
-fn handle_gif_split(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = gif_split(data);
-    handle_results(result)
+pub(crate) fn handle_render_list(sub_matches: &ArgMatches) -> ExitCode {
+    let output_options = OutputOptions::from_matches(sub_matches);
+    let meme_properties = match sub_matches.get_one::<PathBuf>("properties") {
+        Some(path) => match read_data_file::<HashMap<String, MemeProperties>>(path) {
+            Ok(properties) => properties,
+            Err(err) => {
+                let error = CliError::Input(format!("{path:?}：{err}"));
+                return finish("render_list", Err(error), &output_options);
+            }
+        },
+        None => HashMap::new(),
+    };
+    let mut params = RenderMemeListParams {
+        meme_properties,
+        exclude_memes: sub_matches
+            .get_many::<String>("exclude")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        sort_reverse: sub_matches.get_flag("reverse"),
+        add_category_icon: !sub_matches.get_flag("no-category-icon"),
+        ..Default::default()
+    };
+    if let Some(sort_by) = sub_matches.get_one::<MemeSortBy>("sort-by") {
+        params.sort_by = sort_by.clone();
+    }
+    if let Some(text_template) = sub_matches.get_one::<String>("text-template") {
+        params.text_template = text_template.clone();
+    }
+    let result = render_meme_list(params);
+    finish(
+        "render_list",
+        result.map_err(CliError::from),
+        &output_options,
+    )
 }
 
-fn handle_gif_merge(sub_matches: &ArgMatches) {
-    let images = parse_images(sub_matches);
-    let duration = sub_matches.get_one::<f32>("duration").cloned();
-    let result = gif_merge(images, duration);
-    handle_result(result)
+/// 解析 csv 格式的统计数据，每行为 `名称,数量`，首行不是数据时视为表头
+fn parse_statistics_csv(content: &str) -> Result<Vec<(String, i32)>, String> {
+    let mut data = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((name, count)) = line.rsplit_once(',') else {
+            return Err(format!("第 {} 行格式错误：{line}", index + 1));
+        };
+        let name = name.trim().trim_matches('"').to_string();
+        match count.trim().trim_matches('"').parse::<i32>() {
+            Ok(count) => data.push((name, count)),
+            Err(_) if index == 0 => continue,
+            Err(_) => return Err(format!("第 {} 行数量不是整数：{line}", index + 1)),
+        }
+    }
+    Ok(data)
 }
 
-fn handle_gif_reverse(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let result = gif_reverse(data);
-    handle_result(result)
+fn read_statistics_data(path: &Path) -> Result<Vec<(String, i32)>, String> {
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if is_json {
+        read_data_file(path)
+    } else {
+        let content = read_to_string(path).map_err(|err| format!("文件读取失败：{err}"))?;
+        parse_statistics_csv(&content)
+    }
 }
 
-fn handle_gif_duration(sub_matches: &ArgMatches) {
-    let data = parse_image(sub_matches);
-    let duration = *sub_matches.get_one::<f32>("DURATION").unwrap();
-    let result = gif_change_duration(data, duration);
-    handle_result(result)
+pub(crate) fn handle_statistics(sub_matches: &ArgMatches) -> ExitCode {
+    let output_options = OutputOptions::from_matches(sub_matches);
+    let path = sub_matches.get_one::<PathBuf>("DATA").unwrap();
+    let data = match read_statistics_data(path) {
+        Ok(data) => data,
+        Err(err) => {
+            let error = CliError::Input(format!("{path:?}：{err}"));
+            return finish("statistics", Err(error), &output_options);
+        }
+    };
+    let statistics_type = match sub_matches.get_one::<String>("type").map(|s| s.as_str()) {
+        Some("time_count") => MemeStatisticsType::TimeCount,
+        _ => MemeStatisticsType::MemeCount,
+    };
+    let params = RenderMemeStatisticsParams {
+        title: sub_matches
+            .get_one::<String>("title")
+            .cloned()
+            .unwrap_or_default(),
+        statistics_type,
+        data,
+    };
+    let result = render_meme_statistics(params);
+    finish(
+        "statistics",
+        result.map_err(CliError::from),
+        &output_options,
+    )
 }
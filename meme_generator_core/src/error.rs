@@ -1,15 +1,19 @@
-use std::{error, fmt};
+use std::{any::Any, error, fmt};
 
+/// 外部表情库与程序之间直接传递此类型，增删变体会改变其内存布局，
+/// 需要同时提升 meme_generator_core 的版本，旧版本的表情库会被拒绝加载
 #[derive(Debug)]
 pub enum Error {
     ImageDecodeError(String),
     ImageEncodeError(String),
     ImageAssetMissing(String),
+    ImageLimitExceeded(String, u64, u64),
     DeserializeError(String),
     ImageNumberMismatch(u8, u8, u8),
     TextNumberMismatch(u8, u8, u8),
     TextOverLength(String),
     MemeFeedback(String),
+    InternalError(String, String),
 }
 
 impl fmt::Display for Error {
@@ -18,6 +22,10 @@ impl fmt::Display for Error {
             Error::ImageDecodeError(err) => write!(f, "Failed to decode image: {err}"),
             Error::ImageEncodeError(err) => write!(f, "Failed to encode image: {err}"),
             Error::ImageAssetMissing(path) => write!(f, "Image asset missing: {path}"),
+            Error::ImageLimitExceeded(limit, max, actual) => write!(
+                f,
+                "Image limit exceeded: {limit} is {actual}, maximum is {max}",
+            ),
             Error::DeserializeError(err) => write!(f, "Failed to deserialize: {err}"),
             Error::ImageNumberMismatch(min, max, actual) => write!(
                 f,
@@ -29,8 +37,25 @@ impl fmt::Display for Error {
             ),
             Error::TextOverLength(text) => write!(f, "Text is too long: {text}"),
             Error::MemeFeedback(feedback) => write!(f, "{feedback}"),
+            Error::InternalError(key, message) => {
+                write!(f, "Internal error in meme `{key}`: {message}")
+            }
         }
     }
 }
 
 impl error::Error for Error {}
+
+impl Error {
+    /// 将 `catch_unwind` 捕获的 panic 转换为 `Error::InternalError`
+    pub fn from_panic(key: &str, payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_string()
+        };
+        Error::InternalError(key.to_string(), message)
+    }
+}
@@ -155,6 +155,7 @@ fn always_always(images: Vec<InputImage>, loop_: bool) -> Result<Vec<u8>, Error>
         GifInfo {
             frame_num,
             duration: 0.1,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
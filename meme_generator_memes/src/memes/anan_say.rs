@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::ImageExt,
-    text_params,
+    random, text_params,
     tools::{load_image, local_date, new_paint},
 };
 
@@ -23,7 +23,7 @@ struct Expression {
 fn anan_say(_: Vec<InputImage>, texts: Vec<String>, options: Expression) -> Result<Vec<u8>, Error> {
     let text = &texts[0];
     let expression = options.expression.as_deref().unwrap_or({
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         ["angry", "black", "happy", "shy", "speechless"]
             .choose(&mut rng)
             .unwrap()
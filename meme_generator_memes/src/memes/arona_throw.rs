@@ -49,6 +49,7 @@ fn arona_throw(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<
         GifInfo {
             frame_num: 16,
             duration: 0.04,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
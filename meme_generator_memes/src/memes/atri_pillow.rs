@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::ImageExt,
-    text_params,
+    random, text_params,
     tools::{load_image, local_date, new_paint, new_surface},
 };
 
@@ -35,7 +35,7 @@ fn atri_pillow(_: Vec<InputImage>, texts: Vec<String>, options: Mode) -> Result<
         "no"
     } else {
         options.mode.as_deref().unwrap_or({
-            let mut rng = rand::rng();
+            let mut rng = random::rng();
             ["yes", "no"].choose(&mut rng).unwrap()
         })
     };
@@ -78,6 +78,7 @@ fn backflip(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec
         GifInfo {
             frame_num: total_frames,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -28,6 +28,7 @@ fn capoo_stew(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<V
         GifInfo {
             frame_num: 5,
             duration: 0.08,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -40,6 +40,7 @@ fn capoo_strike(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result
         GifInfo {
             frame_num: 7,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
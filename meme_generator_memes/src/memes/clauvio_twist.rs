@@ -53,6 +53,7 @@ fn clauvio_twist(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resul
         GifInfo {
             frame_num: 40,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
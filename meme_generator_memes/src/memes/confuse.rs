@@ -30,6 +30,7 @@ fn confuse(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<
         GifInfo {
             frame_num: 100,
             duration: 0.02,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -6,6 +6,7 @@ use meme_generator_utils::{
     builder::{InputImage, MemeOptions},
     encoder::make_png_or_gif,
     image::ImageExt,
+    random,
     tools::{load_image, local_date},
 };
 
@@ -15,7 +16,7 @@ number_option!(Number, 1, 92);
 
 fn crawl(images: Vec<InputImage>, _: Vec<String>, options: Number) -> Result<Vec<u8>, Error> {
     let num = options.number.unwrap_or({
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         rng.random_range(1..=92)
     });
 
@@ -68,6 +68,7 @@ fn dog_dislike(images: Vec<InputImage>, _: Vec<String>, options: Circle) -> Resu
         GifInfo {
             frame_num: 34,
             duration: 0.08,
+            ..Default::default()
         },
         None,
     )
@@ -6,6 +6,7 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::GifEncoder,
     image::ImageExt,
+    random,
     text::Text2Image,
     text_params,
     tools::{color_from_hex_code, local_date, new_paint, new_stroke_paint, new_surface},
@@ -65,7 +66,7 @@ fn douyin(_: Vec<InputImage>, texts: Vec<String>, _: NoOptions) -> Result<Vec<u8
     let devide_num = 6;
     let seed = 20.0 * 0.05;
     let tilt = 0.17;
-    let mut rng = rand::rng();
+    let mut rng = random::rng();
 
     let mut encoder = GifEncoder::new();
     for _ in 0..frame_num {
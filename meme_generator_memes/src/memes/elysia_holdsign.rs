@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::ImageExt,
-    text_params,
+    random, text_params,
     tools::{color_from_hex_code, load_image, local_date, new_paint, new_surface},
 };
 
@@ -30,7 +30,7 @@ fn elysia_holdsign(
 
     let img_num = 8;
     let num = match options.number {
-        None => rand::rng().random_range(1..=img_num),
+        None => random::rng().random_range(1..=img_num),
         Some(n) => {
             if n < 1 || n > img_num {
                 return Err(Error::MemeFeedback(format!(
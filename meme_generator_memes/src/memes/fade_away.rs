@@ -4,8 +4,9 @@ use skia_safe::{Canvas, Color, Data, Image, Paint, Rect, RuntimeEffect};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::{GifInfo, make_gif_or_combined_gif},
+    encoder::{GifInfo, make_gif_or_combined_gif_sequential},
     image::ImageExt,
+    random,
     tools::{default_sampling_options, local_date, new_paint, new_surface},
 };
 
@@ -24,7 +25,7 @@ struct Dot {
 
 impl Dot {
     fn new(positon: (f32, f32), direction: (f32, f32)) -> Self {
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         Self {
             x: positon.0,
             y: positon.1,
@@ -43,7 +44,7 @@ impl Dot {
         self.vy += a * self.dy;
         self.x += self.vx;
         self.y += self.vy;
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         if rng.random_range(0.0..1.0) < 0.25 {
             self.radius -= 1.0;
         }
@@ -164,7 +165,7 @@ fn fade_away(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Ve
             paint.set_shader(shader);
             canvas.draw_paint(&paint);
 
-            let mut rng = rand::rng();
+            let mut rng = random::rng();
             let pixmap = img.peek_pixels().unwrap();
             for r in r1 as i32..r2 as i32 {
                 for theta in 0..180 {
@@ -196,12 +197,13 @@ fn fade_away(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Ve
         }
     };
 
-    make_gif_or_combined_gif(
+    make_gif_or_combined_gif_sequential(
         images,
         func,
         GifInfo {
             frame_num: 35,
             duration: 0.08,
+            ..Default::default()
         },
         None,
     )
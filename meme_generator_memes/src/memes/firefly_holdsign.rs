@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::ImageExt,
-    text_params,
+    random, text_params,
     tools::{color_from_hex_code, load_image, local_date, new_paint, new_surface},
 };
 
@@ -22,7 +22,7 @@ fn firefly_holdsign(
 ) -> Result<Vec<u8>, Error> {
     let text = &texts[0];
     let num = options.number.unwrap_or({
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         rng.random_range(1..=21)
     });
 
@@ -42,6 +42,7 @@ fn flash_blind(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<
         GifInfo {
             frame_num: 4,
             duration: 0.03,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -5,7 +5,7 @@ use skia_safe::{Data, Image, Paint, RuntimeEffect};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::{GifInfo, make_gif_or_combined_gif},
+    encoder::{GifInfo, make_gif_or_combined_gif_sequential},
     image::ImageExt,
     tools::{default_sampling_options, load_image, local_date, new_surface},
 };
@@ -94,12 +94,13 @@ fn flick(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8
         Ok(frame)
     };
 
-    make_gif_or_combined_gif(
+    make_gif_or_combined_gif_sequential(
         images,
         func,
         GifInfo {
             frame_num: 30,
             duration: 0.05,
+            ..Default::default()
         },
         None,
     )
@@ -6,6 +6,7 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::{GifInfo, make_gif_or_combined_gif},
     image::ImageExt,
+    random,
     tools::{load_image, local_date, new_paint, new_surface},
 };
 
@@ -25,7 +26,7 @@ fn flush(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8
             return Ok(frame.resize_exact((w, h)));
         }
 
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         let padding_ratio = 0.01 * i as f32;
         let jitter_ratio = padding_ratio * 0.4 * rng.random_range(-0.5..0.5);
         let padding = (w as f32 * padding_ratio).round() as i32;
@@ -53,6 +54,7 @@ fn flush(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8
         GifInfo {
             frame_num: 30,
             duration: 0.08,
+            ..Default::default()
         },
         None,
     )
@@ -5,7 +5,7 @@ use skia_safe::{Data, Image, Paint, RuntimeEffect};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
+    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif_sequential},
     tools::{default_sampling_options, local_date, new_surface},
 };
 
@@ -64,12 +64,13 @@ fn funny_mirror(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result
         Ok(surface.image_snapshot())
     };
 
-    make_gif_or_combined_gif(
+    make_gif_or_combined_gif_sequential(
         images,
         func,
         GifInfo {
             frame_num: 21,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -5,7 +5,7 @@ use meme_generator_utils::{
     builder::{InputImage, MemeOptions},
     encoder::GifEncoder,
     image::ImageExt,
-    shortcut,
+    random, shortcut,
     tools::{load_image, local_date},
 };
 
@@ -24,7 +24,7 @@ fn genshin_eat(
     options: Character,
 ) -> Result<Vec<u8>, Error> {
     let character = options.character.as_deref().unwrap_or({
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         ["hutao", "keqing", "klee", "nilou", "yae_miko", "zhongli"]
             .choose(&mut rng)
             .unwrap()
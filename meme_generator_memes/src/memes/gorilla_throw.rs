@@ -47,6 +47,7 @@ fn gorilla_throw(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resul
         GifInfo {
             frame_num: 42,
             duration: 0.04,
+            ..Default::default()
         },
         None,
     )
@@ -121,6 +121,7 @@ fn guichu(images: Vec<InputImage>, _: Vec<String>, options: Direction) -> Result
         GifInfo {
             frame_num: 32,
             duration: 0.2,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
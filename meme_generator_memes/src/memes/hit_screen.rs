@@ -51,6 +51,7 @@ fn hit_screen(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<V
         GifInfo {
             frame_num: 29,
             duration: 0.2,
+            ..Default::default()
         },
         FrameAlign::ExtendFirst,
     )
@@ -65,6 +65,7 @@ fn kirby_hammer(
         GifInfo {
             frame_num: 62,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::ImageExt,
-    text_params,
+    random, text_params,
     tools::{color_from_hex_code, load_image, local_date, new_paint, new_surface},
 };
 
@@ -17,7 +17,7 @@ number_option!(Number, 1, 12);
 
 fn kokona_seal(_: Vec<InputImage>, texts: Vec<String>, options: Number) -> Result<Vec<u8>, Error> {
     let text = &texts[0];
-    let num = options.number.unwrap_or(rand::rng().random_range(1..=12));
+    let num = options.number.unwrap_or(random::rng().random_range(1..=12));
 
     let size = (320, 155);
     let loc = (75, 25);
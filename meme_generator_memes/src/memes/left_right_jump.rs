@@ -92,6 +92,7 @@ fn left_right_jump(
         GifInfo {
             frame_num,
             duration: 0.04,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -81,6 +81,7 @@ fn loop_(images: Vec<InputImage>, _: Vec<String>, options: Direction) -> Result<
         GifInfo {
             frame_num: 30,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::{Fit, ImageExt},
-    text_params,
+    random, text_params,
     tools::{color_from_hex_code, load_image, local_date, new_paint, new_surface},
 };
 
@@ -234,7 +234,7 @@ fn name_generator(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resu
         "糖",
     ];
 
-    let mut rng = rand::rng();
+    let mut rng = random::rng();
     let color = colors.choose(&mut rng).unwrap();
     let name = format!(
         "{}{}{}",
@@ -7,7 +7,7 @@ use meme_generator_utils::{
     canvas::CanvasExt,
     encoder::encode_png,
     image::ImageExt,
-    text_params,
+    random, text_params,
     tools::{load_image, local_date, new_paint, new_stroke_paint, new_surface},
 };
 
@@ -39,7 +39,7 @@ fn operator_generator(
         )
         .map_err(|_| Error::TextOverLength(name.clone()))?;
 
-    let mut rng = rand::rng();
+    let mut rng = random::rng();
 
     let range = load_image(&format!(
         "operator_generator/range/{:02}.jpg",
@@ -6,6 +6,7 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::encode_png,
     image::ImageExt,
+    random,
     text::Text2Image,
     text_params,
     tools::{color_from_hex_code, load_image, local_date, new_paint, new_surface},
@@ -33,7 +34,7 @@ const COLOR_BLACK: &str = "#0F0F0F";
 
 impl BoxChar {
     fn new(char: char, mode: CharMode, font_size: f32) -> Self {
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         let angle = rng.random_range(-10.0..0.0);
         let angle = match mode {
             CharMode::First => angle,
@@ -186,7 +187,7 @@ fn p5letter(_: Vec<InputImage>, texts: Vec<String>, _: NoOptions) -> Result<Vec<
             let mode = if box_chars.is_empty() {
                 CharMode::First
             } else {
-                if rand::rng().random_range(0.0..1.0) < 0.4 {
+                if random::rng().random_range(0.0..1.0) < 0.4 {
                     CharMode::Red
                 } else {
                     CharMode::White
@@ -42,6 +42,7 @@ fn petpet(images: Vec<InputImage>, _: Vec<String>, options: Circle) -> Result<Ve
         GifInfo {
             frame_num: 5,
             duration: 0.06,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
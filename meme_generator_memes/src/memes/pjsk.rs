@@ -6,7 +6,7 @@ use meme_generator_utils::{
     builder::{InputImage, MemeOptions},
     encoder::encode_png,
     image::ImageExt,
-    shortcut,
+    random, shortcut,
     text::Text2Image,
     text_params,
     tools::{color_from_hex_code, load_image, local_date, new_paint, new_stroke_paint},
@@ -212,14 +212,14 @@ fn pjsk(_: Vec<InputImage>, texts: Vec<String>, options: Options) -> Result<Vec<
 
     let character = match options.character {
         None => {
-            let mut rng = rand::rng();
+            let mut rng = random::rng();
             CHARACTERS.choose(&mut rng).unwrap()
         }
         Some(name) => CHARACTERS.iter().find(|c| c.name_en == name).unwrap(),
     };
 
     let num = match options.number {
-        None => rand::rng().random_range(1..=character.img_num),
+        None => random::rng().random_range(1..=character.img_num),
         Some(n) => {
             if n < 1 || n > character.img_num {
                 return Err(Error::MemeFeedback(format!(
@@ -253,7 +253,7 @@ fn pjsk(_: Vec<InputImage>, texts: Vec<String>, options: Options) -> Result<Vec<
 
     let angle = options
         .rotate
-        .unwrap_or_else(|| rand::rng().random_range(-40..40)) as f32;
+        .unwrap_or_else(|| random::rng().random_range(-40..40)) as f32;
     let x_offset = options.x_offset.unwrap();
     let y_offset = options.y_offset.unwrap();
 
@@ -52,6 +52,7 @@ fn play_together(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resul
         GifInfo {
             frame_num: 60,
             duration: 0.06,
+            ..Default::default()
         },
         FrameAlign::ExtendLast,
     )
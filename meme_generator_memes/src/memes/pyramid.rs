@@ -104,6 +104,7 @@ fn pyramid(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<
         GifInfo {
             frame_num: frame_num as u32,
             duration: 0.06,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -6,6 +6,7 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
     image::ImageExt,
+    random,
     tools::{load_image, local_date, new_surface},
 };
 
@@ -20,7 +21,7 @@ fn remote_control(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resu
         let img_h = img.height();
         let mut surface = new_surface((img_w, img_h));
         let canvas = surface.canvas();
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         let pos = if i < 4 {
             (0, 0)
         } else {
@@ -43,6 +44,7 @@ fn remote_control(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resu
         GifInfo {
             frame_num: 17,
             duration: 0.07,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
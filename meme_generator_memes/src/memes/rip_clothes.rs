@@ -32,6 +32,7 @@ fn rip_clothes(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<
         GifInfo {
             frame_num: 20,
             duration: 0.1,
+            ..Default::default()
         },
         FrameAlign::ExtendLast,
     )
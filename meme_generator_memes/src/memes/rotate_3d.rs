@@ -86,6 +86,7 @@ fn rotate_3d(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Ve
         GifInfo {
             frame_num: 30,
             duration: 0.07,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
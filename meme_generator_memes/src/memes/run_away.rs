@@ -42,6 +42,7 @@ fn run_away(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec
         GifInfo {
             frame_num: 42,
             duration: 0.03,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
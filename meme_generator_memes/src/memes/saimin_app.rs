@@ -37,6 +37,7 @@ fn saimin_app(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<V
         GifInfo {
             frame_num: 25,
             duration: 0.03,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
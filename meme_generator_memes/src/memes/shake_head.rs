@@ -7,6 +7,7 @@ use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
     encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
+    random,
     tools::{local_date, new_surface},
 };
 
@@ -29,7 +30,7 @@ fn shake_head(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<V
         let mut surface = new_surface((frame_w, frame_h));
         let canvas = surface.canvas();
         let img = &images[0];
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         let x = (padding_w as f32 * (-(i as f32) * dt).sin() - padding_w as f32
             + rng.random_range(-1.0..1.0) * dw as f32)
             .round() as i32;
@@ -46,6 +47,7 @@ fn shake_head(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<V
         GifInfo {
             frame_num,
             duration: 0.02,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -32,6 +32,7 @@ fn shiroko_pero(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result
         GifInfo {
             frame_num: 4,
             duration: 0.06,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
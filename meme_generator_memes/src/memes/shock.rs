@@ -4,8 +4,9 @@ use skia_safe::{Data, Image, Paint, RuntimeEffect};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
+    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif_sequential},
     image::ImageExt,
+    random,
     tools::{default_sampling_options, local_date, new_surface},
 };
 
@@ -36,7 +37,7 @@ fn shock(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8
 
     let func = |_: usize, images: Vec<Image>| {
         let img = images[0].square().resize_exact((300, 300));
-        let mut rng = rand::rng();
+        let mut rng = random::rng();
         let angle = rng.random_range(-90..=90);
         let angle = (angle as f32).to_radians();
         let direction = (angle.cos(), angle.sin());
@@ -71,12 +72,13 @@ fn shock(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8
         Ok(surface.image_snapshot().rotate_crop(rotate))
     };
 
-    make_gif_or_combined_gif(
+    make_gif_or_combined_gif_sequential(
         images,
         func,
         GifInfo {
             frame_num: 20,
             duration: 0.01,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -3,7 +3,7 @@ use skia_safe::{Color, Image};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::make_png_or_gif,
+    encoder::make_png_or_gif_sequential,
     image::ImageExt,
     shortcut,
     text::Text2Image,
@@ -37,7 +37,7 @@ fn speechless(images: Vec<InputImage>, texts: Vec<String>, _: NoOptions) -> Resu
         Ok(surface.image_snapshot())
     };
 
-    make_png_or_gif(images, func)
+    make_png_or_gif_sequential(images, func)
 }
 
 register_meme!(
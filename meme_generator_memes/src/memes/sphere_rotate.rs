@@ -1,7 +1,7 @@
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
+    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif_sequential},
     tools::{default_sampling_options, local_date, new_surface},
 };
 use skia_safe::{Data, Image, Paint, RuntimeEffect};
@@ -150,12 +150,13 @@ fn sphere_rotate(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resul
         Ok(surface.image_snapshot())
     };
 
-    make_gif_or_combined_gif(
+    make_gif_or_combined_gif_sequential(
         images,
         func,
         GifInfo {
             frame_num: total_frames,
             duration: 0.04,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -5,6 +5,7 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::GifEncoder,
     image::ImageExt,
+    random,
     tools::{load_image, local_date},
 };
 
@@ -17,7 +18,7 @@ fn spider(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u
         123, 131, 134, 143, 154, 158, 161, 163, 169, 174, 173, 174, 173,
     ];
     let img = images[0].image.circle().resize_exact((80, 80));
-    let mut rng = rand::rng();
+    let mut rng = random::rng();
 
     let mut encoder = GifEncoder::new();
     for i in 0..52 {
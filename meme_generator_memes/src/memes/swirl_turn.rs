@@ -35,6 +35,7 @@ fn swirl_turn(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<V
         GifInfo {
             frame_num,
             duration: 0.02,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
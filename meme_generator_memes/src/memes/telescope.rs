@@ -51,6 +51,7 @@ fn telescope(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Ve
         GifInfo {
             frame_num: 69,
             duration: 0.1,
+            ..Default::default()
         },
         FrameAlign::ExtendFirst,
     )
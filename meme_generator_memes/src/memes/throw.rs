@@ -5,13 +5,14 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::encode_png,
     image::ImageExt,
+    random,
     tools::{load_image, local_date},
 };
 
 use crate::{options::NoOptions, register_meme, tags::MemeTags};
 
 fn throw(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8>, Error> {
-    let angle = rand::rng().random_range(1..=360);
+    let angle = random::rng().random_range(1..=360);
     let img = images[0]
         .image
         .circle()
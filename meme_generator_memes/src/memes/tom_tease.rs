@@ -29,6 +29,7 @@ fn tom_tease(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Ve
         GifInfo {
             frame_num: 11,
             duration: 0.2,
+            ..Default::default()
         },
         FrameAlign::ExtendFirst,
     )
@@ -6,13 +6,14 @@ use meme_generator_utils::{
     builder::InputImage,
     encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
     image::ImageExt,
+    random,
     tools::local_date,
 };
 
 use crate::{options::NoOptions, register_meme};
 
 fn turn(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8>, Error> {
-    let direction = [-1, 1].choose(&mut rand::rng()).unwrap();
+    let direction = [-1, 1].choose(&mut random::rng()).unwrap();
 
     let func = |i: usize, images: Vec<Image>| {
         let angle = i as f32 * 10.0 * (*direction) as f32;
@@ -29,6 +30,7 @@ fn turn(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8>
         GifInfo {
             frame_num: 36,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -3,7 +3,7 @@ use skia_safe::{Color, FontStyle, Image, Point, textlayout::TextAlign};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::{InputImage, MemeOptions},
-    encoder::make_png_or_gif,
+    encoder::make_png_or_gif_sequential,
     image::ImageExt,
     text::Text2Image,
     text_params,
@@ -159,7 +159,7 @@ fn universal(
         Ok(surface.image_snapshot())
     };
 
-    make_png_or_gif(images, func)
+    make_png_or_gif_sequential(images, func)
 }
 
 register_meme!(
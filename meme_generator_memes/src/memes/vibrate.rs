@@ -34,6 +34,7 @@ fn vibrate(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<
         GifInfo {
             frame_num: 5,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -33,6 +33,7 @@ fn wallpaper(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Ve
         GifInfo {
             frame_num: 20,
             duration: 0.07,
+            ..Default::default()
         },
         FrameAlign::ExtendLast,
     )
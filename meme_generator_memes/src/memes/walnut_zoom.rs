@@ -42,6 +42,7 @@ fn walnut_zoom(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<
         GifInfo {
             frame_num: 24,
             duration: 0.2,
+            ..Default::default()
         },
         FrameAlign::ExtendLast,
     )
@@ -5,7 +5,7 @@ use skia_safe::{Data, IRect, Image, Paint, RuntimeEffect};
 use meme_generator_core::error::Error;
 use meme_generator_utils::{
     builder::InputImage,
-    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif},
+    encoder::{FrameAlign, GifInfo, make_gif_or_combined_gif_sequential},
     image::ImageExt,
     tools::{default_sampling_options, local_date, new_surface},
 };
@@ -78,12 +78,13 @@ fn wave(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Result<Vec<u8>
         Ok(surface.image_snapshot())
     };
 
-    make_gif_or_combined_gif(
+    make_gif_or_combined_gif_sequential(
         images,
         func,
         GifInfo {
             frame_num,
             duration: 0.01,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
@@ -31,6 +31,7 @@ fn windmill_turn(images: Vec<InputImage>, _: Vec<String>, _: NoOptions) -> Resul
         GifInfo {
             frame_num: 5,
             duration: 0.05,
+            ..Default::default()
         },
         FrameAlign::ExtendLoop,
     )
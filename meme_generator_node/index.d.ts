@@ -130,11 +130,13 @@ export type Error =
   | { type: "ImageDecodeError"; field0: ImageDecodeError }
   | { type: "ImageEncodeError"; field0: ImageEncodeError }
   | { type: "ImageAssetMissing"; field0: ImageAssetMissing }
+  | { type: "ImageLimitExceeded"; field0: ImageLimitExceeded }
   | { type: "DeserializeError"; field0: DeserializeError }
   | { type: "ImageNumberMismatch"; field0: ImageNumberMismatch }
   | { type: "TextNumberMismatch"; field0: TextNumberMismatch }
   | { type: "TextOverLength"; field0: TextOverLength }
-  | { type: "MemeFeedback"; field0: MemeFeedback };
+  | { type: "MemeFeedback"; field0: MemeFeedback }
+  | { type: "InternalError"; field0: InternalError };
 
 export interface ImageDecodeError {
   error: string;
@@ -148,6 +150,12 @@ export interface ImageAssetMissing {
   path: string;
 }
 
+export interface ImageLimitExceeded {
+  limit: string;
+  max: number;
+  actual: number;
+}
+
 export interface DeserializeError {
   error: string;
 }
@@ -172,6 +180,11 @@ export interface MemeFeedback {
   feedback: string;
 }
 
+export interface InternalError {
+  key: string;
+  message: string;
+}
+
 export namespace Resources {
   export function checkResources(): void;
 
@@ -187,6 +200,8 @@ export namespace Tools {
     renderMemeStatisticsParams: RenderMemeStatisticsParams
   ): ImageResult;
 
+  export function parseBbcode(text: string): BBCodeResult;
+
   export interface RenderMemeListParams {
     memeProperties?: Record<string, MemeProperties>;
     excludeMemes?: Array<string>;
@@ -221,6 +236,22 @@ export namespace Tools {
     | { type: "Ok"; field0: Array<Buffer> }
     | { type: "Err"; field0: Error };
 
+  export interface BBCodeToken {
+    kind: "text" | "open_tag" | "close_tag";
+    text?: string;
+    tag?: string;
+    value?: string;
+  }
+
+  export interface BBCodeError {
+    position: number;
+    message: string;
+  }
+
+  export type BBCodeResult =
+    | { type: "Ok"; field0: Array<BBCodeToken> }
+    | { type: "Err"; field0: BBCodeError };
+
   export namespace ImageOperations {
     export function inspect(image: Buffer): ImageInfoResult;
 
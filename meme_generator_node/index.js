@@ -20,6 +20,7 @@ import {
   MemeStatisticsType,
   mergeHorizontal,
   mergeVertical,
+  parseBbcode,
   renderMemeList,
   renderMemeStatistics,
   resize,
@@ -37,6 +38,7 @@ export const Resources = {
 export const Tools = {
   renderMemeList,
   renderMemeStatistics,
+  parseBbcode,
   MemeSortBy,
   MemeStatisticsType,
   ImageOperations: {
@@ -393,7 +393,7 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { Meme, checkResources, checkResourcesInBackground, crop, flipHorizontal, flipVertical, getMeme, getMemeKeys, getMemes, getVersion, gifChangeDuration, gifMerge, gifReverse, gifSplit, grayscale, inspect, invert, MemeSortBy, MemeStatisticsType, mergeHorizontal, mergeVertical, renderMemeList, renderMemeStatistics, resize, rotate, searchMemes } = nativeBinding
+const { Meme, checkResources, checkResourcesInBackground, crop, flipHorizontal, flipVertical, getMeme, getMemeKeys, getMemes, getVersion, gifChangeDuration, gifMerge, gifReverse, gifSplit, grayscale, inspect, invert, MemeSortBy, MemeStatisticsType, mergeHorizontal, mergeVertical, parseBbcode, renderMemeList, renderMemeStatistics, resize, rotate, searchMemes } = nativeBinding
 export { Meme }
 export { checkResources }
 export { checkResourcesInBackground }
@@ -415,6 +415,7 @@ export { MemeSortBy }
 export { MemeStatisticsType }
 export { mergeHorizontal }
 export { mergeVertical }
+export { parseBbcode }
 export { renderMemeList }
 export { renderMemeStatistics }
 export { resize }
@@ -159,6 +159,14 @@ pub struct ImageAssetMissing {
     pub path: String,
 }
 
+#[napi(object)]
+#[derive(Clone)]
+pub struct ImageLimitExceeded {
+    pub limit: String,
+    pub max: i64,
+    pub actual: i64,
+}
+
 #[napi(object)]
 #[derive(Clone)]
 pub struct DeserializeError {
@@ -193,17 +201,26 @@ pub struct MemeFeedback {
     pub feedback: String,
 }
 
+#[napi(object)]
+#[derive(Clone)]
+pub struct InternalError {
+    pub key: String,
+    pub message: String,
+}
+
 #[napi]
 #[derive(Clone)]
 pub enum Error {
     ImageDecodeError(ImageDecodeError),
     ImageEncodeError(ImageEncodeError),
     ImageAssetMissing(ImageAssetMissing),
+    ImageLimitExceeded(ImageLimitExceeded),
     DeserializeError(DeserializeError),
     ImageNumberMismatch(ImageNumberMismatch),
     TextNumberMismatch(TextNumberMismatch),
     TextOverLength(TextOverLength),
     MemeFeedback(MemeFeedback),
+    InternalError(InternalError),
 }
 
 #[napi]
@@ -406,6 +423,13 @@ fn handle_result(result: Result<Vec<u8>, error::Error>) -> MemeResult {
             error::Error::ImageAssetMissing(path) => {
                 MemeResult::Err(Error::ImageAssetMissing(ImageAssetMissing { path }))
             }
+            error::Error::ImageLimitExceeded(limit, max, actual) => {
+                MemeResult::Err(Error::ImageLimitExceeded(ImageLimitExceeded {
+                    limit,
+                    max: max as i64,
+                    actual: actual as i64,
+                }))
+            }
             error::Error::DeserializeError(error) => {
                 MemeResult::Err(Error::DeserializeError(DeserializeError { error }))
             }
@@ -429,6 +453,9 @@ fn handle_result(result: Result<Vec<u8>, error::Error>) -> MemeResult {
             error::Error::MemeFeedback(feedback) => {
                 MemeResult::Err(Error::MemeFeedback(MemeFeedback { feedback }))
             }
+            error::Error::InternalError(key, message) => {
+                MemeResult::Err(Error::InternalError(InternalError { key, message }))
+            }
         },
     }
 }
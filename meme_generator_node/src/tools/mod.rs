@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use napi::bindgen_prelude::Buffer;
 use napi_derive::napi;
 
-use meme_generator::{error, tools};
+use meme_generator::{bbcode, error, tools};
 
 use crate::{Error, ImageDecodeError, ImageEncodeError, MemeSortBy};
 
@@ -154,3 +154,61 @@ pub fn render_meme_statistics(
     });
     handle_image_result(result)
 }
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct BBCodeToken {
+    pub kind: String,
+    pub text: Option<String>,
+    pub tag: Option<String>,
+    pub value: Option<String>,
+}
+
+impl From<bbcode::BBCodeToken> for BBCodeToken {
+    fn from(token: bbcode::BBCodeToken) -> Self {
+        match token {
+            bbcode::BBCodeToken::Text(text) => Self {
+                kind: "text".to_string(),
+                text: Some(text),
+                tag: None,
+                value: None,
+            },
+            bbcode::BBCodeToken::OpenTag(tag) => Self {
+                kind: "open_tag".to_string(),
+                text: None,
+                tag: Some(tag.name().to_string()),
+                value: tag.value(),
+            },
+            bbcode::BBCodeToken::CloseTag(name) => Self {
+                kind: "close_tag".to_string(),
+                text: None,
+                tag: Some(name),
+                value: None,
+            },
+        }
+    }
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct BBCodeError {
+    pub position: u32,
+    pub message: String,
+}
+
+#[napi]
+pub enum BBCodeResult {
+    Ok(Vec<BBCodeToken>),
+    Err(BBCodeError),
+}
+
+#[napi]
+pub fn parse_bbcode(text: String) -> BBCodeResult {
+    match bbcode::parse_bbcode_strict(&text) {
+        Ok(tokens) => BBCodeResult::Ok(tokens.into_iter().map(Into::into).collect()),
+        Err(error) => BBCodeResult::Err(BBCodeError {
+            position: error.position as u32,
+            message: error.to_string(),
+        }),
+    }
+}
@@ -72,6 +72,11 @@ class ImageEncodeError:
 class ImageAssetMissing:
     path: str
 
+class ImageLimitExceeded:
+    limit: str
+    max: int
+    actual: int
+
 class DeserializeError:
     error: str
 
@@ -91,6 +96,10 @@ class TextOverLength:
 class MemeFeedback:
     feedback: str
 
+class InternalError:
+    key: str
+    message: str
+
 class Meme:
     @property
     def key(self) -> str: ...
@@ -106,11 +115,13 @@ class Meme:
         | ImageDecodeError
         | ImageEncodeError
         | ImageAssetMissing
+        | ImageLimitExceeded
         | DeserializeError
         | ImageNumberMismatch
         | TextNumberMismatch
         | TextOverLength
         | MemeFeedback
+        | InternalError
     ): ...
     def generate_preview(
         self,
@@ -122,6 +133,7 @@ class Meme:
         | DeserializeError
         | TextOverLength
         | MemeFeedback
+        | InternalError
     ): ...
 
 class MemeSortBy(Enum):
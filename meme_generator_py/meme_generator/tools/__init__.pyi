@@ -9,6 +9,22 @@ class MemeStatisticsType(Enum):
     MemeCount = 0
     TimeCount = 1
 
+class BBCodeToken:
+    @property
+    def kind(self) -> str: ...
+    @property
+    def text(self) -> str | None: ...
+    @property
+    def tag(self) -> str | None: ...
+    @property
+    def value(self) -> str | None: ...
+
+class BBCodeError:
+    @property
+    def position(self) -> int: ...
+    @property
+    def message(self) -> str: ...
+
 def render_meme_list(
     meme_properties: dict[str, MemeProperties] = {},
     exclude_memes: list[str] = [],
@@ -22,3 +38,4 @@ def render_meme_statistics(
     statistics_type: MemeStatisticsType,
     data: list[tuple[str, int]],
 ) -> bytes | ImageEncodeError: ...
+def parse_bbcode(text: str) -> list[BBCodeToken] | BBCodeError: ...
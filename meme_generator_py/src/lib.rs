@@ -31,11 +31,13 @@ fn meme_generator_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<ImageDecodeError>()?;
     m.add_class::<ImageEncodeError>()?;
     m.add_class::<ImageAssetMissing>()?;
+    m.add_class::<ImageLimitExceeded>()?;
     m.add_class::<DeserializeError>()?;
     m.add_class::<ImageNumberMismatch>()?;
     m.add_class::<TextNumberMismatch>()?;
     m.add_class::<TextOverLength>()?;
     m.add_class::<MemeFeedback>()?;
+    m.add_class::<InternalError>()?;
     m.add_class::<Meme>()?;
     m.add_class::<MemeSortBy>()?;
     m.add_function(wrap_pyfunction!(get_version, m)?)?;
@@ -254,6 +256,17 @@ struct ImageAssetMissing {
     path: String,
 }
 
+#[pyclass(skip_from_py_object)]
+#[derive(Clone)]
+struct ImageLimitExceeded {
+    #[pyo3(get)]
+    limit: String,
+    #[pyo3(get)]
+    max: u64,
+    #[pyo3(get)]
+    actual: u64,
+}
+
 #[pyclass(skip_from_py_object)]
 #[derive(Clone)]
 struct DeserializeError {
@@ -297,16 +310,27 @@ struct MemeFeedback {
     feedback: String,
 }
 
+#[pyclass(skip_from_py_object)]
+#[derive(Clone)]
+struct InternalError {
+    #[pyo3(get)]
+    key: String,
+    #[pyo3(get)]
+    message: String,
+}
+
 #[derive(IntoPyObject, Clone)]
 enum Error {
     ImageDecodeError(ImageDecodeError),
     ImageEncodeError(ImageEncodeError),
     ImageAssetMissing(ImageAssetMissing),
+    ImageLimitExceeded(ImageLimitExceeded),
     DeserializeError(DeserializeError),
     ImageNumberMismatch(ImageNumberMismatch),
     TextNumberMismatch(TextNumberMismatch),
     TextOverLength(TextOverLength),
     MemeFeedback(MemeFeedback),
+    InternalError(InternalError),
 }
 
 #[derive(IntoPyObject, Clone)]
@@ -498,6 +522,13 @@ fn handle_result(result: Result<Vec<u8>, error::Error>) -> MemeResult {
             error::Error::ImageAssetMissing(path) => {
                 MemeResult::Err(Error::ImageAssetMissing(ImageAssetMissing { path }))
             }
+            error::Error::ImageLimitExceeded(limit, max, actual) => {
+                MemeResult::Err(Error::ImageLimitExceeded(ImageLimitExceeded {
+                    limit,
+                    max,
+                    actual,
+                }))
+            }
             error::Error::DeserializeError(error) => {
                 MemeResult::Err(Error::DeserializeError(DeserializeError { error }))
             }
@@ -521,6 +552,9 @@ fn handle_result(result: Result<Vec<u8>, error::Error>) -> MemeResult {
             error::Error::MemeFeedback(feedback) => {
                 MemeResult::Err(Error::MemeFeedback(MemeFeedback { feedback }))
             }
+            error::Error::InternalError(key, message) => {
+                MemeResult::Err(Error::InternalError(InternalError { key, message }))
+            }
         },
     }
 }
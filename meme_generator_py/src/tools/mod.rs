@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use pyo3::prelude::*;
 
-use meme_generator::{error, tools};
+use meme_generator::{bbcode, error, tools};
 
 use crate::{Error, ImageDecodeError, ImageEncodeError, MemeSortBy};
 
@@ -15,8 +15,11 @@ pub(crate) fn register_tools_module(parent_module: &Bound<'_, PyModule>) -> PyRe
     m.add_class::<MemeProperties>()?;
     m.add_class::<MemeSortBy>()?;
     m.add_class::<MemeStatisticsType>()?;
+    m.add_class::<BBCodeToken>()?;
+    m.add_class::<BBCodeError>()?;
     m.add_function(wrap_pyfunction!(render_meme_list, &m)?)?;
     m.add_function(wrap_pyfunction!(render_meme_statistics, &m)?)?;
+    m.add_function(wrap_pyfunction!(parse_bbcode, &m)?)?;
     register_image_operations_module(&m)?;
     parent_module.add_submodule(&m)?;
     parent_module
@@ -158,3 +161,67 @@ fn render_meme_statistics(
         handle_image_result(result)
     })
 }
+
+#[pyclass(skip_from_py_object)]
+#[derive(Clone)]
+struct BBCodeToken {
+    #[pyo3(get)]
+    kind: String,
+    #[pyo3(get)]
+    text: Option<String>,
+    #[pyo3(get)]
+    tag: Option<String>,
+    #[pyo3(get)]
+    value: Option<String>,
+}
+
+impl From<bbcode::BBCodeToken> for BBCodeToken {
+    fn from(token: bbcode::BBCodeToken) -> Self {
+        match token {
+            bbcode::BBCodeToken::Text(text) => Self {
+                kind: "text".to_string(),
+                text: Some(text),
+                tag: None,
+                value: None,
+            },
+            bbcode::BBCodeToken::OpenTag(tag) => Self {
+                kind: "open_tag".to_string(),
+                text: None,
+                tag: Some(tag.name().to_string()),
+                value: tag.value(),
+            },
+            bbcode::BBCodeToken::CloseTag(name) => Self {
+                kind: "close_tag".to_string(),
+                text: None,
+                tag: Some(name),
+                value: None,
+            },
+        }
+    }
+}
+
+#[pyclass(skip_from_py_object)]
+#[derive(Clone)]
+struct BBCodeError {
+    #[pyo3(get)]
+    position: usize,
+    #[pyo3(get)]
+    message: String,
+}
+
+#[derive(IntoPyObject, Clone)]
+enum BBCodeResult {
+    Ok(Vec<BBCodeToken>),
+    Err(BBCodeError),
+}
+
+#[pyfunction]
+fn parse_bbcode(text: &str) -> BBCodeResult {
+    match bbcode::parse_bbcode_strict(text) {
+        Ok(tokens) => BBCodeResult::Ok(tokens.into_iter().map(Into::into).collect()),
+        Err(error) => BBCodeResult::Err(BBCodeError {
+            position: error.position,
+            message: error.to_string(),
+        }),
+    }
+}
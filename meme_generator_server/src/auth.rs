@@ -0,0 +1,416 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{FromRequestParts, Json, RawPathParams, Request},
+    http::{
+        HeaderValue, Method,
+        header::{AUTHORIZATION, RETRY_AFTER, WWW_AUTHENTICATE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    config::{ApiKeyConfig, CONFIG},
+    errors::{ADMIN_REQUIRED, QUOTA_EXCEEDED, RATE_LIMITED, UNAUTHORIZED, meme_forbidden},
+    jobs::wake_workers,
+};
+
+static API_KEYS: LazyLock<HashMap<String, Arc<ApiKey>>> = LazyLock::new(|| {
+    CONFIG
+        .server
+        .auth
+        .keys
+        .iter()
+        .map(|config| (config.key.clone(), Arc::new(ApiKey::new(config.clone()))))
+        .collect()
+});
+
+/// Token bucket refilled with `rate_limit` tokens per minute.
+struct RateLimiter {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    /// Takes a token, or returns the seconds until one is available.
+    fn acquire(&mut self, per_minute: u32) -> Result<(), u64> {
+        let capacity = per_minute as f64;
+        let rate = capacity / 60.0;
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) / rate).ceil() as u64)
+        }
+    }
+}
+
+/// One of the `max_concurrent` slots of an API key, released on drop.
+pub(crate) struct KeySlot {
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for KeySlot {
+    fn drop(&mut self) {
+        // release the permit before waking the workers, so that they can take it
+        if self.permit.take().is_some() {
+            wake_workers();
+        }
+    }
+}
+
+#[derive(Default)]
+struct DailyUsage {
+    day: u64,
+    count: u64,
+}
+
+pub(crate) struct ApiKey {
+    config: ApiKeyConfig,
+    limiter: Mutex<RateLimiter>,
+    usage: Mutex<DailyUsage>,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ApiKeyUsage {
+    name: String,
+    admin: bool,
+    rate_limit: Option<u32>,
+    daily_quota: Option<u64>,
+    used_today: u64,
+    max_concurrent: Option<usize>,
+    running: usize,
+}
+
+impl ApiKey {
+    pub(crate) fn new(config: ApiKeyConfig) -> Self {
+        Self {
+            limiter: Mutex::new(RateLimiter {
+                tokens: config.rate_limit.unwrap_or_default() as f64,
+                updated_at: Instant::now(),
+            }),
+            usage: Mutex::new(DailyUsage::default()),
+            semaphore: config
+                .max_concurrent
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            config,
+        }
+    }
+
+    /// Whether the key may generate the meme `key`.
+    pub(crate) fn allows_meme(&self, key: &str) -> bool {
+        let key = key.to_string();
+        if self.config.denied_memes.contains(&key) {
+            return false;
+        }
+        self.config.allowed_memes.is_empty() || self.config.allowed_memes.contains(&key)
+    }
+
+    /// Waits for a free slot, requests, jobs and batch items share the
+    /// `max_concurrent` slots of the key.
+    pub(crate) async fn acquire_slot(&self) -> KeySlot {
+        let permit = match &self.semaphore {
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.unwrap()),
+            None => None,
+        };
+        KeySlot { permit }
+    }
+
+    /// Takes a free slot without waiting, or returns `None` if all slots are taken.
+    pub(crate) fn try_acquire_slot(&self) -> Option<KeySlot> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => Some(semaphore.clone().try_acquire_owned().ok()?),
+            None => None,
+        };
+        Some(KeySlot { permit })
+    }
+
+    fn check_rate_limit(&self) -> Result<(), u64> {
+        match self.config.rate_limit {
+            Some(per_minute) if per_minute > 0 => self.limiter.lock().unwrap().acquire(per_minute),
+            Some(_) => Err(60),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts `count` generations against the daily quota, either all of
+    /// them or none.
+    pub(crate) fn check_quota(&self, count: u64) -> Result<(), u64> {
+        let day = today();
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != day {
+            *usage = DailyUsage { day, count: 0 };
+        }
+        if let Some(quota) = self.config.daily_quota
+            && usage.count + count > quota
+        {
+            return Err(quota);
+        }
+        usage.count += count;
+        Ok(())
+    }
+
+    fn usage(&self) -> ApiKeyUsage {
+        let usage = self.usage.lock().unwrap();
+        let used_today = if usage.day == today() { usage.count } else { 0 };
+        ApiKeyUsage {
+            name: self.config.name.clone(),
+            admin: self.config.admin,
+            rate_limit: self.config.rate_limit,
+            daily_quota: self.config.daily_quota,
+            used_today,
+            max_concurrent: self.config.max_concurrent,
+            running: match (&self.semaphore, self.config.max_concurrent) {
+                (Some(semaphore), Some(max)) => max.max(1) - semaphore.available_permits(),
+                _ => 0,
+            },
+        }
+    }
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        / (24 * 60 * 60)
+}
+
+/// Requests that render memes and count against the daily quota.
+fn is_generation_request(method: &Method, path: &str) -> bool {
+    method == Method::POST && (path.starts_with("/memes/") || path == "/jobs")
+}
+
+/// Batches take quota and key slots for each item in the handler.
+fn is_batch_request(method: &Method, path: &str) -> bool {
+    method == Method::POST && path == "/memes/batch"
+}
+
+fn unauthorized() -> Response {
+    let mut response = UNAUTHORIZED
+        .error("Missing or invalid API key", json!({}))
+        .into_response();
+    response
+        .headers_mut()
+        .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+fn rate_limited(retry_after: u64) -> Response {
+    let mut response = RATE_LIMITED
+        .error(
+            format!("Rate limit exceeded, retry after {retry_after} seconds"),
+            json!({ "retry_after": retry_after }),
+        )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+pub(crate) fn quota_exceeded(daily_quota: u64) -> Response {
+    QUOTA_EXCEEDED
+        .error(
+            format!("Daily quota of {daily_quota} requests exceeded"),
+            json!({ "daily_quota": daily_quota }),
+        )
+        .into_response()
+}
+
+fn find_key(request: &Request) -> Option<Arc<ApiKey>> {
+    let headers = request.headers();
+    let key = headers
+        .get(CONFIG.server.auth.header.as_str())
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+        })?;
+    API_KEYS.get(key.trim()).cloned()
+}
+
+/// Authenticates the request and applies the limits of its API key.
+///
+/// The key is stored in the request extensions, so that handlers taking
+/// meme keys from the body can check them with [`ApiKey::allows_meme`].
+pub(crate) async fn require_key(request: Request, next: Next) -> Response {
+    if !CONFIG.server.auth.enabled {
+        return next.run(request).await;
+    }
+    let Some(api_key) = find_key(&request) else {
+        return unauthorized();
+    };
+
+    let (mut parts, body) = request.into_parts();
+    if let Ok(params) = RawPathParams::from_request_parts(&mut parts, &()).await
+        && let Some((_, key)) = params.iter().find(|(name, _)| *name == "key")
+        && !api_key.allows_meme(key)
+    {
+        return meme_forbidden(key).into_response();
+    }
+    let mut request = Request::from_parts(parts, body);
+
+    if let Err(retry_after) = api_key.check_rate_limit() {
+        return rate_limited(retry_after);
+    }
+    if is_batch_request(request.method(), request.uri().path()) {
+        request.extensions_mut().insert(api_key);
+        return next.run(request).await;
+    }
+    if is_generation_request(request.method(), request.uri().path())
+        && let Err(daily_quota) = api_key.check_quota(1)
+    {
+        return quota_exceeded(daily_quota);
+    }
+
+    let _slot = api_key.acquire_slot().await;
+    request.extensions_mut().insert(api_key);
+    next.run(request).await
+}
+
+/// Only lets keys with `admin = true` through. Admin routes are closed
+/// when authentication is disabled, `/metrics` is public then.
+pub(crate) async fn require_admin(request: Request, next: Next) -> Response {
+    let is_admin = request
+        .extensions()
+        .get::<Arc<ApiKey>>()
+        .is_some_and(|api_key| api_key.config.admin);
+    if !is_admin {
+        return ADMIN_REQUIRED
+            .error("Admin scope required", json!({}))
+            .into_response();
+    }
+    next.run(request).await
+}
+
+pub(crate) async fn key_usage() -> Response {
+    let mut usages = API_KEYS
+        .values()
+        .map(|api_key| api_key.usage())
+        .collect::<Vec<_>>();
+    usages.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(usages).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn limiter(tokens: f64) -> RateLimiter {
+        RateLimiter {
+            tokens,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn api_key(config: &str) -> ApiKey {
+        ApiKey::new(toml::from_str(&format!("key = \"test\"\n{config}")).unwrap())
+    }
+
+    #[test]
+    fn rate_limiter_allows_burst_up_to_capacity() {
+        let mut limiter = limiter(3.0);
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(3), Ok(()));
+        }
+        // one token every 20 seconds
+        assert_eq!(limiter.acquire(3), Err(20));
+    }
+
+    #[test]
+    fn rate_limiter_refills_over_time() {
+        let mut limiter = limiter(0.0);
+        assert_eq!(limiter.acquire(60), Err(1));
+
+        limiter.updated_at -= Duration::from_secs(2);
+        assert_eq!(limiter.acquire(60), Ok(()));
+        assert_eq!(limiter.acquire(60), Ok(()));
+        assert!(limiter.acquire(60).is_err());
+    }
+
+    #[test]
+    fn rate_limiter_caps_tokens_at_capacity() {
+        let mut limiter = limiter(0.0);
+        limiter.updated_at -= Duration::from_secs(600);
+        for _ in 0..2 {
+            assert_eq!(limiter.acquire(2), Ok(()));
+        }
+        assert!(limiter.acquire(2).is_err());
+    }
+
+    #[test]
+    fn zero_rate_limit_rejects_all_requests() {
+        let api_key = api_key("rate_limit = 0");
+        assert_eq!(api_key.check_rate_limit(), Err(60));
+    }
+
+    #[test]
+    fn daily_quota_counts_requests() {
+        let api_key = api_key("daily_quota = 2");
+        assert_eq!(api_key.check_quota(1), Ok(()));
+        assert_eq!(api_key.check_quota(1), Ok(()));
+        assert_eq!(api_key.check_quota(1), Err(2));
+        assert_eq!(api_key.usage().used_today, 2);
+    }
+
+    #[test]
+    fn daily_quota_charges_batches_as_a_whole() {
+        let api_key = api_key("daily_quota = 5");
+        assert_eq!(api_key.check_quota(3), Ok(()));
+        assert_eq!(api_key.check_quota(3), Err(5));
+        assert_eq!(api_key.usage().used_today, 3);
+        assert_eq!(api_key.check_quota(2), Ok(()));
+        assert_eq!(api_key.usage().used_today, 5);
+    }
+
+    #[test]
+    fn allowed_and_denied_memes() {
+        let api_key = api_key(
+            r#"
+            allowed_memes = ["petpet", "jiji_king"]
+            denied_memes = ["jiji_king"]
+            "#,
+        );
+        assert!(api_key.allows_meme("petpet"));
+        assert!(!api_key.allows_meme("jiji_king"));
+        assert!(!api_key.allows_meme("other"));
+    }
+
+    #[test]
+    fn running_counts_held_permits() {
+        let api_key = api_key("max_concurrent = 2");
+        let slot = api_key.try_acquire_slot().unwrap();
+        let _other = api_key.try_acquire_slot().unwrap();
+        assert_eq!(api_key.usage().running, 2);
+        assert!(api_key.try_acquire_slot().is_none());
+        drop(slot);
+        assert_eq!(api_key.usage().running, 1);
+    }
+
+    #[test]
+    fn keys_without_limit_always_have_a_slot() {
+        let api_key = api_key("");
+        let _slots = (0..3)
+            .map(|_| api_key.try_acquire_slot().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(api_key.usage().running, 0);
+    }
+}
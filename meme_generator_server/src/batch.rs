@@ -0,0 +1,216 @@
+use std::{
+    io::{Cursor, Write},
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::{Extension, Json},
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::task::spawn_blocking;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use meme_generator::batch::{Batch, BatchError, BatchItem};
+
+use crate::{
+    auth::{ApiKey, quota_exceeded},
+    config::CONFIG,
+    errors::{
+        ErrorResponse, IMAGE_ENCODE_ERROR, IMAGE_INDEX_OUT_OF_RANGE, TOO_MANY_BATCH_ITEMS,
+        meme_forbidden, meme_not_found,
+    },
+    extract::ApiJson,
+    metrics::record_error,
+    server::{
+        InlineImage, InlineMemeRequest, acquire_permit, handle_error, handle_server_error,
+        load_inline_request, run_generation, store_image,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BatchRequest {
+    /// Images shared by all items
+    #[serde(default)]
+    images: Vec<InlineImage>,
+    items: Vec<BatchItem>,
+    /// Return a zip archive of the results instead of image ids
+    #[serde(default)]
+    zip: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BatchItemResponse {
+    key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorResponse>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BatchResponse {
+    results: Vec<BatchItemResponse>,
+}
+
+fn handle_batch_error(error: BatchError) -> ErrorResponse {
+    match error {
+        BatchError::Meme(err) => handle_error(err),
+        BatchError::MemeNotFound(key) => meme_not_found(&key),
+        BatchError::ImageIndexOutOfRange(index, len) => IMAGE_INDEX_OUT_OF_RANGE
+            .error(format!("{error}"), json!({ "index": index, "len": len })),
+        BatchError::TooManyItems(max, actual) => {
+            TOO_MANY_BATCH_ITEMS.error(format!("{error}"), json!({ "max": max, "actual": actual }))
+        }
+    }
+}
+
+/// Item errors are returned inside a successful response, so they are
+/// counted here instead of by the request metrics.
+fn item_error(error: ErrorResponse) -> ErrorResponse {
+    record_error(error.code());
+    error
+}
+
+fn build_zip(
+    items: &[BatchItem],
+    results: Vec<Result<Vec<u8>, BatchError>>,
+) -> Result<Vec<u8>, zip::result::ZipError> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    // images are already compressed
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut responses = Vec::new();
+    for (i, (item, result)) in items.iter().zip(results).enumerate() {
+        match result {
+            Ok(data) => {
+                let extension = infer::get(&data)
+                    .map(|kind| kind.extension())
+                    .unwrap_or("bin");
+                let file = format!("{:03}_{}.{extension}", i + 1, item.key);
+                writer.start_file(file.as_str(), options)?;
+                writer.write_all(&data)?;
+                responses.push(BatchItemResponse {
+                    key: item.key.clone(),
+                    image_id: None,
+                    file: Some(file),
+                    error: None,
+                });
+            }
+            Err(err) => responses.push(BatchItemResponse {
+                key: item.key.clone(),
+                image_id: None,
+                file: None,
+                error: Some(item_error(handle_batch_error(err))),
+            }),
+        }
+    }
+    writer.start_file("results.json", options)?;
+    let manifest =
+        serde_json::to_vec_pretty(&BatchResponse { results: responses }).unwrap_or_default();
+    writer.write_all(&manifest)?;
+    Ok(writer.finish()?.into_inner())
+}
+
+pub(crate) async fn meme_batch(
+    api_key: Option<Extension<Arc<ApiKey>>>,
+    ApiJson(payload): ApiJson<BatchRequest>,
+) -> Response {
+    let max_batch_items = CONFIG.server.max_batch_items;
+    if payload.items.len() > max_batch_items {
+        let error = BatchError::TooManyItems(max_batch_items, payload.items.len());
+        return handle_batch_error(error).into_response();
+    }
+
+    let api_key = api_key.map(|Extension(api_key)| api_key);
+    if let Some(api_key) = &api_key {
+        if let Some(item) = payload
+            .items
+            .iter()
+            .find(|item| !api_key.allows_meme(&item.key))
+        {
+            return meme_forbidden(&item.key).into_response();
+        }
+        // every item counts as a generation request
+        if let Err(daily_quota) = api_key.check_quota(payload.items.len() as u64) {
+            return quota_exceeded(daily_quota);
+        }
+    }
+
+    let input = match load_inline_request(InlineMemeRequest {
+        images: payload.images,
+        texts: Vec::new(),
+        options: Default::default(),
+        seed: None,
+    })
+    .await
+    {
+        Ok(input) => input,
+        Err(err) => return handle_server_error(err).into_response(),
+    };
+
+    let batch = Arc::new(Batch::new(input.images));
+    let items = Arc::new(payload.items);
+    let handles = (0..items.len())
+        .map(|i| {
+            let batch = batch.clone();
+            let items = items.clone();
+            let api_key = api_key.clone();
+            tokio::spawn(async move {
+                let _slot = match &api_key {
+                    Some(api_key) => Some(api_key.acquire_slot().await),
+                    None => None,
+                };
+                let _permit = acquire_permit().await;
+                let key = items[i].key.clone();
+                run_generation(&key, move || batch.generate(&items[i])).await
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut results = Vec::new();
+    for handle in handles {
+        results.push(handle.await.unwrap());
+    }
+
+    if payload.zip {
+        let zip = spawn_blocking(move || build_zip(&items, results))
+            .await
+            .unwrap();
+        return match zip {
+            Ok(data) => Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, "application/zip")
+                .body(Body::from(data))
+                .unwrap(),
+            Err(err) => IMAGE_ENCODE_ERROR
+                .error(
+                    format!("Failed to build the zip archive: {err}"),
+                    json!({ "error": err.to_string() }),
+                )
+                .into_response(),
+        };
+    }
+
+    let mut responses = Vec::new();
+    for (item, result) in items.iter().zip(results) {
+        let mut response = BatchItemResponse {
+            key: item.key.clone(),
+            image_id: None,
+            file: None,
+            error: None,
+        };
+        match result {
+            Ok(data) => match store_image(data).await {
+                Ok(id) => response.image_id = Some(id),
+                Err(err) => response.error = Some(item_error(handle_server_error(err))),
+            },
+            Err(err) => response.error = Some(item_error(handle_batch_error(err))),
+        }
+        responses.push(response);
+    }
+    Json(BatchResponse { results: responses }).into_response()
+}
@@ -1,12 +1,13 @@
 use std::{
     net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
     sync::LazyLock,
 };
 
 use serde::Deserialize;
 use tracing::warn;
 
-use meme_generator::read_config_file;
+use meme_generator::{batch::MAX_BATCH_ITEMS, read_config_file};
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -31,6 +32,31 @@ pub struct ServerConfig {
     pub body_limit: usize,
     /// Maximum number of concurrent image generation tasks (default: 16)
     pub max_concurrent_tasks: usize,
+    /// Number of workers processing queued jobs (default: 4)
+    pub job_workers: usize,
+    /// Maximum number of queued jobs, new jobs are rejected when full (default: 100)
+    pub max_queue_depth: usize,
+    /// Seconds to keep finished jobs before they are removed (default: 600)
+    pub job_ttl_secs: u64,
+    /// Maximum number of items in one batch request (default: 100)
+    pub max_batch_items: usize,
+    /// Serve an interactive API documentation page at `/docs`, the page loads
+    /// swagger-ui from `docs_assets_url` in the browser (default: false)
+    pub docs: bool,
+    /// Base url of the `swagger-ui-dist` files used by `/docs`, point it at a
+    /// self-hosted copy to avoid the public CDN
+    /// (default: "https://cdn.jsdelivr.net/npm/swagger-ui-dist@5")
+    pub docs_assets_url: String,
+    /// Serve `/metrics` without authentication; otherwise it requires an
+    /// admin key like the `/admin` routes. `/metrics` is always served
+    /// without a key when authentication is disabled (default: false)
+    pub public_metrics: bool,
+    /// Policy for images loaded from urls and local paths
+    pub fetch: FetchConfig,
+    /// API key authentication
+    pub auth: AuthConfig,
+    /// Storage of uploaded and generated images
+    pub store: StoreConfig,
 }
 
 impl Default for ServerConfig {
@@ -40,6 +66,193 @@ impl Default for ServerConfig {
             port: 2233,
             body_limit: 20 * 1024 * 1024,
             max_concurrent_tasks: 16,
+            job_workers: 4,
+            max_queue_depth: 100,
+            job_ttl_secs: 600,
+            max_batch_items: MAX_BATCH_ITEMS,
+            docs: false,
+            docs_assets_url: "https://cdn.jsdelivr.net/npm/swagger-ui-dist@5".to_string(),
+            public_metrics: false,
+            fetch: FetchConfig::default(),
+            auth: AuthConfig::default(),
+            store: StoreConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FetchConfig {
+    /// Seconds to wait for a connection to be established (default: 5)
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for each read from the connection (default: 10)
+    pub read_timeout_secs: u64,
+    /// Maximum size of a downloaded image in bytes, the download is aborted
+    /// as soon as it grows beyond this (default: 20MB)
+    pub max_bytes: usize,
+    /// Maximum number of redirects to follow (default: 5)
+    pub max_redirects: usize,
+    /// Allowed url schemes (default: ["http", "https"])
+    pub allowed_schemes: Vec<String>,
+    /// Allowed hosts, a host also matches its subdomains; empty allows all hosts (default: [])
+    pub allowed_hosts: Vec<String>,
+    /// Denied hosts, checked before `allowed_hosts` (default: [])
+    pub denied_hosts: Vec<String>,
+    /// Refuse to connect to loopback, private, link-local and other
+    /// non-public addresses, including after redirects (default: true)
+    pub block_private_ips: bool,
+    /// Allowed `Content-Type` prefixes of the response; empty allows any (default: ["image/", "application/octet-stream"])
+    pub allowed_content_types: Vec<String>,
+    /// Allow images to be read from local paths on the server, any file the
+    /// server can read becomes readable by clients (default: false)
+    pub allow_local_paths: bool,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        FetchConfig {
+            connect_timeout_secs: 5,
+            read_timeout_secs: 10,
+            max_bytes: 20 * 1024 * 1024,
+            max_redirects: 5,
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            block_private_ips: true,
+            allowed_content_types: vec![
+                "image/".to_string(),
+                "application/octet-stream".to_string(),
+            ],
+            allow_local_paths: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Require an API key for all routes except `/meme/version`, `/errors`, `/healthz`,
+    /// `/readyz`, `/openapi.json` and `/docs`. `/metrics` and the `/admin` routes need a
+    /// key with `admin = true`, unless `public_metrics` is set for `/metrics`. When
+    /// disabled, `/metrics` is public and the `/admin` routes are closed (default: false)
+    pub enabled: bool,
+    /// Header carrying the API key, `Authorization: Bearer <key>` is accepted as well (default: "X-API-Key")
+    pub header: String,
+    /// Accepted API keys (default: [])
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            enabled: false,
+            header: "X-API-Key".to_string(),
+            keys: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    /// Name of the key owner, shown in logs and usage (default: "")
+    #[serde(default)]
+    pub name: String,
+    /// Allow access to the `/admin` routes (default: false)
+    #[serde(default)]
+    pub admin: bool,
+    /// Maximum requests per minute, unlimited when not set (default: None)
+    #[serde(default)]
+    pub rate_limit: Option<u32>,
+    /// Maximum generation requests per UTC day, each batch item counts as
+    /// one request; unlimited when not set (default: None)
+    #[serde(default)]
+    pub daily_quota: Option<u64>,
+    /// Maximum requests, jobs and batch items of this key handled at the same
+    /// time, i.e. its share of `max_concurrent_tasks`; further ones wait (default: None)
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    /// Memes the key may generate, empty allows all memes (default: [])
+    #[serde(default)]
+    pub allowed_memes: Vec<String>,
+    /// Memes the key may not generate, checked before `allowed_memes` (default: [])
+    #[serde(default)]
+    pub denied_memes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreBackend {
+    Memory,
+    Disk,
+    S3,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    /// Where images are kept: "memory", "disk" or "s3" (default: "disk")
+    pub backend: StoreBackend,
+    /// Seconds an image is kept after it was last accessed, also used for
+    /// `Cache-Control` on `/image/{id}` (default: 600)
+    pub ttl_secs: u64,
+    /// Maximum total size of the memory and disk backends in bytes, the
+    /// least recently used images are evicted first (default: 1GB)
+    pub max_bytes: u64,
+    /// Directory of the disk backend (default: "<MEME_HOME>/tmp")
+    pub disk_dir: Option<PathBuf>,
+    /// Keep the images of the disk backend across restarts, otherwise the
+    /// directory is emptied on start (default: false)
+    pub persist: bool,
+    /// Settings of the s3 backend
+    pub s3: S3Config,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            backend: StoreBackend::Disk,
+            ttl_secs: 600,
+            max_bytes: 1024 * 1024 * 1024,
+            disk_dir: None,
+            persist: false,
+            s3: S3Config::default(),
+        }
+    }
+}
+
+/// An S3-compatible object store. Expiration is left to the lifecycle
+/// rules of the bucket.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    /// Endpoint url (default: "http://127.0.0.1:9000")
+    pub endpoint: String,
+    /// Bucket name (default: "meme-generator")
+    pub bucket: String,
+    /// Region used for signing (default: "us-east-1")
+    pub region: String,
+    /// Access key id (default: "")
+    pub access_key: String,
+    /// Secret access key (default: "")
+    pub secret_key: String,
+    /// Prefix of the object keys (default: "images/")
+    pub prefix: String,
+    /// Address the bucket in the path instead of the host name, needed by
+    /// most self-hosted stores (default: true)
+    pub path_style: bool,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        S3Config {
+            endpoint: "http://127.0.0.1:9000".to_string(),
+            bucket: "meme-generator".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            prefix: "images/".to_string(),
+            path_style: true,
         }
     }
 }
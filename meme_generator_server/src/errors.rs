@@ -0,0 +1,394 @@
+use axum::{
+    extract::Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::metrics::ErrorCode;
+
+/// Body of all error responses, built from a kind in [`ERROR_CATALOG`]
+/// with [`ErrorKind::error`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ErrorResponse {
+    code: u16,
+    message: String,
+    data: Value,
+}
+
+impl ErrorResponse {
+    pub(crate) fn code(&self) -> u16 {
+        self.code
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let status = status_for_code(self.code);
+        let code = ErrorCode(self.code);
+        let mut response = (status, Json(self)).into_response();
+        response.extensions_mut().insert(code);
+        response
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ErrorKind {
+    /// Value of the `code` field in the error body
+    code: u16,
+    /// HTTP status of the response
+    status: u16,
+    name: &'static str,
+    description: &'static str,
+    /// Keys of the `data` field in the error body
+    data: &'static [&'static str],
+}
+
+impl ErrorKind {
+    pub(crate) fn error(&self, message: impl Into<String>, data: Value) -> ErrorResponse {
+        ErrorResponse {
+            code: self.code,
+            message: message.into(),
+            data,
+        }
+    }
+}
+
+const fn kind(
+    code: u16,
+    status: StatusCode,
+    name: &'static str,
+    description: &'static str,
+    data: &'static [&'static str],
+) -> ErrorKind {
+    ErrorKind {
+        code,
+        status: status.as_u16(),
+        name,
+        description,
+        data,
+    }
+}
+
+/// Declares a constant for each kind of error and lists all of them in
+/// `ERROR_CATALOG`. Error responses are only built from these constants, so
+/// every code the server emits is in the catalog.
+macro_rules! error_catalog {
+    ($($name:ident => $kind:expr),* $(,)?) => {
+        $(pub(crate) const $name: ErrorKind = $kind;)*
+
+        pub(crate) const ERROR_CATALOG: &[ErrorKind] = &[$($name),*];
+    };
+}
+
+error_catalog! {
+    REQUEST_ERROR => kind(
+        410,
+        StatusCode::BAD_GATEWAY,
+        "RequestError",
+        "Failed to download an image from the given url",
+        &["error"],
+    ),
+    FETCH_DENIED => kind(
+        411,
+        StatusCode::FORBIDDEN,
+        "FetchDenied",
+        "The image url or path is refused by the server fetch policy",
+        &["reason"],
+    ),
+    FETCH_TOO_LARGE => kind(
+        412,
+        StatusCode::PAYLOAD_TOO_LARGE,
+        "FetchTooLarge",
+        "The downloaded image is larger than the configured maximum",
+        &["max"],
+    ),
+    IO_ERROR => kind(
+        420,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "IOError",
+        "Failed to read or write a file on the server",
+        &["error"],
+    ),
+    IMAGE_NOT_FOUND => kind(
+        421,
+        StatusCode::NOT_FOUND,
+        "ImageNotFound",
+        "The image id or path does not exist, or the image has expired",
+        &["id", "error"],
+    ),
+    STORE_ERROR => kind(
+        422,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "StoreError",
+        "The image store backend failed",
+        &["error"],
+    ),
+    INVALID_REQUEST => kind(
+        430,
+        StatusCode::BAD_REQUEST,
+        "InvalidRequest",
+        "The request body is malformed or misses a required field",
+        &["error"],
+    ),
+    UNAUTHORIZED => kind(
+        440,
+        StatusCode::UNAUTHORIZED,
+        "Unauthorized",
+        "The API key is missing or unknown",
+        &[],
+    ),
+    MEME_FORBIDDEN => kind(
+        441,
+        StatusCode::FORBIDDEN,
+        "MemeForbidden",
+        "The API key is not allowed to use the meme",
+        &["key"],
+    ),
+    ADMIN_REQUIRED => kind(
+        442,
+        StatusCode::FORBIDDEN,
+        "AdminRequired",
+        "The route requires an API key with the admin scope",
+        &[],
+    ),
+    RATE_LIMITED => kind(
+        443,
+        StatusCode::TOO_MANY_REQUESTS,
+        "RateLimited",
+        "The API key exceeded its rate limit, see the Retry-After header",
+        &["retry_after"],
+    ),
+    QUOTA_EXCEEDED => kind(
+        444,
+        StatusCode::TOO_MANY_REQUESTS,
+        "QuotaExceeded",
+        "The API key used up its daily quota",
+        &["daily_quota"],
+    ),
+    NOT_ACCEPTABLE => kind(
+        450,
+        StatusCode::NOT_ACCEPTABLE,
+        "NotAcceptable",
+        "None of the image formats in the Accept header can be produced",
+        &["supported"],
+    ),
+    INTERNAL_ERROR => kind(
+        500,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "InternalError",
+        "The meme panicked while generating, this is a bug in the meme",
+        &["key", "error"],
+    ),
+    IMAGE_DECODE_ERROR => kind(
+        510,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ImageDecodeError",
+        "An input image could not be decoded",
+        &["error"],
+    ),
+    IMAGE_LIMIT_EXCEEDED => kind(
+        511,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ImageLimitExceeded",
+        "An input image exceeds the configured decoder limits",
+        &["limit", "max", "actual"],
+    ),
+    IMAGE_ENCODE_ERROR => kind(
+        520,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "ImageEncodeError",
+        "The generated image or batch archive could not be encoded",
+        &["error"],
+    ),
+    IMAGE_ASSET_MISSING => kind(
+        530,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "ImageAssetMissing",
+        "A resource image of the meme is missing on the server",
+        &["path"],
+    ),
+    DESERIALIZE_ERROR => kind(
+        540,
+        StatusCode::BAD_REQUEST,
+        "DeserializeError",
+        "The meme options could not be parsed",
+        &["error"],
+    ),
+    IMAGE_NUMBER_MISMATCH => kind(
+        550,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ImageNumberMismatch",
+        "The number of images does not match the meme",
+        &["min", "max", "actual"],
+    ),
+    TEXT_NUMBER_MISMATCH => kind(
+        551,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "TextNumberMismatch",
+        "The number of texts does not match the meme",
+        &["min", "max", "actual"],
+    ),
+    IMAGE_INDEX_OUT_OF_RANGE => kind(
+        552,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "ImageIndexOutOfRange",
+        "A batch item refers to a shared image that does not exist",
+        &["index", "len"],
+    ),
+    TOO_MANY_BATCH_ITEMS => kind(
+        553,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "TooManyBatchItems",
+        "A batch request has more items than `max_batch_items`",
+        &["max", "actual"],
+    ),
+    TEXT_OVER_LENGTH => kind(
+        560,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "TextOverLength",
+        "A text is too long to fit in the meme",
+        &["text"],
+    ),
+    BBCODE_ERROR => kind(
+        561,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "BBCodeError",
+        "The BBCode text has an unknown or invalid tag, or unpaired tags",
+        &["position", "error"],
+    ),
+    MEME_FEEDBACK => kind(
+        570,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "MemeFeedback",
+        "The meme rejected the input, the message is meant for end users",
+        &["feedback"],
+    ),
+    MEME_NOT_FOUND => kind(
+        580,
+        StatusCode::NOT_FOUND,
+        "MemeNotFound",
+        "No meme with the given key is loaded",
+        &["key"],
+    ),
+    JOB_NOT_FOUND => kind(
+        590,
+        StatusCode::NOT_FOUND,
+        "JobNotFound",
+        "No job with the given id exists, or the job has expired",
+        &["id"],
+    ),
+    JOB_QUEUE_FULL => kind(
+        591,
+        StatusCode::SERVICE_UNAVAILABLE,
+        "JobQueueFull",
+        "The job queue has reached its maximum depth",
+        &["max_queue_depth"],
+    ),
+}
+
+/// Name of an error `code` in the catalog.
+pub(crate) fn error_name(code: u16) -> &'static str {
+    ERROR_CATALOG
+        .iter()
+        .find(|kind| kind.code == code)
+        .map(|kind| kind.name)
+        .unwrap_or("Unknown")
+}
+
+/// Codes of all errors in the catalog.
+pub(crate) fn error_codes() -> impl Iterator<Item = u16> {
+    ERROR_CATALOG.iter().map(|kind| kind.code)
+}
+
+/// HTTP status for an error `code`, unknown codes are internal faults.
+pub(crate) fn status_for_code(code: u16) -> StatusCode {
+    ERROR_CATALOG
+        .iter()
+        .find(|kind| kind.code == code)
+        .and_then(|kind| StatusCode::from_u16(kind.status).ok())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+pub(crate) fn invalid_request(error: impl Into<String>) -> ErrorResponse {
+    let error = error.into();
+    INVALID_REQUEST.error(
+        format!("Invalid request: {error}"),
+        json!({ "error": error }),
+    )
+}
+
+pub(crate) fn meme_not_found(key: &str) -> ErrorResponse {
+    MEME_NOT_FOUND.error(format!("Meme not found: {key}"), json!({ "key": key }))
+}
+
+pub(crate) fn meme_forbidden(key: &str) -> ErrorResponse {
+    MEME_FORBIDDEN.error(
+        format!("The API key is not allowed to use meme {key}"),
+        json!({ "key": key }),
+    )
+}
+
+pub(crate) fn not_acceptable() -> ErrorResponse {
+    let supported = ["image/png", "image/jpeg", "image/webp", "image/gif"];
+    NOT_ACCEPTABLE.error(
+        format!(
+            "No acceptable image format, supported: {}",
+            supported.join(", ")
+        ),
+        json!({ "supported": supported }),
+    )
+}
+
+pub(crate) fn job_not_found(id: &str) -> ErrorResponse {
+    JOB_NOT_FOUND.error(format!("Job not found: {id}"), json!({ "id": id }))
+}
+
+pub(crate) async fn error_catalog() -> Response {
+    Json(ERROR_CATALOG).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn catalog_codes_are_unique() {
+        let mut seen = HashSet::new();
+        for code in error_codes() {
+            assert!(seen.insert(code), "code {code} is listed twice");
+        }
+    }
+
+    #[test]
+    fn catalog_names_are_unique() {
+        let mut seen = HashSet::new();
+        for kind in ERROR_CATALOG {
+            assert!(seen.insert(kind.name), "{} is listed twice", kind.name);
+        }
+    }
+
+    #[test]
+    fn errors_use_the_status_of_their_kind() {
+        for kind in ERROR_CATALOG {
+            let response = kind.error("test", json!({})).into_response();
+            assert_eq!(response.status().as_u16(), kind.status, "{}", kind.name);
+            assert_eq!(
+                response.extensions().get::<ErrorCode>().map(|code| code.0),
+                Some(kind.code)
+            );
+        }
+        assert_eq!(status_for_code(999), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn rejections_use_invalid_request() {
+        let error = invalid_request("missing field `image_id`");
+        assert_eq!(error.code(), INVALID_REQUEST.code);
+        assert_eq!(status_for_code(error.code()), StatusCode::BAD_REQUEST);
+        assert_eq!(error_name(error.code()), "InvalidRequest");
+    }
+}
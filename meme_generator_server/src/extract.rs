@@ -0,0 +1,180 @@
+//! Extractors that report rejections as `InvalidRequest` error bodies
+//! instead of axum's plain text responses.
+
+use axum::{
+    extract::{
+        FromRequest, FromRequestParts, Json, Multipart, OptionalFromRequest, Query, Request,
+        multipart::MultipartError,
+        rejection::{JsonRejection, MultipartRejection, QueryRejection},
+    },
+    http::request::Parts,
+};
+
+use crate::errors::{ErrorResponse, invalid_request};
+
+/// [`Json`] body, rejected with code 430.
+pub(crate) struct ApiJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => Err(invalid_request(rejection.body_text())),
+        }
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for ApiJson<T>
+where
+    Json<T>: OptionalFromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        match <Json<T> as OptionalFromRequest<S>>::from_request(req, state).await {
+            Ok(value) => Ok(value.map(|Json(value)| ApiJson(value))),
+            Err(rejection) => Err(invalid_request(rejection.body_text())),
+        }
+    }
+}
+
+/// [`Multipart`] body, rejected with code 430.
+pub(crate) struct ApiMultipart(pub Multipart);
+
+impl<S> FromRequest<S> for ApiMultipart
+where
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Multipart::from_request(req, state).await {
+            Ok(multipart) => Ok(ApiMultipart(multipart)),
+            Err(rejection) => Err(multipart_rejection(rejection)),
+        }
+    }
+}
+
+/// [`Query`] string, rejected with code 430.
+pub(crate) struct ApiQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(ApiQuery(value)),
+            Err(rejection) => Err(invalid_request(rejection.body_text())),
+        }
+    }
+}
+
+pub(crate) fn multipart_rejection(rejection: MultipartRejection) -> ErrorResponse {
+    invalid_request(rejection.body_text())
+}
+
+/// Error while reading a multipart field.
+pub(crate) fn multipart_error(error: MultipartError) -> ErrorResponse {
+    invalid_request(error.body_text())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        http::StatusCode,
+        routing::{get, post},
+    };
+    use reqwest::Client;
+    use serde::Deserialize;
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Body {
+        #[allow(dead_code)]
+        image_id: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Params {
+        #[allow(dead_code)]
+        limit: u32,
+    }
+
+    async fn start_server() -> String {
+        let app = Router::new()
+            .route("/json", post(|ApiJson(_): ApiJson<Body>| async {}))
+            .route("/multipart", post(|ApiMultipart(_): ApiMultipart| async {}))
+            .route("/query", get(|ApiQuery(_): ApiQuery<Params>| async {}));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    async fn assert_invalid_request(response: reqwest::Response) {
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.json::<Value>().await.unwrap();
+        assert_eq!(body["code"], 430);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Invalid request")
+        );
+        assert!(body["data"]["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn rejections_are_json_errors() {
+        let base = start_server().await;
+        let client = Client::new();
+
+        let response = client
+            .post(format!("{base}/json"))
+            .header("content-type", "application/json")
+            .body("{\"image\": 1}")
+            .send()
+            .await
+            .unwrap();
+        assert_invalid_request(response).await;
+
+        let response = client
+            .post(format!("{base}/json"))
+            .body("not json")
+            .send()
+            .await
+            .unwrap();
+        assert_invalid_request(response).await;
+
+        let response = client
+            .post(format!("{base}/multipart"))
+            .header("content-type", "text/plain")
+            .body("file")
+            .send()
+            .await
+            .unwrap();
+        assert_invalid_request(response).await;
+
+        let response = client
+            .get(format!("{base}/query?limit=abc"))
+            .send()
+            .await
+            .unwrap();
+        assert_invalid_request(response).await;
+    }
+}
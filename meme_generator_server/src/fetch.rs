@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    error, fmt,
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
+    sync::LazyLock,
+    time::Duration,
+};
+
+use reqwest::{
+    Client, Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect::Policy,
+};
+use tokio::{fs, net::lookup_host};
+
+use crate::{config::CONFIG, server::ServerError};
+
+static FETCH_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    let config = &CONFIG.server.fetch;
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.read_timeout_secs))
+        .redirect(Policy::custom(|attempt| {
+            if attempt.previous().len() >= CONFIG.server.fetch.max_redirects {
+                let max_redirects = CONFIG.server.fetch.max_redirects;
+                return attempt.error(Blocked(format!("more than {max_redirects} redirects")));
+            }
+            match check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(err) => attempt.error(err),
+            }
+        }));
+    if config.block_private_ips {
+        builder = builder.dns_resolver(PublicResolver);
+    }
+    builder.build().unwrap()
+});
+
+/// A url or address refused by the fetch policy.
+#[derive(Debug)]
+struct Blocked(String);
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl error::Error for Blocked {}
+
+/// Resolves hosts with the system resolver and drops non-public addresses,
+/// so that a public name pointing at an internal address is refused as well.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(resolve_public(name.as_str().to_string()))
+    }
+}
+
+async fn resolve_public(host: String) -> Result<Addrs, Box<dyn error::Error + Send + Sync>> {
+    let addrs = lookup_host((host.as_str(), 0))
+        .await?
+        .filter(|addr| !is_blocked_ip(addr.ip()))
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        let err = Blocked(format!("{host} does not resolve to a public address"));
+        return Err(err.into());
+    }
+    Ok(Box::new(addrs.into_iter()))
+}
+
+fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // "this network" 0.0.0.0/8 and reserved 240.0.0.0/4
+                || a == 0
+                || a >= 240
+                // shared address space 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64)
+                // IETF protocol assignments 192.0.0.0/24
+                || (a == 192 && b == 0 && c == 0)
+                // benchmarking 198.18.0.0/15
+                || (a == 198 && (b & 0xfe) == 18)
+        }
+        IpAddr::V6(ip) => {
+            // IPv4-mapped ::ffff:a.b.c.d and IPv4-compatible ::a.b.c.d
+            if let Some(ip) = ip.to_ipv4() {
+                return is_blocked_ip(IpAddr::V4(ip));
+            }
+            let segments = ip.segments();
+            let embedded = |high: u16, low: u16| {
+                is_blocked_ip(IpAddr::V4(Ipv4Addr::from(
+                    (u32::from(high) << 16) | u32::from(low),
+                )))
+            };
+            match segments {
+                // NAT64 64:ff9b::/96 translates to the embedded IPv4 address
+                [0x64, 0xff9b, 0, 0, 0, 0, high, low] => return embedded(high, low),
+                // 6to4 2002::/16 tunnels to the IPv4 address in the next 32 bits
+                [0x2002, high, low, ..] => return embedded(high, low),
+                _ => {}
+            }
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // local-use NAT64 64:ff9b:1::/48
+                || segments[..3] == [0x64, 0xff9b, 1]
+                // documentation 2001:db8::/32
+                || segments[..2] == [0x2001, 0xdb8]
+                // unique local fc00::/7
+                || (segments[0] & 0xfe00) == 0xfc00
+                // link-local fe80::/10
+                || (segments[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// `pattern` matches the host itself and all of its subdomains.
+fn host_matches(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_start_matches("*.").to_ascii_lowercase();
+    host == pattern || host.ends_with(&format!(".{pattern}"))
+}
+
+fn check_url(url: &Url) -> Result<(), Blocked> {
+    let config = &CONFIG.server.fetch;
+    let scheme = url.scheme();
+    if !config
+        .allowed_schemes
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    {
+        return Err(Blocked(format!("scheme `{scheme}` is not allowed")));
+    }
+
+    let Some(host) = url.host_str() else {
+        return Err(Blocked("url has no host".to_string()));
+    };
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    if config
+        .denied_hosts
+        .iter()
+        .any(|pattern| host_matches(&host, pattern))
+    {
+        return Err(Blocked(format!("host `{host}` is denied")));
+    }
+    if !config.allowed_hosts.is_empty()
+        && !config
+            .allowed_hosts
+            .iter()
+            .any(|pattern| host_matches(&host, pattern))
+    {
+        return Err(Blocked(format!("host `{host}` is not allowed")));
+    }
+    // literal addresses never reach the resolver
+    if config.block_private_ips
+        && let Ok(ip) = host.parse::<IpAddr>()
+        && is_blocked_ip(ip)
+    {
+        return Err(Blocked(format!("address `{ip}` is not public")));
+    }
+    Ok(())
+}
+
+pub(crate) fn fetch_client() -> &'static Client {
+    &FETCH_CLIENT
+}
+
+/// Parses a url and checks it against the fetch policy.
+pub(crate) fn parse_url(url: &str) -> Result<Url, ServerError> {
+    let url = Url::parse(url).map_err(|err| ServerError::FetchDenied(format!("{err}")))?;
+    check_url(&url).map_err(|err| ServerError::FetchDenied(err.0))?;
+    Ok(url)
+}
+
+pub(crate) fn fetch_error(err: reqwest::Error) -> ServerError {
+    let mut source = error::Error::source(&err);
+    while let Some(inner) = source {
+        if let Some(blocked) = inner.downcast_ref::<Blocked>() {
+            return ServerError::FetchDenied(blocked.0.clone());
+        }
+        source = inner.source();
+    }
+    ServerError::RequestError(err)
+}
+
+/// Downloads an image under the fetch policy in `ServerConfig::fetch`.
+pub(crate) async fn download_url(
+    url: &str,
+    headers: Option<HashMap<String, String>>,
+) -> Result<Vec<u8>, ServerError> {
+    let config = &CONFIG.server.fetch;
+    let url = parse_url(url)?;
+
+    let headers = headers.unwrap_or_default();
+    let request = headers
+        .iter()
+        .filter(|(key, _)| !key.eq_ignore_ascii_case("host"))
+        .fold(FETCH_CLIENT.get(url), |request, (key, value)| {
+            request.header(key, value)
+        });
+    let mut response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_error)?;
+
+    if !config.allowed_content_types.is_empty() {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !content_type.is_empty()
+            && !config
+                .allowed_content_types
+                .iter()
+                .any(|prefix| content_type.starts_with(&prefix.to_ascii_lowercase()))
+        {
+            return Err(ServerError::FetchDenied(format!(
+                "content type `{content_type}` is not allowed"
+            )));
+        }
+    }
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > config.max_bytes as u64)
+    {
+        return Err(ServerError::FetchTooLarge(config.max_bytes));
+    }
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+        if data.len() + chunk.len() > config.max_bytes {
+            return Err(ServerError::FetchTooLarge(config.max_bytes));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Reads an image from a local path, if `allow_local_paths` is enabled.
+pub(crate) async fn read_local_path(path: &Path) -> Result<Vec<u8>, ServerError> {
+    let config = &CONFIG.server.fetch;
+    if !config.allow_local_paths {
+        return Err(ServerError::FetchDenied(
+            "reading images from local paths is disabled".to_string(),
+        ));
+    }
+    if fs::metadata(path).await?.len() > config.max_bytes as u64 {
+        return Err(ServerError::FetchTooLarge(config.max_bytes));
+    }
+    Ok(fs::read(path).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(ip: &str) -> bool {
+        is_blocked_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn blocks_non_public_ipv4() {
+        for ip in [
+            "0.0.0.0",
+            "10.1.2.3",
+            "100.64.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "172.16.0.1",
+            "192.0.0.8",
+            "192.0.2.1",
+            "192.168.1.1",
+            "198.18.0.1",
+            "224.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(blocked(ip), "{ip} should be blocked");
+        }
+        for ip in [
+            "1.1.1.1",
+            "8.8.8.8",
+            "100.128.0.1",
+            "192.0.1.1",
+            "198.20.0.1",
+        ] {
+            assert!(!blocked(ip), "{ip} should be allowed");
+        }
+    }
+
+    #[test]
+    fn blocks_non_public_ipv6() {
+        for ip in [
+            "::",
+            "::1",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "ff02::1",
+            "2001:db8::1",
+            "64:ff9b:1::1",
+        ] {
+            assert!(blocked(ip), "{ip} should be blocked");
+        }
+        for ip in ["2606:4700:4700::1111", "2001:4860:4860::8888"] {
+            assert!(!blocked(ip), "{ip} should be allowed");
+        }
+    }
+
+    #[test]
+    fn checks_ipv4_embedded_in_ipv6() {
+        // IPv4-mapped
+        assert!(blocked("::ffff:127.0.0.1"));
+        assert!(blocked("::ffff:10.0.0.1"));
+        assert!(!blocked("::ffff:8.8.8.8"));
+        // IPv4-compatible
+        assert!(blocked("::127.0.0.1"));
+        assert!(blocked("::169.254.169.254"));
+        // NAT64
+        assert!(blocked("64:ff9b::127.0.0.1"));
+        assert!(blocked("64:ff9b::a9fe:a9fe"));
+        assert!(!blocked("64:ff9b::8.8.8.8"));
+        // 6to4
+        assert!(blocked("2002:7f00:1::"));
+        assert!(blocked("2002:c0a8:101::1"));
+        assert!(!blocked("2002:808:808::1"));
+    }
+
+    #[test]
+    fn host_matches_subdomains() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("img.example.com", "example.com"));
+        assert!(host_matches("img.example.com", "*.example.com"));
+        assert!(host_matches("example.com", "*.example.com"));
+        assert!(host_matches("example.com", "Example.COM"));
+        assert!(!host_matches("badexample.com", "example.com"));
+        assert!(!host_matches("example.com.evil.org", "example.com"));
+        assert!(!host_matches("com", "example.com"));
+    }
+}
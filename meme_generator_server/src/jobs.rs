@@ -0,0 +1,685 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Extension, Json, Path},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use meme_generator::{cache::generate_with_cache, get_meme, progress::with_progress};
+
+use crate::{
+    auth::{ApiKey, KeySlot},
+    config::CONFIG,
+    errors::{ErrorResponse, JOB_QUEUE_FULL, job_not_found, meme_forbidden, meme_not_found},
+    extract::ApiJson,
+    fetch::{fetch_client, fetch_error, parse_url},
+    metrics::{observe_queue_wait, record_error},
+    server::{
+        InlineMemeRequest, MemeInput, ServerError, acquire_permit, handle_error,
+        handle_server_error, load_inline_request, run_generation, store_image,
+    },
+};
+
+static JOBS: LazyLock<Mutex<HashMap<String, Arc<Job>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static QUEUE: LazyLock<Mutex<BinaryHeap<QueuedJob>>> =
+    LazyLock::new(|| Mutex::new(BinaryHeap::new()));
+
+static QUEUE_NOTIFY: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+static JOB_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct JobRequest {
+    key: String,
+    #[serde(flatten)]
+    request: InlineMemeRequest,
+    /// Jobs with a higher priority run first, equal priorities run in submission order
+    #[serde(default)]
+    priority: i32,
+    /// URL that receives the job status as a POST request when the job finishes,
+    /// subject to the same policy as image urls
+    #[serde(default)]
+    callback_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct JobInfo {
+    id: String,
+    key: String,
+    status: JobStatus,
+    progress: f32,
+    priority: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorResponse>,
+    created_at: u64,
+}
+
+struct Job {
+    info: Mutex<JobInfo>,
+    cancelled: AtomicBool,
+    finished_at: Mutex<Option<Instant>>,
+    callback_url: Option<Url>,
+    /// API key that queued the job, `None` when authentication is disabled
+    owner: Option<Arc<ApiKey>>,
+}
+
+impl Job {
+    fn new(
+        id: String,
+        key: String,
+        priority: i32,
+        created_at: u64,
+        callback_url: Option<Url>,
+        owner: Option<Arc<ApiKey>>,
+    ) -> Self {
+        Job {
+            info: Mutex::new(JobInfo {
+                id,
+                key,
+                status: JobStatus::Queued,
+                progress: 0.0,
+                priority,
+                image_id: None,
+                error: None,
+                created_at,
+            }),
+            cancelled: AtomicBool::new(false),
+            finished_at: Mutex::new(None),
+            callback_url,
+            owner,
+        }
+    }
+
+    /// Whether `api_key` may see and cancel the job. Jobs are only visible
+    /// to the key that queued them.
+    fn is_owned_by(&self, api_key: Option<&Arc<ApiKey>>) -> bool {
+        match (&self.owner, api_key) {
+            (None, _) => true,
+            (Some(owner), Some(api_key)) => Arc::ptr_eq(owner, api_key),
+            (Some(_), None) => false,
+        }
+    }
+
+    fn info(&self) -> JobInfo {
+        self.info.lock().unwrap().clone()
+    }
+
+    fn set_progress(&self, progress: f32) {
+        self.info.lock().unwrap().progress = progress;
+    }
+
+    /// Moves a queued job to running. Fails if the job was cancelled after
+    /// a worker took it from the queue.
+    fn start(&self) -> bool {
+        let mut info = self.info.lock().unwrap();
+        if info.status != JobStatus::Queued {
+            return false;
+        }
+        info.status = JobStatus::Running;
+        true
+    }
+
+    /// Cancels the job and returns the status it had, or `None` if it has
+    /// already finished. Queued jobs finish right away, running jobs finish
+    /// when their generation returns.
+    fn cancel(&self) -> Option<JobStatus> {
+        let mut info = self.info.lock().unwrap();
+        let status = info.status;
+        if status.is_finished() {
+            return None;
+        }
+        self.cancelled.store(true, AtomicOrdering::Relaxed);
+        if status == JobStatus::Queued {
+            self.finish_locked(&mut info, JobStatus::Cancelled, None, None);
+        }
+        Some(status)
+    }
+
+    /// Finishes the job, unless it has already finished.
+    fn finish(
+        &self,
+        status: JobStatus,
+        image_id: Option<String>,
+        error: Option<ErrorResponse>,
+    ) -> bool {
+        let mut info = self.info.lock().unwrap();
+        if info.status.is_finished() {
+            return false;
+        }
+        self.finish_locked(&mut info, status, image_id, error);
+        true
+    }
+
+    fn finish_locked(
+        &self,
+        info: &mut JobInfo,
+        status: JobStatus,
+        image_id: Option<String>,
+        error: Option<ErrorResponse>,
+    ) {
+        info.status = status;
+        if status == JobStatus::Done {
+            info.progress = 1.0;
+        }
+        info.image_id = image_id;
+        if let Some(error) = &error {
+            record_error(error.code());
+        }
+        info.error = error;
+        *self.finished_at.lock().unwrap() = Some(Instant::now());
+    }
+}
+
+struct QueuedJob {
+    priority: i32,
+    seq: u64,
+    queued_at: Instant,
+    job: Arc<Job>,
+    request: JobRequest,
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedJob {}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct JobResponse {
+    job_id: String,
+}
+
+pub(crate) async fn create_job(
+    api_key: Option<Extension<Arc<ApiKey>>>,
+    ApiJson(payload): ApiJson<JobRequest>,
+) -> Response {
+    if get_meme(&payload.key).is_none() {
+        return meme_not_found(&payload.key).into_response();
+    }
+    if let Some(Extension(api_key)) = &api_key
+        && !api_key.allows_meme(&payload.key)
+    {
+        return meme_forbidden(&payload.key).into_response();
+    }
+    let owner = api_key.map(|Extension(api_key)| api_key);
+    let callback_url = match payload.callback_url.as_deref().map(parse_url).transpose() {
+        Ok(url) => url,
+        Err(err) => return handle_server_error(err).into_response(),
+    };
+
+    let mut queue = QUEUE.lock().unwrap();
+    if queue.len() >= CONFIG.server.max_queue_depth {
+        let max_queue_depth = CONFIG.server.max_queue_depth;
+        return JOB_QUEUE_FULL
+            .error(
+                format!("Job queue is full, maximum depth is {max_queue_depth}"),
+                json!({ "max_queue_depth": max_queue_depth }),
+            )
+            .into_response();
+    }
+
+    let seq = JOB_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    // ids are the only thing needed to read or cancel a job without auth
+    let id = format!("{:032x}", rand::random::<u128>());
+    let job = Arc::new(Job::new(
+        id.clone(),
+        payload.key.clone(),
+        payload.priority,
+        created_at,
+        callback_url,
+        owner,
+    ));
+    JOBS.lock().unwrap().insert(id.clone(), job.clone());
+    queue.push(QueuedJob {
+        priority: payload.priority,
+        seq,
+        queued_at: Instant::now(),
+        job,
+        request: payload,
+    });
+    drop(queue);
+    QUEUE_NOTIFY.notify_one();
+
+    (StatusCode::ACCEPTED, Json(JobResponse { job_id: id })).into_response()
+}
+
+/// Looks up a job, jobs of other API keys are reported as not found.
+fn find_job(id: &str, api_key: Option<Extension<Arc<ApiKey>>>) -> Option<Arc<Job>> {
+    let job = JOBS.lock().unwrap().get(id).cloned()?;
+    let api_key = api_key.map(|Extension(api_key)| api_key);
+    job.is_owned_by(api_key.as_ref()).then_some(job)
+}
+
+pub(crate) async fn job_status(
+    api_key: Option<Extension<Arc<ApiKey>>>,
+    Path(id): Path<String>,
+) -> Response {
+    match find_job(&id, api_key) {
+        Some(job) => Json(job.info()).into_response(),
+        None => job_not_found(&id).into_response(),
+    }
+}
+
+/// Cancels a job. Queued jobs are dropped right away, running jobs are
+/// marked as cancelled and their result is discarded when they finish.
+pub(crate) async fn cancel_job(
+    api_key: Option<Extension<Arc<ApiKey>>>,
+    Path(id): Path<String>,
+) -> Response {
+    let Some(job) = find_job(&id, api_key) else {
+        return job_not_found(&id).into_response();
+    };
+    match job.cancel() {
+        None => return (StatusCode::CONFLICT, Json(job.info())).into_response(),
+        Some(JobStatus::Queued) => {
+            QUEUE
+                .lock()
+                .unwrap()
+                .retain(|queued| !Arc::ptr_eq(&queued.job, &job));
+            notify_callback(&job).await;
+        }
+        Some(_) => {}
+    }
+    Json(job.info()).into_response()
+}
+
+/// Wakes the waiting workers after a key slot was released, so that they
+/// pick up the jobs of that key.
+pub(crate) fn wake_workers() {
+    QUEUE_NOTIFY.notify_waiters();
+}
+
+/// Takes the first job in queue order whose key has a free slot. Jobs of
+/// keys at their `max_concurrent` limit stay queued, so that they do not
+/// hold workers that could run the jobs of other keys.
+fn pop_ready(queue: &mut BinaryHeap<QueuedJob>) -> Option<(QueuedJob, Option<KeySlot>)> {
+    let mut skipped = Vec::new();
+    let mut ready = None;
+    while let Some(queued) = queue.pop() {
+        let slot = match &queued.job.owner {
+            Some(api_key) => match api_key.try_acquire_slot() {
+                Some(slot) => Some(slot),
+                None => {
+                    skipped.push(queued);
+                    continue;
+                }
+            },
+            None => None,
+        };
+        ready = Some((queued, slot));
+        break;
+    }
+    queue.extend(skipped);
+    ready
+}
+
+async fn next_job() -> (QueuedJob, Option<KeySlot>) {
+    loop {
+        let notified = QUEUE_NOTIFY.notified();
+        if let Some(ready) = pop_ready(&mut QUEUE.lock().unwrap()) {
+            return ready;
+        }
+        notified.await;
+    }
+}
+
+/// Runs a job that has been started with [`Job::start`], while holding a
+/// slot of its key.
+async fn run_job(job: Arc<Job>, request: JobRequest) {
+    let Some(meme) = get_meme(&request.key) else {
+        job.finish(JobStatus::Failed, None, Some(meme_not_found(&request.key)));
+        return;
+    };
+    let MemeInput {
+        images,
+        texts,
+        options,
+        seed,
+    } = match load_inline_request(request.request).await {
+        Ok(input) => input,
+        Err(err) => {
+            job.finish(JobStatus::Failed, None, Some(handle_server_error(err)));
+            return;
+        }
+    };
+
+    let _permit = acquire_permit().await;
+    if job.cancelled.load(AtomicOrdering::Relaxed) {
+        job.finish(JobStatus::Cancelled, None, None);
+        return;
+    }
+    let progress_job = job.clone();
+    let result = run_generation(&request.key, move || {
+        with_progress(
+            move |done, total| progress_job.set_progress(done as f32 / total.max(1) as f32),
+            || generate_with_cache(meme.as_ref(), images, texts, options, seed),
+        )
+    })
+    .await;
+
+    if job.cancelled.load(AtomicOrdering::Relaxed) {
+        job.finish(JobStatus::Cancelled, None, None);
+        return;
+    }
+    match result {
+        Ok((data, _)) => match store_image(data).await {
+            Ok(id) => job.finish(JobStatus::Done, Some(id), None),
+            Err(err) => job.finish(JobStatus::Failed, None, Some(handle_server_error(err))),
+        },
+        Err(error) => job.finish(JobStatus::Failed, None, Some(handle_error(error))),
+    }
+}
+
+async fn send_callback(client: &Client, url: &Url, job: &Job) -> Result<(), ServerError> {
+    client
+        .post(url.clone())
+        .timeout(Duration::from_secs(10))
+        .json(&job.info())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_error)?;
+    Ok(())
+}
+
+/// Posts the job status to the callback url through the fetch client, so
+/// redirects and resolved addresses are checked against the fetch policy.
+async fn notify_callback(job: &Job) {
+    let Some(url) = &job.callback_url else {
+        return;
+    };
+    if let Err(err) = send_callback(fetch_client(), url, job).await {
+        warn!("Failed to call job callback {url}: {err}");
+    }
+}
+
+async fn worker() {
+    loop {
+        let (
+            QueuedJob {
+                job,
+                request,
+                queued_at,
+                ..
+            },
+            slot,
+        ) = next_job().await;
+        observe_queue_wait(queued_at.elapsed());
+        // cancelled between being popped and started
+        if !job.start() {
+            continue;
+        }
+        run_job(job.clone(), request).await;
+        drop(slot);
+        notify_callback(&job).await;
+    }
+}
+
+pub(crate) fn start_workers() {
+    for _ in 0..CONFIG.server.job_workers.max(1) {
+        tokio::spawn(worker());
+    }
+    info!("Started {} job workers", CONFIG.server.job_workers.max(1));
+}
+
+pub(crate) fn queue_depth() -> usize {
+    QUEUE.lock().unwrap().len()
+}
+
+/// Removes finished jobs older than `job_ttl_secs`.
+pub(crate) fn cleanup_jobs() {
+    let ttl = Duration::from_secs(CONFIG.server.job_ttl_secs);
+    JOBS.lock().unwrap().retain(|_, job| {
+        job.finished_at
+            .lock()
+            .unwrap()
+            .is_none_or(|finished_at| finished_at.elapsed() < ttl)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, routing::post};
+    use serde_json::Value;
+    use tokio::{net::TcpListener, sync::mpsc};
+
+    use super::*;
+
+    fn new_job(callback_url: Option<Url>) -> Job {
+        Job::new(
+            "test".to_string(),
+            "petpet".to_string(),
+            0,
+            0,
+            callback_url,
+            None,
+        )
+    }
+
+    /// Starts a local webhook that forwards every body it receives.
+    async fn start_webhook() -> (Url, mpsc::UnboundedReceiver<Value>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/callback",
+            post(move |Json(body): Json<Value>| {
+                let sender = sender.clone();
+                async move {
+                    sender.send(body).unwrap();
+                    StatusCode::NO_CONTENT
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let url = Url::parse(&format!("http://localhost:{}/callback", addr.port())).unwrap();
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn callback_receives_job_status() {
+        let (url, mut receiver) = start_webhook().await;
+        let job = new_job(Some(url.clone()));
+        assert!(job.start());
+        assert!(job.finish(JobStatus::Done, Some("image".to_string()), None));
+
+        send_callback(&Client::new(), &url, &job).await.unwrap();
+        let body = receiver.recv().await.unwrap();
+        assert_eq!(body["id"], "test");
+        assert_eq!(body["status"], "done");
+        assert_eq!(body["image_id"], "image");
+        assert_eq!(body["progress"], 1.0);
+    }
+
+    #[tokio::test]
+    async fn callback_to_local_address_is_refused() {
+        let (url, mut receiver) = start_webhook().await;
+        let job = new_job(Some(url.clone()));
+
+        // localhost only resolves to loopback addresses
+        let result = send_callback(fetch_client(), &url, &job).await;
+        assert!(result.is_err());
+        assert!(receiver.try_recv().is_err());
+
+        assert!(matches!(
+            parse_url("http://127.0.0.1:8080/callback"),
+            Err(ServerError::FetchDenied(_))
+        ));
+        assert!(matches!(
+            parse_url("http://[::ffff:10.0.0.1]/callback"),
+            Err(ServerError::FetchDenied(_))
+        ));
+        assert!(matches!(
+            parse_url("file:///etc/passwd"),
+            Err(ServerError::FetchDenied(_))
+        ));
+    }
+
+    #[test]
+    fn jobs_are_only_visible_to_their_key() {
+        let api_key = |key: &str| {
+            Arc::new(ApiKey::new(
+                toml::from_str(&format!("key = \"{key}\"")).unwrap(),
+            ))
+        };
+        let owner = api_key("owner");
+        let other = api_key("other");
+        let job = Job::new(
+            "test".to_string(),
+            "petpet".to_string(),
+            0,
+            0,
+            None,
+            Some(owner.clone()),
+        );
+        assert!(job.is_owned_by(Some(&owner)));
+        assert!(!job.is_owned_by(Some(&other)));
+        assert!(!job.is_owned_by(None));
+
+        // jobs queued while authentication was disabled
+        assert!(new_job(None).is_owned_by(Some(&other)));
+        assert!(new_job(None).is_owned_by(None));
+    }
+
+    #[test]
+    fn cancelled_queued_job_does_not_start() {
+        let job = new_job(None);
+        assert_eq!(job.cancel(), Some(JobStatus::Queued));
+        assert_eq!(job.info().status, JobStatus::Cancelled);
+
+        // a worker that popped the job before it was cancelled
+        assert!(!job.start());
+        assert!(!job.finish(JobStatus::Done, Some("image".to_string()), None));
+        assert_eq!(job.info().status, JobStatus::Cancelled);
+        assert_eq!(job.info().image_id, None);
+        assert_eq!(job.cancel(), None);
+    }
+
+    #[test]
+    fn cancelled_running_job_finishes_once() {
+        let job = new_job(None);
+        assert!(job.start());
+        assert_eq!(job.cancel(), Some(JobStatus::Running));
+        assert_eq!(job.info().status, JobStatus::Running);
+        assert!(job.cancelled.load(AtomicOrdering::Relaxed));
+
+        assert!(job.finish(JobStatus::Cancelled, None, None));
+        assert!(!job.finish(JobStatus::Failed, None, None));
+        assert_eq!(job.info().status, JobStatus::Cancelled);
+        assert_eq!(job.cancel(), None);
+    }
+
+    fn queued(priority: i32, seq: u64, owner: Option<Arc<ApiKey>>) -> QueuedJob {
+        QueuedJob {
+            priority,
+            seq,
+            queued_at: Instant::now(),
+            job: Arc::new(Job::new(
+                seq.to_string(),
+                "petpet".to_string(),
+                priority,
+                0,
+                None,
+                owner,
+            )),
+            request: serde_json::from_value(json!({ "key": "petpet" })).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn queue_skips_keys_without_free_slot() {
+        let busy = Arc::new(ApiKey::new(
+            toml::from_str("key = \"busy\"\nmax_concurrent = 1").unwrap(),
+        ));
+        let held = busy.try_acquire_slot().unwrap();
+        let mut queue = BinaryHeap::new();
+        queue.push(queued(1, 0, Some(busy.clone())));
+        queue.push(queued(0, 1, None));
+
+        // the other job runs first although it has a lower priority
+        let (job, _) = pop_ready(&mut queue).unwrap();
+        assert_eq!(job.seq, 1);
+        assert!(pop_ready(&mut queue).is_none());
+        assert_eq!(queue.len(), 1);
+
+        // releasing the slot wakes the waiting workers
+        let notified = QUEUE_NOTIFY.notified();
+        drop(held);
+        notified.await;
+        let (job, slot) = pop_ready(&mut queue).unwrap();
+        assert_eq!(job.seq, 0);
+        assert!(slot.is_some());
+        assert!(busy.try_acquire_slot().is_none());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn queue_orders_by_priority_then_submission() {
+        let mut heap = BinaryHeap::new();
+        heap.push(queued(0, 0, None));
+        heap.push(queued(1, 1, None));
+        heap.push(queued(0, 2, None));
+        heap.push(queued(1, 3, None));
+        let order = std::iter::from_fn(|| heap.pop())
+            .map(|job| job.seq)
+            .collect::<Vec<_>>();
+        assert_eq!(order, [1, 3, 0, 2]);
+    }
+}
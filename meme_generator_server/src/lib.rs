@@ -1,5 +1,14 @@
+mod auth;
+mod batch;
 mod config;
+mod errors;
+mod extract;
+mod fetch;
+mod jobs;
+mod metrics;
+mod openapi;
 mod server;
+mod store;
 mod tools;
 
 pub use server::{run_server, run_server_sync};
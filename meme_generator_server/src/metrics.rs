@@ -0,0 +1,399 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Json, MatchedPath, Request},
+    http::{StatusCode, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+
+use meme_generator::{
+    cache::cache_stats,
+    get_meme_keys,
+    resources::{ResourceStatus, resource_status},
+};
+
+use crate::{
+    config::CONFIG, errors::error_name, jobs::queue_depth, server::SEMAPHORE, store::IMAGE_STORE,
+};
+
+/// Upper bounds in seconds shared by all histograms.
+const BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(|| Mutex::new(Metrics::default()));
+
+#[derive(Clone)]
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKETS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let value = duration.as_secs_f64();
+        for (count, bound) in self.counts.iter_mut().zip(BUCKETS) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (count, bound) in self.counts.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    requests: HashMap<(String, String, u16), u64>,
+    request_durations: HashMap<String, Histogram>,
+    generation_durations: HashMap<String, Histogram>,
+    semaphore_wait: Histogram,
+    queue_wait: Histogram,
+    errors: HashMap<u16, u64>,
+}
+
+pub(crate) fn observe_generation(key: &str, duration: Duration) {
+    METRICS
+        .lock()
+        .unwrap()
+        .generation_durations
+        .entry(key.to_string())
+        .or_default()
+        .observe(duration);
+}
+
+pub(crate) fn observe_semaphore_wait(duration: Duration) {
+    METRICS.lock().unwrap().semaphore_wait.observe(duration);
+}
+
+pub(crate) fn observe_queue_wait(duration: Duration) {
+    METRICS.lock().unwrap().queue_wait.observe(duration);
+}
+
+/// `code` of an [`ErrorResponse`](crate::errors::ErrorResponse), attached to
+/// the response so that [`track_requests`] can count it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ErrorCode(pub(crate) u16);
+
+/// Counts an error that is reported inside a successful response, such as a
+/// failed batch item or job.
+pub(crate) fn record_error(code: u16) {
+    *METRICS.lock().unwrap().errors.entry(code).or_default() += 1;
+}
+
+/// Records the count and latency of requests per matched route, and the
+/// error code of error responses, including those from the auth layers.
+pub(crate) async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let duration = start.elapsed();
+
+    let mut metrics = METRICS.lock().unwrap();
+    if let Some(ErrorCode(code)) = response.extensions().get::<ErrorCode>() {
+        *metrics.errors.entry(*code).or_default() += 1;
+    }
+    *metrics
+        .requests
+        .entry((method, route.clone(), response.status().as_u16()))
+        .or_default() += 1;
+    metrics
+        .request_durations
+        .entry(route)
+        .or_default()
+        .observe(duration);
+    response
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn render_metrics() -> String {
+    let mut out = String::new();
+    let metrics = METRICS.lock().unwrap();
+
+    header(
+        &mut out,
+        "meme_http_requests_total",
+        "counter",
+        "HTTP requests by method, route and status",
+    );
+    for ((method, route, status), count) in &metrics.requests {
+        let _ = writeln!(
+            out,
+            "meme_http_requests_total{{method=\"{method}\",route=\"{}\",status=\"{status}\"}} {count}",
+            escape(route)
+        );
+    }
+
+    header(
+        &mut out,
+        "meme_http_request_duration_seconds",
+        "histogram",
+        "HTTP request latency by route",
+    );
+    for (route, histogram) in &metrics.request_durations {
+        let labels = format!("route=\"{}\"", escape(route));
+        histogram.render(&mut out, "meme_http_request_duration_seconds", &labels);
+    }
+
+    header(
+        &mut out,
+        "meme_generation_duration_seconds",
+        "histogram",
+        "Time spent generating memes by meme key",
+    );
+    for (key, histogram) in &metrics.generation_durations {
+        let labels = format!("key=\"{}\"", escape(key));
+        histogram.render(&mut out, "meme_generation_duration_seconds", &labels);
+    }
+
+    header(
+        &mut out,
+        "meme_semaphore_wait_seconds",
+        "histogram",
+        "Time spent waiting for a generation slot",
+    );
+    metrics
+        .semaphore_wait
+        .render(&mut out, "meme_semaphore_wait_seconds", "");
+
+    header(
+        &mut out,
+        "meme_job_queue_wait_seconds",
+        "histogram",
+        "Time jobs spent in the queue before a worker picked them up",
+    );
+    metrics
+        .queue_wait
+        .render(&mut out, "meme_job_queue_wait_seconds", "");
+
+    header(
+        &mut out,
+        "meme_errors_total",
+        "counter",
+        "Errors returned to clients by error code",
+    );
+    for (code, count) in &metrics.errors {
+        let _ = writeln!(
+            out,
+            "meme_errors_total{{code=\"{code}\",name=\"{}\"}} {count}",
+            error_name(*code)
+        );
+    }
+    drop(metrics);
+
+    header(
+        &mut out,
+        "meme_job_queue_depth",
+        "gauge",
+        "Jobs waiting in the queue",
+    );
+    let _ = writeln!(out, "meme_job_queue_depth {}", queue_depth());
+
+    header(
+        &mut out,
+        "meme_generation_slots_available",
+        "gauge",
+        "Free generation slots out of max_concurrent_tasks",
+    );
+    let _ = writeln!(
+        out,
+        "meme_generation_slots_available {}",
+        SEMAPHORE.available_permits()
+    );
+    header(
+        &mut out,
+        "meme_generation_slots_total",
+        "gauge",
+        "Configured max_concurrent_tasks",
+    );
+    let _ = writeln!(
+        out,
+        "meme_generation_slots_total {}",
+        CONFIG.server.max_concurrent_tasks
+    );
+
+    if let Some(usage) = IMAGE_STORE.usage() {
+        header(
+            &mut out,
+            "meme_image_store_images",
+            "gauge",
+            "Images kept in the image store",
+        );
+        let _ = writeln!(out, "meme_image_store_images {}", usage.images);
+        header(
+            &mut out,
+            "meme_image_store_bytes",
+            "gauge",
+            "Size of the image store in bytes",
+        );
+        let _ = writeln!(out, "meme_image_store_bytes {}", usage.bytes);
+    }
+
+    let stats = cache_stats();
+    header(
+        &mut out,
+        "meme_cache_requests_total",
+        "counter",
+        "Result cache lookups by status",
+    );
+    for (status, count) in [
+        ("hit", stats.hits),
+        ("miss", stats.misses),
+        ("bypass", stats.bypasses),
+    ] {
+        let _ = writeln!(
+            out,
+            "meme_cache_requests_total{{status=\"{status}\"}} {count}"
+        );
+    }
+
+    out
+}
+
+pub(crate) async fn metrics() -> Response {
+    let body = spawn_blocking(render_metrics).await.unwrap();
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+pub(crate) async fn healthz() -> Response {
+    "ok".into_response()
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Readiness {
+    ready: bool,
+    memes: usize,
+    #[serde(flatten)]
+    resources: ResourceStatus,
+}
+
+/// Ready once memes are loaded, fonts are initialized and resource images
+/// are present. The first call initializes the fonts.
+pub(crate) async fn readyz() -> Response {
+    let readiness = spawn_blocking(|| {
+        let memes = get_meme_keys().len();
+        let resources = resource_status();
+        Readiness {
+            ready: memes > 0 && resources.fonts_ready && resources.images_ready,
+            memes,
+            resources,
+        }
+    })
+    .await
+    .unwrap();
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, middleware, routing::get};
+    use reqwest::Client;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::errors::{ADMIN_REQUIRED, meme_not_found};
+
+    fn error_count(code: u16) -> u64 {
+        METRICS
+            .lock()
+            .unwrap()
+            .errors
+            .get(&code)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn counts_error_responses() {
+        let app = Router::new()
+            .route("/missing", get(|| async { meme_not_found("missing") }))
+            .route(
+                "/admin",
+                get(|| async { ADMIN_REQUIRED.error("Admin scope required", json!({})) }),
+            )
+            .route("/ok", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn(track_requests));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (not_found, forbidden) = (error_count(580), error_count(442));
+        let client = Client::new();
+        for path in ["missing", "admin", "admin", "ok"] {
+            client
+                .get(format!("http://{addr}/{path}"))
+                .send()
+                .await
+                .unwrap();
+        }
+        assert!(error_count(580) > not_found);
+        assert!(error_count(442) >= forbidden + 2);
+    }
+}
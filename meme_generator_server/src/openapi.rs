@@ -0,0 +1,1273 @@
+use std::sync::LazyLock;
+
+use axum::{
+    http::header::CONTENT_TYPE,
+    response::{Html, IntoResponse, Response},
+};
+use serde_json::{Map, Value, json};
+use tokio::task::spawn_blocking;
+
+use meme_generator::{MemeSortBy, VERSION, get_memes_sorted, meme::MemeOption};
+
+use crate::{config::CONFIG, errors::error_codes, server::public_metrics};
+
+/// The document only depends on the loaded memes, which don't change while
+/// the server is running.
+static OPENAPI: LazyLock<String> =
+    LazyLock::new(|| serde_json::to_string(&openapi_document()).unwrap());
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+/// Component names may only contain `A-Z a-z 0-9 . - _`.
+fn component_name(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn json_body(schema: Value) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn image_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "image/png": {}, "image/jpeg": {}, "image/gif": {}, "image/webp": {},
+        },
+    })
+}
+
+fn path_param(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn query_param(name: &str, schema: Value, required: bool) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": required,
+        "schema": schema,
+    })
+}
+
+fn sort_params() -> Value {
+    json!([
+        query_param("sort_by", schema_ref("MemeSortBy"), false),
+        query_param("sort_reverse", json!({ "type": "boolean" }), false),
+    ])
+}
+
+/// Builds an operation, `protected` operations require an API key when
+/// authentication is enabled.
+fn operation(tag: &str, summary: &str, protected: bool, mut fields: Value) -> Value {
+    let fields_map = fields.as_object_mut().unwrap();
+    fields_map.insert("tags".to_string(), json!([tag]));
+    fields_map.insert("summary".to_string(), json!(summary));
+    let responses = fields_map
+        .entry("responses")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .unwrap();
+    responses.insert(
+        "default".to_string(),
+        json_response("Error", schema_ref("ErrorResponse")),
+    );
+    if protected && CONFIG.server.auth.enabled {
+        fields_map.insert(
+            "security".to_string(),
+            json!([{ "ApiKeyHeader": [] }, { "BearerAuth": [] }]),
+        );
+    }
+    fields
+}
+
+fn option_schema(option: &MemeOption) -> (String, Value) {
+    let (name, mut schema, description) = match option {
+        MemeOption::Boolean {
+            name,
+            default,
+            description,
+            ..
+        } => (
+            name,
+            json!({ "type": "boolean", "default": default }),
+            description,
+        ),
+        MemeOption::String {
+            name,
+            default,
+            choices,
+            description,
+            ..
+        } => (
+            name,
+            json!({ "type": "string", "default": default, "enum": choices }),
+            description,
+        ),
+        MemeOption::Integer {
+            name,
+            default,
+            minimum,
+            maximum,
+            description,
+            ..
+        } => (
+            name,
+            json!({
+                "type": "integer",
+                "format": "int32",
+                "default": default,
+                "minimum": minimum,
+                "maximum": maximum,
+            }),
+            description,
+        ),
+        MemeOption::Float {
+            name,
+            default,
+            minimum,
+            maximum,
+            description,
+            ..
+        } => (
+            name,
+            json!({
+                "type": "number",
+                "format": "float",
+                "default": default,
+                "minimum": minimum,
+                "maximum": maximum,
+            }),
+            description,
+        ),
+    };
+    let schema_map = schema.as_object_mut().unwrap();
+    if let Some(description) = description {
+        schema_map.insert("description".to_string(), json!(description));
+    }
+    schema_map.retain(|_, value| !value.is_null());
+    (name.clone(), schema)
+}
+
+fn options_schema(options: &[MemeOption]) -> Value {
+    let properties = options.iter().map(option_schema).collect::<Map<_, _>>();
+    json!({ "type": "object", "properties": properties })
+}
+
+/// Adds the typed request bodies and the generation paths of every meme.
+fn add_memes(paths: &mut Map<String, Value>, schemas: &mut Map<String, Value>) {
+    for meme in get_memes_sorted(MemeSortBy::Key, false) {
+        let info = meme.info();
+        let params = &info.params;
+        let name = component_name(&info.key);
+        let texts = json!({
+            "type": "array",
+            "items": { "type": "string" },
+            "minItems": params.min_texts,
+            "maxItems": params.max_texts,
+            "default": params.default_texts,
+        });
+        let images = |item: &str| {
+            json!({
+                "type": "array",
+                "items": schema_ref(item),
+                "minItems": params.min_images,
+                "maxItems": params.max_images,
+            })
+        };
+        let options_name = format!("meme.{name}.Options");
+        schemas.insert(options_name.clone(), options_schema(&params.options));
+        schemas.insert(
+            format!("meme.{name}.Request"),
+            json!({
+                "type": "object",
+                "required": ["images", "texts", "options"],
+                "properties": {
+                    "images": images("Image"),
+                    "texts": texts,
+                    "options": schema_ref(&options_name),
+                    "seed": { "type": "integer", "format": "int64", "minimum": 0 },
+                },
+            }),
+        );
+        schemas.insert(
+            format!("meme.{name}.InlineRequest"),
+            json!({
+                "type": "object",
+                "properties": {
+                    "images": images("InlineImage"),
+                    "texts": texts,
+                    "options": schema_ref(&options_name),
+                    "seed": { "type": "integer", "format": "int64", "minimum": 0 },
+                },
+            }),
+        );
+
+        let tag = "generate";
+        let summary = format!("Generate `{}` ({})", info.key, info.keywords.join(", "));
+        paths.insert(
+            format!("/memes/{}", info.key),
+            json!({
+                "post": operation(tag, &summary, true, json!({
+                    "operationId": format!("generate_{name}"),
+                    "requestBody": json_body(schema_ref(&format!("meme.{name}.Request"))),
+                    "responses": {
+                        "200": json_response("Id of the generated image", schema_ref("ImageResponse")),
+                    },
+                })),
+            }),
+        );
+        paths.insert(
+            format!("/memes/{}/generate", info.key),
+            json!({
+                "post": operation(tag, &summary, true, json!({
+                    "operationId": format!("generate_image_{name}"),
+                    "description": "Takes images inline and returns the image itself, \
+                        the format is negotiated with the `Accept` header.",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": schema_ref(&format!("meme.{name}.InlineRequest")),
+                            },
+                            "multipart/form-data": { "schema": schema_ref("MultipartMemeRequest") },
+                        },
+                    },
+                    "responses": {
+                        "200": image_response("The generated image"),
+                        "406": json_response("No acceptable image format", schema_ref("ErrorResponse")),
+                    },
+                })),
+            }),
+        );
+    }
+}
+
+fn image_data_schemas(schemas: &mut Map<String, Value>) {
+    let variants = [
+        (
+            "url",
+            "UrlImageData",
+            json!({
+                "url": { "type": "string", "format": "uri" },
+                "headers": {
+                    "type": "object",
+                    "nullable": true,
+                    "additionalProperties": { "type": "string" },
+                },
+            }),
+        ),
+        (
+            "path",
+            "PathImageData",
+            json!({ "path": { "type": "string" } }),
+        ),
+        (
+            "data",
+            "DataImageData",
+            json!({ "data": { "type": "string", "format": "byte" } }),
+        ),
+    ];
+    let mut mapping = Map::new();
+    let mut one_of = Vec::new();
+    for (tag, name, mut properties) in variants {
+        properties.as_object_mut().unwrap().insert(
+            "type".to_string(),
+            json!({ "type": "string", "enum": [tag] }),
+        );
+        schemas.insert(
+            name.to_string(),
+            json!({
+                "type": "object",
+                "required": ["type", tag],
+                "properties": properties,
+            }),
+        );
+        mapping.insert(
+            tag.to_string(),
+            json!(format!("#/components/schemas/{name}")),
+        );
+        one_of.push(schema_ref(name));
+    }
+    schemas.insert(
+        "ImageData".to_string(),
+        json!({
+            "oneOf": one_of,
+            "discriminator": { "propertyName": "type", "mapping": mapping },
+        }),
+    );
+}
+
+fn common_schemas(schemas: &mut Map<String, Value>) {
+    image_data_schemas(schemas);
+    let string_array = json!({ "type": "array", "items": { "type": "string" } });
+    let error_codes = error_codes().collect::<Vec<_>>();
+    let common = [
+        (
+            "ErrorResponse",
+            json!({
+                "type": "object",
+                "required": ["code", "message", "data"],
+                "description": "See `/errors` for the list of codes",
+                "properties": {
+                    "code": { "type": "integer", "enum": error_codes },
+                    "message": { "type": "string" },
+                    "data": {},
+                },
+            }),
+        ),
+        (
+            "ImageResponse",
+            json!({
+                "type": "object",
+                "required": ["image_id"],
+                "properties": { "image_id": { "type": "string" } },
+            }),
+        ),
+        (
+            "ImagesResponse",
+            json!({
+                "type": "object",
+                "required": ["image_ids"],
+                "properties": { "image_ids": string_array },
+            }),
+        ),
+        (
+            "Image",
+            json!({
+                "type": "object",
+                "required": ["name", "id"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "id": { "type": "string", "description": "Id of an uploaded image" },
+                },
+            }),
+        ),
+        (
+            "InlineImage",
+            json!({
+                "type": "object",
+                "required": ["image"],
+                "properties": {
+                    "name": { "type": "string", "default": "" },
+                    "image": schema_ref("ImageData"),
+                },
+            }),
+        ),
+        (
+            "OptionValue",
+            json!({
+                "oneOf": [
+                    { "type": "boolean" },
+                    { "type": "string" },
+                    { "type": "integer" },
+                    { "type": "number" },
+                ],
+            }),
+        ),
+        (
+            "InlineMemeRequest",
+            json!({
+                "type": "object",
+                "properties": {
+                    "images": { "type": "array", "items": schema_ref("InlineImage") },
+                    "texts": string_array,
+                    "options": { "type": "object", "additionalProperties": schema_ref("OptionValue") },
+                    "seed": { "type": "integer", "format": "int64", "minimum": 0 },
+                },
+            }),
+        ),
+        (
+            "MultipartMemeRequest",
+            json!({
+                "type": "object",
+                "properties": {
+                    "images": {
+                        "type": "array",
+                        "items": { "type": "string", "format": "binary" },
+                    },
+                    "names": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The n-th name belongs to the n-th image",
+                    },
+                    "texts": string_array,
+                    "options": { "type": "string", "description": "Options as a JSON object" },
+                    "seed": { "type": "integer", "format": "int64", "minimum": 0 },
+                },
+            }),
+        ),
+        (
+            "MemeSortBy",
+            json!({
+                "type": "string",
+                "enum": ["key", "keywords", "keywords_pinyin", "date_created", "date_modified"],
+            }),
+        ),
+        (
+            "MemeOption",
+            json!({
+                "type": "object",
+                "required": ["type", "name"],
+                "properties": {
+                    "type": { "type": "string", "enum": ["boolean", "string", "integer", "float"] },
+                    "name": { "type": "string" },
+                    "default": {},
+                    "choices": { "type": "array", "items": { "type": "string" }, "nullable": true },
+                    "minimum": { "type": "number", "nullable": true },
+                    "maximum": { "type": "number", "nullable": true },
+                    "description": { "type": "string", "nullable": true },
+                    "parser_flags": { "type": "object" },
+                },
+            }),
+        ),
+        (
+            "MemeInfo",
+            json!({
+                "type": "object",
+                "properties": {
+                    "key": { "type": "string" },
+                    "params": {
+                        "type": "object",
+                        "properties": {
+                            "min_images": { "type": "integer" },
+                            "max_images": { "type": "integer" },
+                            "min_texts": { "type": "integer" },
+                            "max_texts": { "type": "integer" },
+                            "default_texts": string_array,
+                            "options": { "type": "array", "items": schema_ref("MemeOption") },
+                        },
+                    },
+                    "keywords": string_array,
+                    "shortcuts": { "type": "array", "items": { "type": "object" } },
+                    "tags": string_array,
+                    "date_created": { "type": "string", "format": "date-time" },
+                    "date_modified": { "type": "string", "format": "date-time" },
+                },
+            }),
+        ),
+        (
+            "BatchItem",
+            json!({
+                "type": "object",
+                "required": ["key"],
+                "properties": {
+                    "key": { "type": "string" },
+                    "texts": string_array,
+                    "options": { "type": "object", "additionalProperties": schema_ref("OptionValue") },
+                    "images": {
+                        "type": "array",
+                        "items": { "type": "integer", "minimum": 0 },
+                        "nullable": true,
+                        "description": "Indexes of the shared images, defaults to the first images in order",
+                    },
+                    "seed": { "type": "integer", "format": "int64", "minimum": 0 },
+                },
+            }),
+        ),
+        (
+            "BatchRequest",
+            json!({
+                "type": "object",
+                "required": ["items"],
+                "properties": {
+                    "images": {
+                        "type": "array",
+                        "items": schema_ref("InlineImage"),
+                        "description": "Images shared by all items",
+                    },
+                    "items": {
+                        "type": "array",
+                        "items": schema_ref("BatchItem"),
+                        "description": "At most `max_batch_items` items (default: 100)",
+                    },
+                    "zip": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Return a zip archive of the results instead of image ids",
+                    },
+                },
+            }),
+        ),
+        (
+            "BatchResponse",
+            json!({
+                "type": "object",
+                "properties": {
+                    "results": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "key": { "type": "string" },
+                                "image_id": { "type": "string" },
+                                "file": { "type": "string" },
+                                "error": schema_ref("ErrorResponse"),
+                            },
+                        },
+                    },
+                },
+            }),
+        ),
+        (
+            "JobRequest",
+            json!({
+                "allOf": [
+                    schema_ref("InlineMemeRequest"),
+                    {
+                        "type": "object",
+                        "required": ["key"],
+                        "properties": {
+                            "key": { "type": "string" },
+                            "priority": {
+                                "type": "integer",
+                                "default": 0,
+                                "description": "Jobs with a higher priority run first",
+                            },
+                            "callback_url": {
+                                "type": "string",
+                                "format": "uri",
+                                "nullable": true,
+                                "description": "Receives the job status as a POST request when the job finishes, checked against the fetch policy",
+                            },
+                        },
+                    },
+                ],
+            }),
+        ),
+        (
+            "JobResponse",
+            json!({
+                "type": "object",
+                "required": ["job_id"],
+                "properties": { "job_id": { "type": "string" } },
+            }),
+        ),
+        (
+            "JobInfo",
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string" },
+                    "key": { "type": "string" },
+                    "status": {
+                        "type": "string",
+                        "enum": ["queued", "running", "done", "failed", "cancelled"],
+                    },
+                    "progress": { "type": "number" },
+                    "priority": { "type": "integer" },
+                    "image_id": { "type": "string" },
+                    "error": schema_ref("ErrorResponse"),
+                    "created_at": { "type": "integer", "description": "Unix timestamp in seconds" },
+                },
+            }),
+        ),
+        (
+            "RenderMemeListParams",
+            json!({
+                "type": "object",
+                "properties": {
+                    "meme_properties": {
+                        "type": "object",
+                        "additionalProperties": {
+                            "type": "object",
+                            "properties": {
+                                "disabled": { "type": "boolean" },
+                                "hot": { "type": "boolean" },
+                                "new": { "type": "boolean" },
+                            },
+                        },
+                    },
+                    "exclude_memes": string_array,
+                    "sort_by": schema_ref("MemeSortBy"),
+                    "sort_reverse": { "type": "boolean" },
+                    "text_template": { "type": "string" },
+                    "add_category_icon": { "type": "boolean" },
+                },
+            }),
+        ),
+        (
+            "RenderMemeStatisticsParams",
+            json!({
+                "type": "object",
+                "required": ["title", "statistics_type", "data"],
+                "properties": {
+                    "title": { "type": "string" },
+                    "statistics_type": { "type": "string", "enum": ["meme_count", "time_count"] },
+                    "data": {
+                        "type": "array",
+                        "items": {
+                            "type": "array",
+                            "description": "Pairs of a meme key or time label and a count",
+                            "items": { "oneOf": [{ "type": "string" }, { "type": "integer" }] },
+                            "minItems": 2,
+                            "maxItems": 2,
+                        },
+                    },
+                },
+            }),
+        ),
+        (
+            "BBCodeRequest",
+            json!({
+                "type": "object",
+                "required": ["text"],
+                "properties": { "text": { "type": "string" } },
+            }),
+        ),
+        (
+            "BBCodeTokens",
+            json!({
+                "type": "object",
+                "properties": {
+                    "tokens": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["type"],
+                            "properties": {
+                                "type": { "type": "string", "enum": ["text", "open_tag", "close_tag"] },
+                                "text": { "type": "string", "description": "Text of a `text` token" },
+                                "tag": { "type": "string", "description": "Tag name, e.g. `color`" },
+                                "value": {
+                                    "type": "string",
+                                    "nullable": true,
+                                    "description": "Value of an `open_tag` token, e.g. `red` for `[color=red]`",
+                                },
+                            },
+                        },
+                    },
+                },
+            }),
+        ),
+        (
+            "ImageRequest",
+            json!({
+                "type": "object",
+                "required": ["image_id"],
+                "properties": { "image_id": { "type": "string" } },
+            }),
+        ),
+        (
+            "ImagesRequest",
+            json!({
+                "type": "object",
+                "required": ["image_ids"],
+                "properties": { "image_ids": string_array },
+            }),
+        ),
+        (
+            "RotateRequest",
+            json!({
+                "type": "object",
+                "required": ["image_id"],
+                "properties": {
+                    "image_id": { "type": "string" },
+                    "degrees": { "type": "number", "nullable": true },
+                },
+            }),
+        ),
+        (
+            "ResizeRequest",
+            json!({
+                "type": "object",
+                "required": ["image_id"],
+                "properties": {
+                    "image_id": { "type": "string" },
+                    "width": { "type": "integer", "nullable": true },
+                    "height": { "type": "integer", "nullable": true },
+                },
+            }),
+        ),
+        (
+            "CropRequest",
+            json!({
+                "type": "object",
+                "required": ["image_id"],
+                "properties": {
+                    "image_id": { "type": "string" },
+                    "left": { "type": "integer", "nullable": true },
+                    "top": { "type": "integer", "nullable": true },
+                    "right": { "type": "integer", "nullable": true },
+                    "bottom": { "type": "integer", "nullable": true },
+                },
+            }),
+        ),
+        (
+            "GifMergeRequest",
+            json!({
+                "type": "object",
+                "required": ["image_ids"],
+                "properties": {
+                    "image_ids": string_array,
+                    "duration": { "type": "number", "nullable": true },
+                },
+            }),
+        ),
+        (
+            "GifDurationRequest",
+            json!({
+                "type": "object",
+                "required": ["image_id", "duration"],
+                "properties": {
+                    "image_id": { "type": "string" },
+                    "duration": { "type": "number" },
+                },
+            }),
+        ),
+        (
+            "ImageInfo",
+            json!({
+                "type": "object",
+                "properties": {
+                    "width": { "type": "integer" },
+                    "height": { "type": "integer" },
+                    "is_multi_frame": { "type": "boolean" },
+                    "frame_count": { "type": "integer", "nullable": true },
+                    "average_duration": { "type": "number", "nullable": true },
+                },
+            }),
+        ),
+    ];
+    for (name, schema) in common {
+        schemas.insert(name.to_string(), schema);
+    }
+}
+
+fn image_operation_paths(paths: &mut Map<String, Value>) {
+    let operations = [
+        ("inspect", "Inspect an image", "ImageRequest", "ImageInfo"),
+        (
+            "flip_horizontal",
+            "Flip an image horizontally",
+            "ImageRequest",
+            "ImageResponse",
+        ),
+        (
+            "flip_vertical",
+            "Flip an image vertically",
+            "ImageRequest",
+            "ImageResponse",
+        ),
+        (
+            "rotate",
+            "Rotate an image",
+            "RotateRequest",
+            "ImageResponse",
+        ),
+        (
+            "resize",
+            "Resize an image",
+            "ResizeRequest",
+            "ImageResponse",
+        ),
+        ("crop", "Crop an image", "CropRequest", "ImageResponse"),
+        (
+            "grayscale",
+            "Convert an image to grayscale",
+            "ImageRequest",
+            "ImageResponse",
+        ),
+        (
+            "invert",
+            "Invert the colors of an image",
+            "ImageRequest",
+            "ImageResponse",
+        ),
+        (
+            "merge_horizontal",
+            "Merge images horizontally",
+            "ImagesRequest",
+            "ImageResponse",
+        ),
+        (
+            "merge_vertical",
+            "Merge images vertically",
+            "ImagesRequest",
+            "ImageResponse",
+        ),
+        (
+            "gif_split",
+            "Split a gif into frames",
+            "ImageRequest",
+            "ImagesResponse",
+        ),
+        (
+            "gif_merge",
+            "Merge frames into a gif",
+            "GifMergeRequest",
+            "ImageResponse",
+        ),
+        (
+            "gif_reverse",
+            "Reverse a gif",
+            "ImageRequest",
+            "ImageResponse",
+        ),
+        (
+            "gif_change_duration",
+            "Change the frame duration of a gif",
+            "GifDurationRequest",
+            "ImageResponse",
+        ),
+    ];
+    for (name, summary, request, response) in operations {
+        paths.insert(
+            format!("/tools/image_operations/{name}"),
+            json!({
+                "post": operation("tools", summary, true, json!({
+                    "operationId": name,
+                    "requestBody": json_body(schema_ref(request)),
+                    "responses": { "200": json_response("Result", schema_ref(response)) },
+                })),
+            }),
+        );
+    }
+}
+
+fn openapi_document() -> Value {
+    let image_id = json_response("Id of the image", schema_ref("ImageResponse"));
+    let routes = [
+        (
+            "/image/upload",
+            json!({
+                "post": operation("images", "Upload an image", true, json!({
+                    "operationId": "upload_image",
+                    "requestBody": json_body(schema_ref("ImageData")),
+                    "responses": { "200": image_id },
+                })),
+            }),
+        ),
+        (
+            "/image/upload/multipart",
+            json!({
+                "post": operation("images", "Upload an image file", true, json!({
+                    "operationId": "upload_image_multipart",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "multipart/form-data": {
+                                "schema": {
+                                    "type": "object",
+                                    "required": ["file"],
+                                    "properties": { "file": { "type": "string", "format": "binary" } },
+                                },
+                            },
+                        },
+                    },
+                    "responses": { "200": image_id },
+                })),
+            }),
+        ),
+        (
+            "/image/{id}",
+            json!({
+                "parameters": [path_param("id", "Id of a stored image")],
+                "get": operation("images", "Download an image", true, json!({
+                    "operationId": "get_image",
+                    "responses": {
+                        "200": image_response("The image, cacheable by its ETag"),
+                        "304": { "description": "Not modified" },
+                    },
+                })),
+                "head": operation("images", "Get the size and type of an image", true, json!({
+                    "operationId": "head_image",
+                    "responses": {
+                        "200": { "description": "Headers of the image" },
+                        "304": { "description": "Not modified" },
+                    },
+                })),
+            }),
+        ),
+        (
+            "/meme/keys",
+            json!({
+                "get": operation("memes", "List meme keys", true, json!({
+                    "operationId": "meme_keys",
+                    "parameters": sort_params(),
+                    "responses": {
+                        "200": json_response("Meme keys", json!({ "type": "array", "items": { "type": "string" } })),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/meme/infos",
+            json!({
+                "get": operation("memes", "List meme infos", true, json!({
+                    "operationId": "meme_infos",
+                    "parameters": sort_params(),
+                    "responses": {
+                        "200": json_response("Meme infos", json!({ "type": "array", "items": schema_ref("MemeInfo") })),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/meme/search",
+            json!({
+                "get": operation("memes", "Search memes by keyword", true, json!({
+                    "operationId": "meme_search",
+                    "parameters": [
+                        query_param("query", json!({ "type": "string" }), true),
+                        query_param("include_tags", json!({ "type": "boolean" }), false),
+                    ],
+                    "responses": {
+                        "200": json_response("Matching meme keys", json!({ "type": "array", "items": { "type": "string" } })),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/meme/version",
+            json!({
+                "get": operation("server", "Version of the meme generator", false, json!({
+                    "operationId": "version",
+                    "responses": {
+                        "200": {
+                            "description": "Version",
+                            "content": { "text/plain": { "schema": { "type": "string" } } },
+                        },
+                    },
+                })),
+            }),
+        ),
+        (
+            "/memes/{key}/info",
+            json!({
+                "parameters": [path_param("key", "Meme key")],
+                "get": operation("memes", "Get the info of a meme", true, json!({
+                    "operationId": "meme_info",
+                    "responses": { "200": json_response("Meme info", schema_ref("MemeInfo")) },
+                })),
+            }),
+        ),
+        (
+            "/memes/{key}/preview",
+            json!({
+                "parameters": [path_param("key", "Meme key")],
+                "get": operation("memes", "Generate a preview with the default options", true, json!({
+                    "operationId": "meme_preview_get",
+                    "responses": { "200": image_id },
+                })),
+                "post": operation("memes", "Generate a preview", true, json!({
+                    "operationId": "meme_preview",
+                    "requestBody": {
+                        "required": false,
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "options": {
+                                            "type": "object",
+                                            "additionalProperties": schema_ref("OptionValue"),
+                                        },
+                                    },
+                                },
+                            },
+                        },
+                    },
+                    "responses": { "200": image_id },
+                })),
+            }),
+        ),
+        (
+            "/memes/batch",
+            json!({
+                "post": operation("generate", "Generate several memes from shared images", true, json!({
+                    "operationId": "meme_batch",
+                    "requestBody": json_body(schema_ref("BatchRequest")),
+                    "responses": {
+                        "200": {
+                            "description": "Results in order, or a zip archive when `zip` is set",
+                            "content": {
+                                "application/json": { "schema": schema_ref("BatchResponse") },
+                                "application/zip": {},
+                            },
+                        },
+                    },
+                })),
+            }),
+        ),
+        (
+            "/jobs",
+            json!({
+                "post": operation("jobs", "Queue a meme generation", true, json!({
+                    "operationId": "create_job",
+                    "requestBody": json_body(schema_ref("JobRequest")),
+                    "responses": { "202": json_response("Job queued", schema_ref("JobResponse")) },
+                })),
+            }),
+        ),
+        (
+            "/jobs/{id}",
+            json!({
+                "parameters": [path_param("id", "Job id")],
+                "get": operation("jobs", "Get the status of a job", true, json!({
+                    "operationId": "job_status",
+                    "responses": { "200": json_response("Job status", schema_ref("JobInfo")) },
+                })),
+                "delete": operation("jobs", "Cancel a job", true, json!({
+                    "operationId": "cancel_job",
+                    "responses": {
+                        "200": json_response("Job cancelled", schema_ref("JobInfo")),
+                        "409": json_response("The job has already finished", schema_ref("JobInfo")),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/tools/render_list",
+            json!({
+                "post": operation("tools", "Render the list of memes", true, json!({
+                    "operationId": "render_list",
+                    "requestBody": json_body(schema_ref("RenderMemeListParams")),
+                    "responses": { "200": image_id },
+                })),
+            }),
+        ),
+        (
+            "/tools/render_statistics",
+            json!({
+                "post": operation("tools", "Render meme statistics", true, json!({
+                    "operationId": "render_statistics",
+                    "requestBody": json_body(schema_ref("RenderMemeStatisticsParams")),
+                    "responses": { "200": image_id },
+                })),
+            }),
+        ),
+        (
+            "/tools/parse_bbcode",
+            json!({
+                "post": operation("tools", "Check BBCode text and split it into tokens", true, json!({
+                    "operationId": "parse_bbcode",
+                    "requestBody": json_body(schema_ref("BBCodeRequest")),
+                    "responses": {
+                        "200": json_response("Tokens of the text", schema_ref("BBCodeTokens")),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/admin/keys",
+            json!({
+                "get": operation("admin", "Usage of the API keys", true, json!({
+                    "operationId": "key_usage",
+                    "responses": {
+                        "200": json_response("Usage per key", json!({ "type": "array", "items": { "type": "object" } })),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/admin/cache/clear",
+            json!({
+                "post": operation("admin", "Clear the result cache", true, json!({
+                    "operationId": "clear_meme_cache",
+                    "responses": { "204": { "description": "Cache cleared" } },
+                })),
+            }),
+        ),
+        (
+            "/errors",
+            json!({
+                "get": operation("server", "Catalog of error codes", false, json!({
+                    "operationId": "error_catalog",
+                    "responses": {
+                        "200": json_response("Error kinds", json!({ "type": "array", "items": { "type": "object" } })),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/metrics",
+            json!({
+                "get": operation("server", "Prometheus metrics, requires an admin key unless `public_metrics` is set or authentication is disabled", !public_metrics(), json!({
+                    "operationId": "metrics",
+                    "responses": {
+                        "200": { "description": "Metrics", "content": { "text/plain": {} } },
+                    },
+                })),
+            }),
+        ),
+        (
+            "/healthz",
+            json!({
+                "get": operation("server", "Liveness probe", false, json!({
+                    "operationId": "healthz",
+                    "responses": { "200": { "description": "The server is running" } },
+                })),
+            }),
+        ),
+        (
+            "/readyz",
+            json!({
+                "get": operation("server", "Readiness probe", false, json!({
+                    "operationId": "readyz",
+                    "responses": {
+                        "200": json_response("Ready", json!({ "type": "object" })),
+                        "503": json_response("Not ready", json!({ "type": "object" })),
+                    },
+                })),
+            }),
+        ),
+        (
+            "/openapi.json",
+            json!({
+                "get": operation("server", "This document", false, json!({
+                    "operationId": "openapi",
+                    "responses": { "200": json_response("OpenAPI document", json!({ "type": "object" })) },
+                })),
+            }),
+        ),
+    ];
+    let mut paths = routes
+        .into_iter()
+        .map(|(path, item)| (path.to_string(), item))
+        .collect::<Map<_, _>>();
+    if CONFIG.server.docs {
+        paths.insert(
+            "/docs".to_string(),
+            json!({
+                "get": operation("server", "Interactive API documentation", false, json!({
+                    "operationId": "docs",
+                    "responses": { "200": { "description": "Documentation page", "content": { "text/html": {} } } },
+                })),
+            }),
+        );
+    }
+    image_operation_paths(&mut paths);
+
+    let mut schemas = Map::new();
+    common_schemas(&mut schemas);
+    add_memes(&mut paths, &mut schemas);
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "meme-generator",
+            "version": VERSION,
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "ApiKeyHeader": {
+                    "type": "apiKey",
+                    "in": "header",
+                    "name": CONFIG.server.auth.header,
+                },
+                "BearerAuth": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
+
+pub(crate) async fn openapi() -> Response {
+    spawn_blocking(|| LazyLock::force(&OPENAPI)).await.unwrap();
+    ([(CONTENT_TYPE, "application/json")], OPENAPI.as_str()).into_response()
+}
+
+/// Swagger UI page, its assets are loaded from `docs_assets_url`.
+static DOCS_PAGE: LazyLock<String> = LazyLock::new(|| {
+    let assets = CONFIG.server.docs_assets_url.trim_end_matches('/');
+    DOCS_TEMPLATE.replace("{assets}", assets)
+});
+
+const DOCS_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>meme-generator API</title>
+  <link rel="stylesheet" href="{assets}/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="{assets}/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+pub(crate) async fn docs() -> Html<&'static str> {
+    Html(DOCS_PAGE.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{admin_routes, key_routes, public_routes};
+
+    /// Whether two paths are equal, a `{param}` segment on either side
+    /// matches any segment.
+    fn path_matches(a: &str, b: &str) -> bool {
+        let is_param = |segment: &str| segment.starts_with('{') && segment.ends_with('}');
+        let (a, b) = (
+            a.split('/').collect::<Vec<_>>(),
+            b.split('/').collect::<Vec<_>>(),
+        );
+        a.len() == b.len()
+            && a.iter()
+                .zip(&b)
+                .all(|(a, b)| a == b || is_param(a) || is_param(b))
+    }
+
+    #[test]
+    fn spec_paths_match_router() {
+        let routes = key_routes()
+            .into_iter()
+            .chain(admin_routes())
+            .chain(public_routes())
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        let document = openapi_document();
+        let paths = document["paths"].as_object().unwrap();
+
+        for path in paths.keys() {
+            let matched = routes.iter().any(|route| path_matches(route, path));
+            assert!(matched, "{path} is documented but not routed");
+        }
+        for route in &routes {
+            assert!(
+                paths.keys().any(|path| path_matches(route, path)),
+                "{route} is routed but not documented"
+            );
+        }
+    }
+
+    #[test]
+    fn docs_page_uses_configured_assets() {
+        let assets = CONFIG.server.docs_assets_url.trim_end_matches('/');
+        assert!(DOCS_PAGE.contains(&format!("{assets}/swagger-ui-bundle.js")));
+        assert!(!DOCS_PAGE.contains("{assets}"));
+    }
+}
@@ -4,26 +4,28 @@ use std::{
     net::{IpAddr, SocketAddr},
     path::PathBuf,
     sync::{Arc, LazyLock},
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant},
 };
 
 use axum::{
     Router,
     body::Body,
-    extract::{DefaultBodyLimit, Json, Multipart, Path, Query},
-    http::StatusCode,
+    extract::{DefaultBodyLimit, FromRequest, Json, Multipart, Path, Request},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+        header::{ACCEPT, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    },
+    middleware,
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{MethodRouter, get, post},
 };
 use base64_serde::base64_serde_type;
 use serde::{Deserialize, Serialize};
-use serde_json::{Value, json};
+use serde_json::json;
 use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncWriteExt},
     net::TcpListener,
     runtime::Runtime,
-    sync::Semaphore,
+    sync::{Semaphore, SemaphorePermit},
     task::spawn_blocking,
     time::interval,
 };
@@ -34,78 +36,60 @@ use tower_http::{
 use tracing::{Level, info, warn};
 
 use meme_generator::{
-    MEME_HOME, MemeSortBy, VERSION,
+    MemeSortBy, VERSION,
+    cache::{CacheStatus, clear_cache, generate_with_cache},
     error::Error,
     get_meme, get_meme_keys_sorted, get_memes_sorted,
     meme::{self, OptionValue},
     search_memes,
+    tools::image_operations::{self, ImageFormat, ImageInfo, convert_format},
 };
 
 use crate::{
-    config::CONFIG,
+    auth::{key_usage, require_admin, require_key},
+    batch::meme_batch,
+    config::{CONFIG, ServerConfig},
+    errors::{
+        DESERIALIZE_ERROR, ErrorResponse, FETCH_DENIED, FETCH_TOO_LARGE, IMAGE_ASSET_MISSING,
+        IMAGE_DECODE_ERROR, IMAGE_ENCODE_ERROR, IMAGE_LIMIT_EXCEEDED, IMAGE_NOT_FOUND,
+        IMAGE_NUMBER_MISMATCH, INTERNAL_ERROR, IO_ERROR, MEME_FEEDBACK, REQUEST_ERROR, STORE_ERROR,
+        TEXT_NUMBER_MISMATCH, TEXT_OVER_LENGTH, error_catalog, invalid_request, meme_not_found,
+        not_acceptable,
+    },
+    extract::{ApiJson, ApiMultipart, ApiQuery, multipart_error, multipart_rejection},
+    fetch::{download_url, read_local_path},
+    jobs::{cancel_job, cleanup_jobs, create_job, job_status, start_workers},
+    metrics::{
+        healthz, metrics, observe_generation, observe_semaphore_wait, readyz, track_requests,
+    },
+    openapi::{docs, openapi},
+    store::{IMAGE_STORE, image_id, mime_type},
     tools::{
         image_operations::{
             crop, flip_horizontal, flip_vertical, gif_change_duration, gif_merge, gif_reverse,
             gif_split, grayscale, inspect, invert, merge_horizontal, merge_vertical, resize,
             rotate,
         },
-        render_list, render_statistics,
+        parse_bbcode, render_list, render_statistics,
     },
 };
 
 base64_serde_type!(Base64Standard, base64::engine::general_purpose::STANDARD);
 
-static REQWEST_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| reqwest::Client::new());
-
-pub static TEMP_DIR: LazyLock<PathBuf> = LazyLock::new(|| MEME_HOME.join("tmp"));
-
 pub(crate) static SEMAPHORE: LazyLock<Arc<Semaphore>> =
     LazyLock::new(|| Arc::new(Semaphore::new(CONFIG.server.max_concurrent_tasks)));
 
-fn clear_temp_dir() {
-    let _ = std::fs::remove_dir_all(&*TEMP_DIR);
-    let _ = std::fs::create_dir(&*TEMP_DIR);
-}
-
-async fn cleanup_temp_files() {
-    let temp_dir = &*TEMP_DIR;
-    if !temp_dir.exists() {
-        return;
-    }
-
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let max_age_seconds = 10 * 60;
-    let mut deleted_count = 0;
-
-    if let Ok(entries) = std::fs::read_dir(temp_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry
-                && let Ok(metadata) = entry.metadata()
-                && metadata.is_file()
-                && let Ok(modified) = metadata.modified()
-                && let Ok(modified_secs) = modified.duration_since(UNIX_EPOCH)
-                && now - modified_secs.as_secs() > max_age_seconds
-            {
-                if let Err(e) = std::fs::remove_file(entry.path()) {
-                    warn!("Failed to delete temp file {:?}: {}", entry.path(), e);
-                } else {
-                    deleted_count += 1;
-                }
-            }
-        }
-    }
-
-    if deleted_count > 0 {
-        info!("Cleaned up {} temporary files", deleted_count);
-    }
-}
-
 #[derive(Debug)]
 pub(crate) enum ServerError {
     RequestError(reqwest::Error),
+    /// The url or path is refused by the fetch policy
+    FetchDenied(String),
+    /// The downloaded image exceeds `max_bytes`
+    FetchTooLarge(usize),
+    /// No stored image has the id, or it expired
+    ImageNotFound(String),
+    /// The image store backend failed
+    StoreError(String),
     IOError(std::io::Error),
     MemeGeneratorError(Error),
 }
@@ -132,6 +116,12 @@ impl fmt::Display for ServerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ServerError::RequestError(err) => write!(f, "Request error: {err}"),
+            ServerError::FetchDenied(reason) => write!(f, "Fetch denied: {reason}"),
+            ServerError::FetchTooLarge(max) => {
+                write!(f, "Fetched image is larger than {max} bytes")
+            }
+            ServerError::ImageNotFound(id) => write!(f, "Image not found: {id}"),
+            ServerError::StoreError(err) => write!(f, "Image store error: {err}"),
             ServerError::IOError(err) => write!(f, "IO error: {err}"),
             ServerError::MemeGeneratorError(err) => write!(f, "{err}"),
         }
@@ -140,22 +130,17 @@ impl fmt::Display for ServerError {
 
 impl error::Error for ServerError {}
 
-pub(crate) async fn create_temp_file(data: Vec<u8>) -> Result<String, ServerError> {
-    let id = format!("{:x}", md5::compute(&data));
-    let path = TEMP_DIR.join(&id);
-    if path.exists() {
-        return Ok(id);
-    }
-    File::create(&path).await?.write_all(&data).await?;
+pub(crate) async fn store_image(data: Vec<u8>) -> Result<String, ServerError> {
+    let id = image_id(&data);
+    IMAGE_STORE.put(&id, data).await?;
     Ok(id)
 }
 
-pub(crate) async fn get_temp_file(id: &str) -> Result<Vec<u8>, ServerError> {
-    let path = TEMP_DIR.join(id);
-    let mut file = File::open(&path).await?;
-    let mut data = Vec::new();
-    file.read_to_end(&mut data).await?;
-    Ok(data)
+pub(crate) async fn load_image(id: &str) -> Result<Vec<u8>, ServerError> {
+    IMAGE_STORE
+        .get(id)
+        .await?
+        .ok_or_else(|| ServerError::ImageNotFound(id.to_string()))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -179,37 +164,20 @@ pub(crate) struct UploadImageResponse {
     image_id: String,
 }
 
-async fn download_url(
-    url: &str,
-    headers: Option<HashMap<String, String>>,
-) -> Result<Vec<u8>, ServerError> {
-    let headers = headers.unwrap_or_default();
-    let request = REQWEST_CLIENT.get(url);
-    let request = headers
-        .iter()
-        .fold(request, |request, (key, value)| request.header(key, value));
-    let response = request.send().await?;
-    let data = response.bytes().await?;
-    Ok(data.to_vec())
-}
-
-pub(crate) async fn upload_image(Json(image_data): Json<ImageData>) -> Response {
-    let data = match image_data {
-        ImageData::Url { url, headers } => match download_url(&url, headers).await {
-            Ok(data) => data,
-            Err(err) => {
-                return handle_server_error(err).into_response();
-            }
-        },
-        ImageData::Path { path } => match std::fs::read(&path) {
-            Ok(data) => data,
-            Err(err) => {
-                return handle_server_error(err.into()).into_response();
-            }
-        },
-        ImageData::Data { data } => data,
+async fn load_image_data(image_data: ImageData) -> Result<Vec<u8>, ServerError> {
+    match image_data {
+        ImageData::Url { url, headers } => download_url(&url, headers).await,
+        ImageData::Path { path } => read_local_path(&path).await,
+        ImageData::Data { data } => Ok(data),
+    }
+}
+
+pub(crate) async fn upload_image(ApiJson(image_data): ApiJson<ImageData>) -> Response {
+    let data = match load_image_data(image_data).await {
+        Ok(data) => data,
+        Err(err) => return handle_server_error(err).into_response(),
     };
-    match create_temp_file(data).await {
+    match store_image(data).await {
         Ok(id) => {
             let response = UploadImageResponse { image_id: id };
             Json(response).into_response()
@@ -218,12 +186,19 @@ pub(crate) async fn upload_image(Json(image_data): Json<ImageData>) -> Response
     }
 }
 
-pub(crate) async fn upload_image_multipart(mut multipart: Multipart) -> Response {
-    while let Some(field) = multipart.next_field().await.unwrap() {
-        let name = field.name().unwrap();
-        if name == "file" {
-            let data = field.bytes().await.unwrap().to_vec();
-            match create_temp_file(data).await {
+pub(crate) async fn upload_image_multipart(ApiMultipart(mut multipart): ApiMultipart) -> Response {
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => return multipart_error(err).into_response(),
+        };
+        if field.name() == Some("file") {
+            let data = match field.bytes().await {
+                Ok(data) => data.to_vec(),
+                Err(err) => return multipart_error(err).into_response(),
+            };
+            match store_image(data).await {
                 Ok(id) => {
                     let response = UploadImageResponse { image_id: id };
                     return Json(response).into_response();
@@ -234,19 +209,73 @@ pub(crate) async fn upload_image_multipart(mut multipart: Multipart) -> Response
             }
         }
     }
-    (StatusCode::BAD_REQUEST, "The field 'file' is required").into_response()
+    invalid_request("The field 'file' is required").into_response()
+}
+
+/// Ids are content hashes, so the id doubles as a strong ETag and the
+/// response never changes while the image exists.
+fn image_cache_headers(id: &str) -> [(HeaderName, String); 2] {
+    [
+        (ETAG, format!("\"{id}\"")),
+        (
+            CACHE_CONTROL,
+            format!(
+                "public, max-age={}, immutable",
+                CONFIG.server.store.ttl_secs
+            ),
+        ),
+    ]
+}
+
+fn is_not_modified(headers: &HeaderMap, id: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+        .any(|tag| tag == "*" || tag == id)
+}
+
+/// Answers a matching `If-None-Match` with 304, as long as the image is still
+/// in the store; an expired id gets the usual not found error.
+async fn not_modified(id: String) -> Response {
+    match IMAGE_STORE.head(&id).await {
+        Ok(Some(_)) => (StatusCode::NOT_MODIFIED, image_cache_headers(&id)).into_response(),
+        Ok(None) => handle_server_error(ServerError::ImageNotFound(id)).into_response(),
+        Err(err) => handle_server_error(err).into_response(),
+    }
 }
 
-pub(crate) async fn get_image(Path(id): Path<String>) -> Response {
-    match get_temp_file(&id).await {
-        Ok(data) => {
-            let kind = infer::get(&data).unwrap();
-            Response::builder()
-                .status(StatusCode::OK)
-                .header("Content-Type", kind.mime_type())
-                .body(Body::from(data))
-                .unwrap()
-        }
+pub(crate) async fn get_image(Path(id): Path<String>, headers: HeaderMap) -> Response {
+    if is_not_modified(&headers, &id) {
+        return not_modified(id).await;
+    }
+    match load_image(&id).await {
+        Ok(data) => (
+            image_cache_headers(&id),
+            [(CONTENT_TYPE, mime_type(&data))],
+            data,
+        )
+            .into_response(),
+        Err(err) => handle_server_error(err).into_response(),
+    }
+}
+
+pub(crate) async fn head_image(Path(id): Path<String>, headers: HeaderMap) -> Response {
+    if is_not_modified(&headers, &id) {
+        return not_modified(id).await;
+    }
+    match IMAGE_STORE.head(&id).await {
+        Ok(Some(meta)) => (
+            image_cache_headers(&id),
+            [
+                (CONTENT_TYPE, meta.mime_type),
+                (CONTENT_LENGTH, meta.size.to_string()),
+            ],
+        )
+            .into_response(),
+        Ok(None) => handle_server_error(ServerError::ImageNotFound(id)).into_response(),
         Err(err) => handle_server_error(err).into_response(),
     }
 }
@@ -262,6 +291,8 @@ struct MemeRequest {
     images: Vec<Image>,
     texts: Vec<String>,
     options: HashMap<String, OptionValue>,
+    #[serde(default)]
+    seed: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -269,27 +300,13 @@ pub(crate) struct ImageResponse {
     image_id: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub(crate) struct ErrorResponse {
-    code: u16,
-    message: String,
-    data: Value,
-}
-
-impl IntoResponse for ErrorResponse {
-    fn into_response(self) -> Response {
-        let body = Json(self);
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
-    }
-}
-
 #[derive(Deserialize)]
 struct SortQuery {
     sort_by: Option<MemeSortBy>,
     sort_reverse: Option<bool>,
 }
 
-async fn meme_keys(Query(query): Query<SortQuery>) -> Response {
+async fn meme_keys(ApiQuery(query): ApiQuery<SortQuery>) -> Response {
     let sort_by = query.sort_by.unwrap_or(MemeSortBy::Key);
     let sort_reverse = query.sort_reverse.unwrap_or(false);
     Json(get_meme_keys_sorted(sort_by, sort_reverse)).into_response()
@@ -299,11 +316,11 @@ async fn meme_info(Path(key): Path<String>) -> Response {
     if let Some(meme) = get_meme(&key) {
         Json(meme.info()).into_response()
     } else {
-        (StatusCode::NOT_FOUND, "Meme not found").into_response()
+        meme_not_found(&key).into_response()
     }
 }
 
-async fn meme_infos(Query(query): Query<SortQuery>) -> Response {
+async fn meme_infos(ApiQuery(query): ApiQuery<SortQuery>) -> Response {
     let sort_by = query.sort_by.unwrap_or(MemeSortBy::Key);
     let sort_reverse = query.sort_reverse.unwrap_or(false);
     let infos = get_memes_sorted(sort_by, sort_reverse)
@@ -319,7 +336,7 @@ struct SearchQuery {
     include_tags: Option<bool>,
 }
 
-async fn meme_search(Query(query): Query<SearchQuery>) -> Response {
+async fn meme_search(ApiQuery(query): ApiQuery<SearchQuery>) -> Response {
     let keys = search_memes(&query.query, query.include_tags.unwrap_or(false));
     Json(keys).into_response()
 }
@@ -327,13 +344,11 @@ async fn meme_search(Query(query): Query<SearchQuery>) -> Response {
 async fn meme_preview_get(Path(key): Path<String>) -> Response {
     let meme = match get_meme(&key) {
         Some(meme) => meme,
-        None => return (StatusCode::NOT_FOUND, "Meme not found").into_response(),
+        None => return meme_not_found(&key).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || meme.generate_preview(HashMap::new()))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_generation(&key, move || meme.generate_preview(HashMap::new())).await;
     handle_image_result(result).await
 }
 
@@ -343,48 +358,306 @@ struct PreviewRequest {
     options: HashMap<String, OptionValue>,
 }
 
-async fn meme_preview(Path(key): Path<String>, payload: Option<Json<PreviewRequest>>) -> Response {
+async fn meme_preview(
+    Path(key): Path<String>,
+    payload: Option<ApiJson<PreviewRequest>>,
+) -> Response {
     let meme = match get_meme(&key) {
         Some(meme) => meme,
-        None => return (StatusCode::NOT_FOUND, "Meme not found").into_response(),
+        None => return meme_not_found(&key).into_response(),
     };
 
     let options = payload.map(|p| p.0.options).unwrap_or_default();
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || meme.generate_preview(options))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_generation(&key, move || meme.generate_preview(options)).await;
     handle_image_result(result).await
 }
 
-async fn meme_generate(Path(key): Path<String>, Json(payload): Json<MemeRequest>) -> Response {
+async fn meme_generate(
+    Path(key): Path<String>,
+    ApiJson(payload): ApiJson<MemeRequest>,
+) -> Response {
     let meme = match get_meme(&key) {
         Some(meme) => meme,
-        None => return (StatusCode::NOT_FOUND, "Meme not found").into_response(),
+        None => return meme_not_found(&key).into_response(),
     };
 
     let mut images: Vec<meme::Image> = Vec::new();
     for Image { name, id } in payload.images {
-        match get_temp_file(&id).await {
+        match load_image(&id).await {
             Ok(data) => images.push(meme::Image { name, data }),
             Err(err) => return handle_server_error(err).into_response(),
         }
     }
     let texts = payload.texts;
     let options = payload.options;
+    let seed = payload.seed;
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || meme.generate(images, texts, options))
-        .await
-        .unwrap();
-    handle_image_result(result).await
+    let _permit = acquire_permit().await;
+    let result = run_generation(&key, move || {
+        generate_with_cache(meme.as_ref(), images, texts, options, seed)
+    })
+    .await;
+    match result {
+        Ok((data, cache_status)) => {
+            let mut response = handle_image_result(Ok(data)).await;
+            response.headers_mut().insert(
+                "X-Meme-Cache",
+                HeaderValue::from_static(cache_status.as_str()),
+            );
+            response
+        }
+        Err(error) => handle_error(error).into_response(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InlineImage {
+    #[serde(default)]
+    name: String,
+    image: ImageData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InlineMemeRequest {
+    #[serde(default)]
+    pub(crate) images: Vec<InlineImage>,
+    #[serde(default)]
+    pub(crate) texts: Vec<String>,
+    #[serde(default)]
+    pub(crate) options: HashMap<String, OptionValue>,
+    #[serde(default)]
+    pub(crate) seed: Option<u64>,
+}
+
+#[derive(Default)]
+pub(crate) struct MemeInput {
+    pub(crate) images: Vec<meme::Image>,
+    pub(crate) texts: Vec<String>,
+    pub(crate) options: HashMap<String, OptionValue>,
+    pub(crate) seed: Option<u64>,
+}
+
+pub(crate) async fn load_inline_request(
+    payload: InlineMemeRequest,
+) -> Result<MemeInput, ServerError> {
+    let mut images: Vec<meme::Image> = Vec::new();
+    for InlineImage { name, image } in payload.images {
+        let data = load_image_data(image).await?;
+        images.push(meme::Image { name, data });
+    }
+    Ok(MemeInput {
+        images,
+        texts: payload.texts,
+        options: payload.options,
+        seed: payload.seed,
+    })
+}
+
+/// Reads a multipart body with the fields:
+/// - `images`: image files, in order
+/// - `names`: names of the images, the n-th name belongs to the n-th image
+/// - `texts`: texts, in order
+/// - `options`: options as a JSON object
+/// - `seed`: random seed
+async fn parse_multipart_request(mut multipart: Multipart) -> Result<MemeInput, Response> {
+    let bad_request = |message: String| invalid_request(message).into_response();
+
+    let mut input = MemeInput::default();
+    let mut names: Vec<String> = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => return Err(multipart_error(err).into_response()),
+        };
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            "images" => match field.bytes().await {
+                Ok(data) => input.images.push(meme::Image {
+                    name: String::new(),
+                    data: data.to_vec(),
+                }),
+                Err(err) => return Err(multipart_error(err).into_response()),
+            },
+            "names" | "texts" | "options" | "seed" => {
+                let value = match field.text().await {
+                    Ok(value) => value,
+                    Err(err) => return Err(multipart_error(err).into_response()),
+                };
+                match name.as_str() {
+                    "names" => names.push(value),
+                    "texts" => input.texts.push(value),
+                    "options" => match serde_json::from_str(&value) {
+                        Ok(options) => input.options = options,
+                        Err(err) => {
+                            return Err(bad_request(format!("Invalid field 'options': {err}")));
+                        }
+                    },
+                    _ => match value.trim().parse() {
+                        Ok(seed) => input.seed = Some(seed),
+                        Err(err) => {
+                            return Err(bad_request(format!("Invalid field 'seed': {err}")));
+                        }
+                    },
+                }
+            }
+            _ => return Err(bad_request(format!("Unknown field '{name}'"))),
+        }
+    }
+    for (image, name) in input.images.iter_mut().zip(names) {
+        image.name = name;
+    }
+    Ok(input)
+}
+
+/// Picks the output format from the `Accept` header.
+///
+/// Returns `Some(None)` to keep the generated format, and `None` when no
+/// acceptable format can be produced. Animated results prefer gif and fall
+/// back to the first frame in a static format.
+fn negotiate_format(accept: Option<&str>, is_animated: bool) -> Option<Option<ImageFormat>> {
+    let Some(accept) = accept else {
+        return Some(None);
+    };
+    let mut media_types = accept
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let media_type = parts.next()?.trim().to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then_some((media_type, quality))
+        })
+        .collect::<Vec<_>>();
+    if media_types.is_empty() {
+        return Some(None);
+    }
+    media_types.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut fallback = None;
+    for (media_type, _) in media_types {
+        if media_type == "*/*" || media_type == "image/*" {
+            return Some(None);
+        }
+        match ImageFormat::from_mime_type(&media_type) {
+            Some(ImageFormat::Gif) if is_animated => return Some(None),
+            Some(format) if !is_animated => return Some(Some(format)),
+            Some(format) => {
+                fallback.get_or_insert(format);
+            }
+            None => {}
+        }
+    }
+    fallback.map(Some)
+}
+
+struct GeneratedImage {
+    data: Vec<u8>,
+    cache_status: CacheStatus,
+    info: ImageInfo,
+}
+
+async fn meme_generate_image(Path(key): Path<String>, request: Request) -> Response {
+    let meme = match get_meme(&key) {
+        Some(meme) => meme,
+        None => return meme_not_found(&key).into_response(),
+    };
+
+    let accept = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let is_multipart = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let input = if is_multipart {
+        match Multipart::from_request(request, &()).await {
+            Ok(multipart) => parse_multipart_request(multipart).await,
+            Err(rejection) => return multipart_rejection(rejection).into_response(),
+        }
+    } else {
+        match ApiJson::<InlineMemeRequest>::from_request(request, &()).await {
+            Ok(ApiJson(payload)) => load_inline_request(payload)
+                .await
+                .map_err(|err| handle_server_error(err).into_response()),
+            Err(error) => return error.into_response(),
+        }
+    };
+    let MemeInput {
+        images,
+        texts,
+        options,
+        seed,
+    } = match input {
+        Ok(input) => input,
+        Err(response) => return response,
+    };
+
+    let _permit = acquire_permit().await;
+    let result = run_generation(&key, move || -> Result<Option<GeneratedImage>, Error> {
+        let (data, cache_status) =
+            generate_with_cache(meme.as_ref(), images, texts, options, seed)?;
+        let info = image_operations::inspect(data.clone())?;
+        let Some(format) = negotiate_format(accept.as_deref(), info.is_multi_frame) else {
+            return Ok(None);
+        };
+        let (data, info) = match format {
+            Some(format) => {
+                let data = convert_format(data, format)?;
+                let info = image_operations::inspect(data.clone())?;
+                (data, info)
+            }
+            None => (data, info),
+        };
+        Ok(Some(GeneratedImage {
+            data,
+            cache_status,
+            info,
+        }))
+    })
+    .await;
+
+    let GeneratedImage {
+        data,
+        cache_status,
+        info,
+    } = match result {
+        Ok(Some(image)) => image,
+        Ok(None) => return not_acceptable().into_response(),
+        Err(error) => return handle_error(error).into_response(),
+    };
+
+    let mime_type = infer::get(&data)
+        .map(|kind| kind.mime_type())
+        .unwrap_or("application/octet-stream");
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, mime_type)
+        .header(CONTENT_LENGTH, data.len())
+        .header("X-Meme-Key", &key)
+        .header("X-Meme-Cache", cache_status.as_str())
+        .header("X-Image-Width", info.width)
+        .header("X-Image-Height", info.height);
+    if let (Some(frame_count), Some(duration)) = (info.frame_count, info.average_duration) {
+        response = response
+            .header("X-Image-Frame-Count", frame_count)
+            .header("X-Image-Duration", duration.to_string());
+    }
+    response.body(Body::from(data)).unwrap()
 }
 
 pub(crate) async fn handle_image_result(result: Result<Vec<u8>, Error>) -> Response {
     match result {
         Ok(data) => {
-            let id = match create_temp_file(data).await {
+            let id = match store_image(data).await {
                 Ok(id) => id,
                 Err(err) => return handle_server_error(err).into_response(),
             };
@@ -398,123 +671,228 @@ pub(crate) async fn handle_image_result(result: Result<Vec<u8>, Error>) -> Respo
 pub(crate) fn handle_server_error(error: ServerError) -> ErrorResponse {
     let message = format!("{error}");
     warn!("Server error: {message}");
-    match error {
-        ServerError::RequestError(err) => ErrorResponse {
-            code: 410,
-            message,
-            data: json!({ "error": format!("{err}") }),
-        },
-        ServerError::IOError(err) => ErrorResponse {
-            code: 420,
-            message,
-            data: json!({ "error": format!("{err}") }),
-        },
-        ServerError::MemeGeneratorError(err) => handle_error(err),
-    }
+    let response = match error {
+        ServerError::RequestError(err) => {
+            REQUEST_ERROR.error(message, json!({ "error": format!("{err}") }))
+        }
+        ServerError::FetchDenied(reason) => {
+            FETCH_DENIED.error(message, json!({ "reason": reason }))
+        }
+        ServerError::FetchTooLarge(max) => FETCH_TOO_LARGE.error(message, json!({ "max": max })),
+        ServerError::ImageNotFound(id) => IMAGE_NOT_FOUND.error(message, json!({ "id": id })),
+        ServerError::StoreError(err) => STORE_ERROR.error(message, json!({ "error": err })),
+        ServerError::IOError(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            IMAGE_NOT_FOUND.error(message, json!({ "error": format!("{err}") }))
+        }
+        ServerError::IOError(err) => IO_ERROR.error(message, json!({ "error": format!("{err}") })),
+        ServerError::MemeGeneratorError(err) => return handle_error(err),
+    };
+    response
 }
 
 pub(crate) fn handle_error(error: Error) -> ErrorResponse {
     let message = format!("{error}");
     warn!("Meme error: {message}");
-    match error {
-        Error::ImageDecodeError(err) => ErrorResponse {
-            code: 510,
-            message,
-            data: json!({ "error": err }),
-        },
-        Error::ImageEncodeError(err) => ErrorResponse {
-            code: 520,
-            message,
-            data: json!({ "error": err }),
-        },
-        Error::ImageAssetMissing(path) => ErrorResponse {
-            code: 530,
-            message,
-            data: json!({ "path": path }),
-        },
-        Error::DeserializeError(err) => ErrorResponse {
-            code: 540,
-            message,
-            data: json!({ "error": err }),
-        },
-        Error::ImageNumberMismatch(min, max, actual) => ErrorResponse {
-            code: 550,
-            message,
-            data: json!({ "min": min, "max": max, "actual": actual }),
-        },
-        Error::TextNumberMismatch(min, max, actual) => ErrorResponse {
-            code: 551,
-            message,
-            data: json!({ "min": min, "max": max, "actual": actual }),
-        },
-        Error::TextOverLength(text) => ErrorResponse {
-            code: 560,
-            message,
-            data: json!({ "text": text }),
-        },
-        Error::MemeFeedback(feedback) => ErrorResponse {
-            code: 570,
+    let response = match error {
+        Error::ImageDecodeError(err) => IMAGE_DECODE_ERROR.error(message, json!({ "error": err })),
+        Error::ImageEncodeError(err) => IMAGE_ENCODE_ERROR.error(message, json!({ "error": err })),
+        Error::ImageAssetMissing(path) => {
+            IMAGE_ASSET_MISSING.error(message, json!({ "path": path }))
+        }
+        Error::ImageLimitExceeded(limit, max, actual) => IMAGE_LIMIT_EXCEEDED.error(
             message,
-            data: json!({ "feedback": feedback }),
-        },
+            json!({ "limit": limit, "max": max, "actual": actual }),
+        ),
+        Error::DeserializeError(err) => DESERIALIZE_ERROR.error(message, json!({ "error": err })),
+        Error::ImageNumberMismatch(min, max, actual) => IMAGE_NUMBER_MISMATCH
+            .error(message, json!({ "min": min, "max": max, "actual": actual })),
+        Error::TextNumberMismatch(min, max, actual) => {
+            TEXT_NUMBER_MISMATCH.error(message, json!({ "min": min, "max": max, "actual": actual }))
+        }
+        Error::TextOverLength(text) => TEXT_OVER_LENGTH.error(message, json!({ "text": text })),
+        Error::MemeFeedback(feedback) => {
+            MEME_FEEDBACK.error(message, json!({ "feedback": feedback }))
+        }
+        Error::InternalError(key, error) => {
+            INTERNAL_ERROR.error(message, json!({ "key": key, "error": error }))
+        }
+    };
+    response
+}
+
+/// Runs a generation task on the blocking pool. A panic escaping the task is
+/// turned into `Error::InternalError` instead of tearing down the handler.
+pub(crate) async fn run_generation<T, E>(
+    key: &str,
+    func: impl FnOnce() -> Result<T, E> + Send + 'static,
+) -> Result<T, E>
+where
+    T: Send + 'static,
+    E: From<Error> + Send + 'static,
+{
+    let start = Instant::now();
+    let result = run_blocking(key, func).await;
+    observe_generation(key, start.elapsed());
+    result
+}
+
+/// Like [`run_generation`] for tasks that are not memes, such as the tools,
+/// which are not recorded in the generation metrics.
+pub(crate) async fn run_blocking<T, E>(
+    key: &str,
+    func: impl FnOnce() -> Result<T, E> + Send + 'static,
+) -> Result<T, E>
+where
+    T: Send + 'static,
+    E: From<Error> + Send + 'static,
+{
+    match spawn_blocking(func).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => Err(Error::from_panic(key, err.into_panic()).into()),
+        Err(err) => Err(Error::InternalError(key.to_string(), format!("{err}")).into()),
     }
 }
 
-pub async fn run_server(host: Option<IpAddr>, port: Option<u16>) {
-    clear_temp_dir();
+/// Waits for one of the `max_concurrent_tasks` slots and records the wait.
+pub(crate) async fn acquire_permit() -> SemaphorePermit<'static> {
+    let start = Instant::now();
+    let permit = SEMAPHORE.acquire().await.unwrap();
+    observe_semaphore_wait(start.elapsed());
+    permit
+}
 
-    let cleanup_task = {
-        let mut interval = interval(Duration::from_secs(10 * 60));
-        tokio::spawn(async move {
-            loop {
-                interval.tick().await;
-                cleanup_temp_files().await;
-            }
-        })
-    };
+async fn clear_meme_cache() -> Response {
+    spawn_blocking(clear_cache).await.unwrap();
+    StatusCode::NO_CONTENT.into_response()
+}
 
-    let app = Router::new()
-        .route("/image/upload", post(upload_image))
-        .route("/image/upload/multipart", post(upload_image_multipart))
-        .route("/image/{id}", get(get_image))
-        .route("/meme/version", get(|| async { VERSION }))
-        .route("/meme/keys", get(meme_keys))
-        .route("/meme/infos", get(meme_infos))
-        .route("/meme/search", get(meme_search))
-        .route("/memes/{key}/info", get(meme_info))
-        .route(
+/// Routes that need an API key when authentication is enabled.
+pub(crate) fn key_routes() -> Vec<(&'static str, MethodRouter)> {
+    vec![
+        ("/image/upload", post(upload_image)),
+        ("/image/upload/multipart", post(upload_image_multipart)),
+        ("/image/{id}", get(get_image).head(head_image)),
+        ("/meme/keys", get(meme_keys)),
+        ("/meme/infos", get(meme_infos)),
+        ("/meme/search", get(meme_search)),
+        ("/memes/{key}/info", get(meme_info)),
+        (
             "/memes/{key}/preview",
             get(meme_preview_get).post(meme_preview),
-        )
-        .route("/memes/{key}", post(meme_generate))
-        .route("/tools/render_list", post(render_list))
-        .route("/tools/render_statistics", post(render_statistics))
-        .route("/tools/image_operations/inspect", post(inspect))
-        .route(
+        ),
+        ("/memes/batch", post(meme_batch)),
+        ("/memes/{key}", post(meme_generate)),
+        ("/memes/{key}/generate", post(meme_generate_image)),
+        ("/jobs", post(create_job)),
+        ("/jobs/{id}", get(job_status).delete(cancel_job)),
+        ("/tools/render_list", post(render_list)),
+        ("/tools/render_statistics", post(render_statistics)),
+        ("/tools/parse_bbcode", post(parse_bbcode)),
+        ("/tools/image_operations/inspect", post(inspect)),
+        (
             "/tools/image_operations/flip_horizontal",
             post(flip_horizontal),
-        )
-        .route("/tools/image_operations/flip_vertical", post(flip_vertical))
-        .route("/tools/image_operations/rotate", post(rotate))
-        .route("/tools/image_operations/resize", post(resize))
-        .route("/tools/image_operations/crop", post(crop))
-        .route("/tools/image_operations/grayscale", post(grayscale))
-        .route("/tools/image_operations/invert", post(invert))
-        .route(
+        ),
+        ("/tools/image_operations/flip_vertical", post(flip_vertical)),
+        ("/tools/image_operations/rotate", post(rotate)),
+        ("/tools/image_operations/resize", post(resize)),
+        ("/tools/image_operations/crop", post(crop)),
+        ("/tools/image_operations/grayscale", post(grayscale)),
+        ("/tools/image_operations/invert", post(invert)),
+        (
             "/tools/image_operations/merge_horizontal",
             post(merge_horizontal),
-        )
-        .route(
+        ),
+        (
             "/tools/image_operations/merge_vertical",
             post(merge_vertical),
-        )
-        .route("/tools/image_operations/gif_split", post(gif_split))
-        .route("/tools/image_operations/gif_merge", post(gif_merge))
-        .route("/tools/image_operations/gif_reverse", post(gif_reverse))
-        .route(
+        ),
+        ("/tools/image_operations/gif_split", post(gif_split)),
+        ("/tools/image_operations/gif_merge", post(gif_merge)),
+        ("/tools/image_operations/gif_reverse", post(gif_reverse)),
+        (
             "/tools/image_operations/gif_change_duration",
             post(gif_change_duration),
-        )
+        ),
+    ]
+}
+
+/// Whether `/metrics` is served without a key. Without authentication there
+/// are no admin keys, so it is public then as well.
+pub(crate) fn public_metrics() -> bool {
+    metrics_are_public(&CONFIG.server)
+}
+
+fn metrics_are_public(config: &ServerConfig) -> bool {
+    config.public_metrics || !config.auth.enabled
+}
+
+/// Routes that need an admin key, they are closed when authentication is disabled.
+pub(crate) fn admin_routes() -> Vec<(&'static str, MethodRouter)> {
+    let mut routes = vec![
+        ("/admin/keys", get(key_usage)),
+        ("/admin/cache/clear", post(clear_meme_cache)),
+    ];
+    if !public_metrics() {
+        routes.push(("/metrics", get(metrics)));
+    }
+    routes
+}
+
+/// Routes open to everyone.
+pub(crate) fn public_routes() -> Vec<(&'static str, MethodRouter)> {
+    let mut routes = vec![
+        ("/meme/version", get(|| async { VERSION })),
+        ("/errors", get(error_catalog)),
+        ("/healthz", get(healthz)),
+        ("/readyz", get(readyz)),
+        ("/openapi.json", get(openapi)),
+    ];
+    if public_metrics() {
+        routes.push(("/metrics", get(metrics)));
+    }
+    if CONFIG.server.docs {
+        routes.push(("/docs", get(docs)));
+    }
+    routes
+}
+
+fn router(routes: Vec<(&'static str, MethodRouter)>) -> Router {
+    routes
+        .into_iter()
+        .fold(Router::new(), |router, (path, route)| {
+            router.route(path, route)
+        })
+}
+
+pub async fn run_server(host: Option<IpAddr>, port: Option<u16>) {
+    LazyLock::force(&IMAGE_STORE);
+    start_workers();
+    if CONFIG.server.auth.enabled && CONFIG.server.auth.keys.is_empty() {
+        warn!("API key authentication is enabled, but no keys are configured");
+    }
+
+    let cleanup_task = {
+        // sweep often enough that images don't outlive their ttl by much
+        let period = (CONFIG.server.store.ttl_secs / 2).clamp(10, 10 * 60);
+        let mut interval = interval(Duration::from_secs(period));
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                if let Err(err) = IMAGE_STORE.cleanup().await {
+                    warn!("Failed to clean up image store: {err}");
+                }
+                cleanup_jobs();
+            }
+        })
+    };
+
+    let admin = router(admin_routes()).route_layer(middleware::from_fn(require_admin));
+    let app = router(key_routes())
+        .merge(admin)
+        .route_layer(middleware::from_fn(require_key))
+        .merge(router(public_routes()))
+        .route_layer(middleware::from_fn(track_requests))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
@@ -540,3 +918,75 @@ pub async fn run_server(host: Option<IpAddr>, port: Option<u16>) {
 pub fn run_server_sync(host: Option<IpAddr>, port: Option<u16>) {
     Runtime::new().unwrap().block_on(run_server(host, port));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_format_without_accept_keeps_format() {
+        assert_eq!(negotiate_format(None, false), Some(None));
+        assert_eq!(negotiate_format(None, true), Some(None));
+        assert_eq!(negotiate_format(Some(""), false), Some(None));
+        assert_eq!(negotiate_format(Some("*/*"), true), Some(None));
+        assert_eq!(negotiate_format(Some("image/*"), false), Some(None));
+    }
+
+    #[test]
+    fn negotiate_format_picks_highest_quality() {
+        assert_eq!(
+            negotiate_format(Some("image/png;q=0.5, image/webp;q=0.9"), false),
+            Some(Some(ImageFormat::Webp))
+        );
+        assert_eq!(
+            negotiate_format(Some("image/jpeg, image/png;q=0.1"), false),
+            Some(Some(ImageFormat::Jpeg))
+        );
+        assert_eq!(
+            negotiate_format(Some("image/webp;q=0, image/png"), false),
+            Some(Some(ImageFormat::Png))
+        );
+    }
+
+    #[test]
+    fn negotiate_format_for_animated_images() {
+        assert_eq!(negotiate_format(Some("image/gif"), true), Some(None));
+        assert_eq!(
+            negotiate_format(Some("image/png, image/gif;q=0.5"), true),
+            Some(None)
+        );
+        assert_eq!(
+            negotiate_format(Some("image/webp, image/png;q=0.5"), true),
+            Some(Some(ImageFormat::Webp))
+        );
+    }
+
+    #[test]
+    fn metrics_are_reachable_with_default_config() {
+        let mut config = ServerConfig::default();
+        assert!(metrics_are_public(&config));
+
+        config.auth.enabled = true;
+        assert!(!metrics_are_public(&config));
+        config.public_metrics = true;
+        assert!(metrics_are_public(&config));
+    }
+
+    #[tokio::test]
+    async fn run_blocking_turns_panics_into_internal_errors() {
+        let result: Result<(), Error> = run_blocking("flip_horizontal", || panic!("boom")).await;
+        match result {
+            Err(Error::InternalError(key, message)) => {
+                assert_eq!(key, "flip_horizontal");
+                assert!(message.contains("boom"));
+            }
+            _ => panic!("expected an internal error"),
+        }
+    }
+
+    #[test]
+    fn negotiate_format_rejects_unsupported_types() {
+        assert_eq!(negotiate_format(Some("text/html"), false), None);
+        assert_eq!(negotiate_format(Some("image/avif"), true), None);
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use tokio::{fs, io::AsyncReadExt};
+use tracing::{info, warn};
+
+use meme_generator::MEME_HOME;
+
+use crate::config::CONFIG;
+
+use super::{ImageMeta, ImageStore, StoreFuture, StoreUsage, is_valid_id, mime_type, too_large};
+
+struct Entry {
+    size: u64,
+    last_access: SystemTime,
+}
+
+#[derive(Default)]
+struct Index {
+    entries: HashMap<String, Entry>,
+    size: u64,
+    /// Bytes of images that are being written and not yet in `entries`
+    reserved: u64,
+}
+
+impl Index {
+    fn insert(&mut self, id: String, size: u64, last_access: SystemTime) {
+        self.remove(&id);
+        self.size += size;
+        self.entries.insert(id, Entry { size, last_access });
+    }
+
+    fn remove(&mut self, id: &str) -> bool {
+        match self.entries.remove(id) {
+            Some(entry) => {
+                self.size -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_expired(entry: &Entry, ttl: Duration) -> bool {
+        entry
+            .last_access
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed > ttl)
+    }
+
+    fn remove_expired(&mut self, ttl: Duration) -> Vec<String> {
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| Self::is_expired(entry, ttl))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        for id in &expired {
+            self.remove(id);
+        }
+        expired
+    }
+
+    /// Evicts the least recently used images until `incoming` more bytes fit.
+    fn make_room(&mut self, incoming: u64, max_bytes: u64) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while self.size + self.reserved + incoming > max_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => {
+                    self.remove(&id);
+                    evicted.push(id);
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    /// Marks an image as used, returns its size if it exists and is not expired.
+    fn touch(&mut self, id: &str, ttl: Duration) -> Option<u64> {
+        let entry = self.entries.get_mut(id)?;
+        if Self::is_expired(entry, ttl) {
+            return None;
+        }
+        entry.last_access = SystemTime::now();
+        Some(entry.size)
+    }
+}
+
+/// Space taken in the index for an image while its file is written, so that
+/// concurrent puts cannot together grow beyond `max_bytes`.
+struct Reservation<'a> {
+    index: &'a Mutex<Index>,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl<'a> Reservation<'a> {
+    /// Evicts images until `size` more bytes fit and reserves them, returns
+    /// the evicted ids.
+    fn new(index: &'a Mutex<Index>, size: u64, max_bytes: u64) -> (Self, Vec<String>) {
+        let mut guard = index.lock().unwrap();
+        let evicted = guard.make_room(size, max_bytes);
+        guard.reserved += size;
+        let reservation = Self {
+            index,
+            size,
+            max_bytes,
+        };
+        (reservation, evicted)
+    }
+
+    /// Turns the reserved space into an entry of the index, returns the ids
+    /// evicted because other puts reserved space at the same time.
+    fn commit(self, id: &str) -> Vec<String> {
+        let mut index = self.index.lock().unwrap();
+        index.reserved -= self.size;
+        // the image replaces any earlier version, which must not be evicted
+        index.remove(id);
+        let evicted = index.make_room(self.size, self.max_bytes);
+        index.insert(id.to_string(), self.size, SystemTime::now());
+        drop(index);
+        std::mem::forget(self);
+        evicted
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.index.lock().unwrap().reserved -= self.size;
+    }
+}
+
+/// Keeps images as files named by their id. The index of sizes and access
+/// times lives in memory and is rebuilt from the files on start.
+pub(crate) struct DiskStore {
+    dir: PathBuf,
+    index: Mutex<Index>,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl DiskStore {
+    pub(crate) fn new() -> Self {
+        let config = &CONFIG.server.store;
+        let dir = config
+            .disk_dir
+            .clone()
+            .unwrap_or_else(|| MEME_HOME.join("tmp"));
+        if !config.persist {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        if let Err(err) = std::fs::create_dir_all(&dir) {
+            warn!("Failed to create image store directory {dir:?}: {err}");
+        }
+
+        let mut index = Index::default();
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let id = entry.file_name().to_string_lossy().to_string();
+                // left behind by a put that was interrupted
+                if id.ends_with(".tmp") {
+                    let _ = std::fs::remove_file(entry.path());
+                    continue;
+                }
+                if !is_valid_id(&id) {
+                    continue;
+                }
+                if let Ok(metadata) = entry.metadata()
+                    && metadata.is_file()
+                {
+                    let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+                    index.insert(id, metadata.len(), modified);
+                }
+            }
+        }
+        if !index.entries.is_empty() {
+            info!(
+                "Loaded {} images ({} bytes) from {dir:?}",
+                index.entries.len(),
+                index.size
+            );
+        }
+
+        Self {
+            dir,
+            index: Mutex::new(index),
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_bytes: config.max_bytes,
+        }
+    }
+
+    async fn remove_files(&self, ids: Vec<String>) {
+        for id in ids {
+            if let Err(err) = fs::remove_file(self.dir.join(&id)).await
+                && err.kind() != ErrorKind::NotFound
+            {
+                warn!("Failed to delete image {id}: {err}");
+            }
+        }
+    }
+
+    fn touch(&self, id: &str) -> Option<u64> {
+        if !is_valid_id(id) {
+            return None;
+        }
+        self.index.lock().unwrap().touch(id, self.ttl)
+    }
+
+    /// Drops an image from the index when its file disappeared.
+    fn forget(&self, id: &str) {
+        self.index.lock().unwrap().remove(id);
+    }
+}
+
+impl ImageStore for DiskStore {
+    fn put<'a>(&'a self, id: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            if self.touch(id).is_some() {
+                return Ok(());
+            }
+            let size = data.len() as u64;
+            if size > self.max_bytes {
+                return Err(too_large(size, self.max_bytes));
+            }
+            let (reservation, evicted) = Reservation::new(&self.index, size, self.max_bytes);
+            self.remove_files(evicted).await;
+
+            // write and rename, so that readers never see a partial file; every
+            // put has its own temporary file, as the same id may be put concurrently
+            let path = self.dir.join(id);
+            let temp_path = self
+                .dir
+                .join(format!("{id}.{:016x}.tmp", rand::random::<u64>()));
+            let result = match fs::write(&temp_path, &data).await {
+                Ok(()) => fs::rename(&temp_path, &path).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                let _ = fs::remove_file(&temp_path).await;
+                return Err(err.into());
+            }
+            let evicted = reservation.commit(id);
+            self.remove_files(evicted).await;
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            if self.touch(id).is_none() {
+                return Ok(None);
+            }
+            match fs::read(self.dir.join(id)).await {
+                Ok(data) => Ok(Some(data)),
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    self.forget(id);
+                    Ok(None)
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    fn head<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<ImageMeta>> {
+        Box::pin(async move {
+            let Some(size) = self.touch(id) else {
+                return Ok(None);
+            };
+            let mut file = match fs::File::open(self.dir.join(id)).await {
+                Ok(file) => file,
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    self.forget(id);
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            };
+            // enough to recognize the image format
+            let mut header = vec![0; 64];
+            let read = file.read(&mut header).await?;
+            header.truncate(read);
+            Ok(Some(ImageMeta {
+                size,
+                mime_type: mime_type(&header),
+            }))
+        })
+    }
+
+    fn cleanup(&self) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            let expired = self.index.lock().unwrap().remove_expired(self.ttl);
+            if !expired.is_empty() {
+                info!("Removed {} expired images", expired.len());
+            }
+            self.remove_files(expired).await;
+            Ok(())
+        })
+    }
+
+    fn usage(&self) -> Option<StoreUsage> {
+        let index = self.index.lock().unwrap();
+        Some(StoreUsage {
+            images: index.entries.len() as u64,
+            bytes: index.size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::server::ServerError;
+
+    fn store(name: &str, max_bytes: u64) -> DiskStore {
+        let dir = std::env::temp_dir().join(format!("meme-store-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        DiskStore {
+            dir,
+            index: Mutex::new(Index::default()),
+            ttl: Duration::from_secs(600),
+            max_bytes,
+        }
+    }
+
+    fn id(n: u8) -> String {
+        format!("{n:032x}")
+    }
+
+    #[test]
+    fn make_room_evicts_least_recently_used() {
+        let now = SystemTime::now();
+        let mut index = Index::default();
+        index.insert(id(1), 4, now - Duration::from_secs(30));
+        index.insert(id(2), 4, now - Duration::from_secs(10));
+        index.insert(id(3), 4, now - Duration::from_secs(20));
+
+        assert_eq!(index.make_room(4, 12), vec![id(1)]);
+        assert_eq!(index.make_room(8, 12), vec![id(3)]);
+        assert_eq!(index.size, 4);
+    }
+
+    #[tokio::test]
+    async fn rejects_images_larger_than_the_store() {
+        let store = store("large", 8);
+        store.put(&id(1), vec![0; 6]).await.unwrap();
+
+        let result = store.put(&id(2), vec![0; 9]).await;
+        assert!(matches!(result, Err(ServerError::StoreError(_))));
+        // the existing image is not evicted for an image that cannot fit
+        assert_eq!(store.get(&id(1)).await.unwrap(), Some(vec![0; 6]));
+
+        store.put(&id(3), vec![0; 8]).await.unwrap();
+        assert_eq!(store.get(&id(1)).await.unwrap(), None);
+        assert_eq!(store.usage().unwrap().bytes, 8);
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn reservations_count_against_max_bytes() {
+        let index = Mutex::new(Index::default());
+        let (first, evicted) = Reservation::new(&index, 6, 8);
+        assert!(evicted.is_empty());
+        assert_eq!(index.lock().unwrap().reserved, 6);
+
+        // nothing to evict yet, so the second put is checked again on commit
+        let (second, _) = Reservation::new(&index, 6, 8);
+        assert_eq!(index.lock().unwrap().reserved, 12);
+        assert!(first.commit(&id(1)).is_empty());
+        assert_eq!(second.commit(&id(2)), vec![id(1)]);
+        {
+            let index = index.lock().unwrap();
+            assert_eq!((index.size, index.reserved), (6, 0));
+        }
+
+        let (third, _) = Reservation::new(&index, 4, 8);
+        drop(third);
+        assert_eq!(index.lock().unwrap().reserved, 0);
+    }
+
+    #[tokio::test]
+    async fn concurrent_puts_stay_within_max_bytes() {
+        let store = Arc::new(store("concurrent", 16));
+        let handles = (0..16u8)
+            .map(|n| {
+                let store = store.clone();
+                // every id is put twice at the same time
+                tokio::spawn(async move { store.put(&id(n / 2), vec![n / 2; 4]).await })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let usage = store.usage().unwrap();
+        assert!(usage.bytes <= 16);
+        assert_eq!(store.index.lock().unwrap().reserved, 0);
+        let files = std::fs::read_dir(&store.dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert!(files.iter().all(|file| !file.ends_with(".tmp")));
+        for n in 0..8 {
+            if let Some(data) = store.get(&id(n)).await.unwrap() {
+                assert_eq!(data, vec![n; 4]);
+            }
+        }
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+}
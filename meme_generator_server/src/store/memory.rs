@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::config::CONFIG;
+
+use super::{ImageMeta, ImageStore, StoreFuture, StoreUsage, mime_type, too_large};
+
+struct Entry {
+    data: Vec<u8>,
+    last_access: Instant,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    size: u64,
+}
+
+impl Entries {
+    fn remove(&mut self, id: &str) {
+        if let Some(entry) = self.entries.remove(id) {
+            self.size -= entry.data.len() as u64;
+        }
+    }
+
+    fn remove_expired(&mut self, ttl: Duration) {
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_access.elapsed() > ttl)
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        for id in expired {
+            self.remove(&id);
+        }
+    }
+
+    /// Evicts the least recently used images until `incoming` more bytes fit.
+    fn make_room(&mut self, incoming: u64, max_bytes: u64) {
+        while self.size + incoming > max_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(id, _)| id.clone());
+            match oldest {
+                Some(id) => self.remove(&id),
+                None => break,
+            }
+        }
+    }
+
+    fn touch(&mut self, id: &str, ttl: Duration) -> Option<&Entry> {
+        if self
+            .entries
+            .get(id)
+            .is_some_and(|entry| entry.last_access.elapsed() > ttl)
+        {
+            self.remove(id);
+            return None;
+        }
+        let entry = self.entries.get_mut(id)?;
+        entry.last_access = Instant::now();
+        Some(entry)
+    }
+}
+
+/// Keeps images in memory, they are lost on restart.
+pub(crate) struct MemoryStore {
+    entries: Mutex<Entries>,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl MemoryStore {
+    pub(crate) fn new() -> Self {
+        let config = &CONFIG.server.store;
+        Self {
+            entries: Mutex::new(Entries::default()),
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_bytes: config.max_bytes,
+        }
+    }
+}
+
+impl ImageStore for MemoryStore {
+    fn put<'a>(&'a self, id: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.touch(id, self.ttl).is_some() {
+                return Ok(());
+            }
+            let size = data.len() as u64;
+            if size > self.max_bytes {
+                return Err(too_large(size, self.max_bytes));
+            }
+            entries.make_room(size, self.max_bytes);
+            entries.size += size;
+            entries.entries.insert(
+                id.to_string(),
+                Entry {
+                    data,
+                    last_access: Instant::now(),
+                },
+            );
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().unwrap();
+            Ok(entries.touch(id, self.ttl).map(|entry| entry.data.clone()))
+        })
+    }
+
+    fn head<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<ImageMeta>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().unwrap();
+            Ok(entries.touch(id, self.ttl).map(|entry| ImageMeta {
+                size: entry.data.len() as u64,
+                mime_type: mime_type(&entry.data),
+            }))
+        })
+    }
+
+    fn cleanup(&self) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            self.entries.lock().unwrap().remove_expired(self.ttl);
+            Ok(())
+        })
+    }
+
+    fn usage(&self) -> Option<StoreUsage> {
+        let entries = self.entries.lock().unwrap();
+        Some(StoreUsage {
+            images: entries.entries.len() as u64,
+            bytes: entries.size,
+        })
+    }
+}
@@ -0,0 +1,75 @@
+use std::{future::Future, pin::Pin, sync::LazyLock};
+
+use crate::{
+    config::{CONFIG, StoreBackend},
+    server::ServerError,
+};
+
+mod disk;
+mod memory;
+mod s3;
+
+pub(crate) type StoreFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, ServerError>> + Send + 'a>>;
+
+pub(crate) static IMAGE_STORE: LazyLock<Box<dyn ImageStore>> =
+    LazyLock::new(|| match CONFIG.server.store.backend {
+        StoreBackend::Memory => Box::new(memory::MemoryStore::new()),
+        StoreBackend::Disk => Box::new(disk::DiskStore::new()),
+        StoreBackend::S3 => Box::new(s3::S3Store::new()),
+    });
+
+#[derive(Debug, Clone)]
+pub(crate) struct ImageMeta {
+    pub(crate) size: u64,
+    pub(crate) mime_type: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StoreUsage {
+    pub(crate) images: u64,
+    pub(crate) bytes: u64,
+}
+
+/// Storage of uploaded and generated images.
+///
+/// Ids are the md5 of the image data, so storing the same data twice is a
+/// no-op and an id always refers to the same bytes.
+pub(crate) trait ImageStore: Send + Sync {
+    fn put<'a>(&'a self, id: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()>;
+
+    /// Returns `None` for unknown or expired ids.
+    fn get<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Vec<u8>>>;
+
+    /// Size and type of an image, without reading all of it.
+    fn head<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<ImageMeta>>;
+
+    /// Removes expired images.
+    fn cleanup(&self) -> StoreFuture<'_, ()>;
+
+    /// Number and total size of the stored images, if the backend tracks them.
+    fn usage(&self) -> Option<StoreUsage>;
+}
+
+/// Error for an image that can never fit into a store of `max_bytes`.
+pub(crate) fn too_large(size: u64, max_bytes: u64) -> ServerError {
+    ServerError::StoreError(format!(
+        "Image of {size} bytes is larger than the store limit of {max_bytes} bytes"
+    ))
+}
+
+pub(crate) fn image_id(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+/// Ids come from user input and end up in paths and object keys.
+pub(crate) fn is_valid_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+pub(crate) fn mime_type(data: &[u8]) -> String {
+    infer::get(data)
+        .map(|kind| kind.mime_type())
+        .unwrap_or("application/octet-stream")
+        .to_string()
+}
@@ -0,0 +1,370 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url, header::CONTENT_TYPE};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{CONFIG, S3Config},
+    server::ServerError,
+};
+
+use super::{ImageMeta, ImageStore, StoreFuture, StoreUsage, is_valid_id, mime_type};
+
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Percent-encodes a path as S3 expects in the canonical request: every byte
+/// except unreserved characters and `/` is encoded, with upper case hex.
+fn uri_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Key for signing requests made on `date` (`YYYYMMDD`).
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{secret_key}").as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+/// Stores images in an S3-compatible bucket, requests are signed with AWS
+/// signature version 4.
+pub(crate) struct S3Store {
+    client: Client,
+    config: S3Config,
+}
+
+impl S3Store {
+    pub(crate) fn new() -> Self {
+        Self {
+            client: Client::new(),
+            config: CONFIG.server.store.s3.clone(),
+        }
+    }
+
+    fn object_url(&self, id: &str) -> Result<Url, ServerError> {
+        let config = &self.config;
+        let mut url = Url::parse(&config.endpoint)
+            .map_err(|err| ServerError::StoreError(format!("Invalid s3 endpoint: {err}")))?;
+        let key = format!("{}{id}", config.prefix);
+        // the path is encoded here, so that it is sent exactly as it is signed
+        if config.path_style {
+            url.set_path(&uri_encode_path(&format!("/{}/{key}", config.bucket)));
+        } else {
+            let host = format!("{}.{}", config.bucket, url.host_str().unwrap_or_default());
+            url.set_host(Some(&host))
+                .map_err(|err| ServerError::StoreError(format!("Invalid s3 endpoint: {err}")))?;
+            url.set_path(&uri_encode_path(&format!("/{key}")));
+        }
+        Ok(url)
+    }
+
+    fn signed_request(
+        &self,
+        method: Method,
+        url: Url,
+        payload_hash: &str,
+    ) -> Result<RequestBuilder, ServerError> {
+        let config = &self.config;
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        // `object_url` already encoded the path with `uri_encode_path`
+        let canonical_uri = url.path();
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{canonical_uri}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let key = signing_key(&config.secret_key, &date, &config.region, "s3");
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            config.access_key
+        );
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization))
+    }
+
+    fn unexpected_status(method: &str, status: StatusCode) -> ServerError {
+        ServerError::StoreError(format!("S3 {method} request failed with status {status}"))
+    }
+}
+
+impl ImageStore for S3Store {
+    fn put<'a>(&'a self, id: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let url = self.object_url(id)?;
+            let payload_hash = sha256_hex(&data);
+            let response = self
+                .signed_request(Method::PUT, url, &payload_hash)?
+                .header(CONTENT_TYPE, mime_type(&data))
+                .body(data)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                return Err(Self::unexpected_status("PUT", response.status()));
+            }
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            if !is_valid_id(id) {
+                return Ok(None);
+            }
+            let url = self.object_url(id)?;
+            let response = self
+                .signed_request(Method::GET, url, EMPTY_PAYLOAD_HASH)?
+                .send()
+                .await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => Ok(None),
+                status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+                status => Err(Self::unexpected_status("GET", status)),
+            }
+        })
+    }
+
+    fn head<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<ImageMeta>> {
+        Box::pin(async move {
+            if !is_valid_id(id) {
+                return Ok(None);
+            }
+            let url = self.object_url(id)?;
+            let response = self
+                .signed_request(Method::HEAD, url, EMPTY_PAYLOAD_HASH)?
+                .send()
+                .await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => Ok(None),
+                status if status.is_success() => {
+                    let headers = response.headers();
+                    let size = headers
+                        .get("content-length")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or_default();
+                    let mime_type = headers
+                        .get(CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or("application/octet-stream")
+                        .to_string();
+                    Ok(Some(ImageMeta { size, mime_type }))
+                }
+                status => Err(Self::unexpected_status("HEAD", status)),
+            }
+        })
+    }
+
+    fn cleanup(&self) -> StoreFuture<'_, ()> {
+        // expiration is handled by the lifecycle rules of the bucket
+        Box::pin(async { Ok(()) })
+    }
+
+    fn usage(&self) -> Option<StoreUsage> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        Router,
+        body::Bytes,
+        extract::{Request, State},
+        http::{HeaderMap, StatusCode as HttpStatus, header::CONTENT_LENGTH},
+        response::{IntoResponse, Response},
+    };
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const ACCESS_KEY: &str = "AKIDEXAMPLE";
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const REGION: &str = "us-east-1";
+
+    type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    }
+
+    /// Recomputes the signature from the request as the stub received it.
+    fn verify_signature(method: &str, path: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+        let payload_hash = header(headers, "x-amz-content-sha256");
+        if payload_hash != sha256_hex(body) {
+            return false;
+        }
+        let amz_date = header(headers, "x-amz-date");
+        let date = &amz_date[..8];
+        let canonical_request = [
+            method,
+            path,
+            "",
+            &format!("host:{}", header(headers, "host")),
+            &format!("x-amz-content-sha256:{payload_hash}"),
+            &format!("x-amz-date:{amz_date}"),
+            "",
+            "host;x-amz-content-sha256;x-amz-date",
+            payload_hash,
+        ]
+        .join("\n");
+        let scope = format!("{date}/{REGION}/s3/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&signing_key(SECRET_KEY, date, REGION, "s3")).unwrap();
+        mac.update(string_to_sign.as_bytes());
+        let expected = format!(
+            "AWS4-HMAC-SHA256 Credential={ACCESS_KEY}/{scope}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            hex(&mac.finalize().into_bytes())
+        );
+        header(headers, "authorization") == expected
+    }
+
+    async fn stub(State(objects): State<Objects>, request: Request) -> Response {
+        let method = request.method().to_string();
+        let path = request.uri().path().to_string();
+        let headers = request.headers().clone();
+        let body = axum::body::to_bytes(request.into_body(), usize::MAX)
+            .await
+            .unwrap_or_else(|_| Bytes::new());
+        if !verify_signature(&method, &path, &headers, &body) {
+            return HttpStatus::FORBIDDEN.into_response();
+        }
+        let mut objects = objects.lock().unwrap();
+        match method.as_str() {
+            "PUT" => {
+                objects.insert(path, body.to_vec());
+                HttpStatus::OK.into_response()
+            }
+            "GET" | "HEAD" => match objects.get(&path) {
+                Some(data) => {
+                    ([(CONTENT_LENGTH, data.len().to_string())], data.clone()).into_response()
+                }
+                None => HttpStatus::NOT_FOUND.into_response(),
+            },
+            _ => HttpStatus::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    async fn start_stub() -> (S3Store, Objects) {
+        let objects = Objects::default();
+        let app = Router::new().fallback(stub).with_state(objects.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let store = S3Store {
+            client: Client::new(),
+            config: S3Config {
+                endpoint: format!("http://{addr}"),
+                bucket: "memes".to_string(),
+                region: REGION.to_string(),
+                access_key: ACCESS_KEY.to_string(),
+                secret_key: SECRET_KEY.to_string(),
+                prefix: "images (new)/".to_string(),
+                path_style: true,
+            },
+        };
+        (store, objects)
+    }
+
+    #[test]
+    fn signing_key_matches_aws_example() {
+        // https://docs.aws.amazon.com/IAM/latest/UserGuide/signing-elements.html
+        let key = signing_key(SECRET_KEY, "20120215", REGION, "iam");
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn encodes_reserved_characters() {
+        assert_eq!(uri_encode_path("/bucket/a-b_c.d~e"), "/bucket/a-b_c.d~e");
+        assert_eq!(
+            uri_encode_path("/bucket/images (new)/a+b=c!"),
+            "/bucket/images%20%28new%29/a%2Bb%3Dc%21"
+        );
+        assert_eq!(uri_encode_path("/表情"), "/%E8%A1%A8%E6%83%85");
+    }
+
+    #[tokio::test]
+    async fn signed_requests_are_accepted() {
+        let (store, objects) = start_stub().await;
+        let id = "0123456789abcdef0123456789abcdef";
+        let data = b"GIF89a image".to_vec();
+
+        store.put(id, data.clone()).await.unwrap();
+        assert!(
+            objects
+                .lock()
+                .unwrap()
+                .contains_key(&format!("/memes/images%20%28new%29/{id}"))
+        );
+        assert_eq!(store.get(id).await.unwrap(), Some(data.clone()));
+        let meta = store.head(id).await.unwrap().unwrap();
+        assert_eq!(meta.size, data.len() as u64);
+
+        let missing = "fedcba9876543210fedcba9876543210";
+        assert_eq!(store.get(missing).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn wrong_secret_is_rejected() {
+        let (mut store, _) = start_stub().await;
+        store.config.secret_key = "wrong".to_string();
+        let result = store
+            .put("0123456789abcdef0123456789abcdef", b"data".to_vec())
+            .await;
+        assert!(matches!(result, Err(ServerError::StoreError(_))));
+    }
+}
@@ -3,13 +3,15 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
-use tokio::task::spawn_blocking;
 
 use meme_generator::{error::Error, tools::image_operations};
 
-use crate::server::{
-    SEMAPHORE, create_temp_file, get_temp_file, handle_error, handle_image_result,
-    handle_server_error,
+use crate::{
+    extract::ApiJson,
+    server::{
+        acquire_permit, handle_error, handle_image_result, handle_server_error, load_image,
+        run_blocking, store_image,
+    },
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,45 +19,44 @@ pub(crate) struct ImageRequest {
     image_id: String,
 }
 
-pub(crate) async fn inspect(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn inspect(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    match spawn_blocking(move || image_operations::inspect(data))
-        .await
-        .unwrap()
-    {
+    let _permit = acquire_permit().await;
+    match run_blocking("inspect", move || image_operations::inspect(data)).await {
         Ok(result) => Json(result).into_response(),
         Err(error) => handle_error(error).into_response(),
     }
 }
 
-pub(crate) async fn flip_horizontal(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn flip_horizontal(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::flip_horizontal(data))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("flip_horizontal", move || {
+        image_operations::flip_horizontal(data)
+    })
+    .await;
     handle_image_result(result).await
 }
 
-pub(crate) async fn flip_vertical(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn flip_vertical(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::flip_vertical(data))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("flip_vertical", move || {
+        image_operations::flip_vertical(data)
+    })
+    .await;
     handle_image_result(result).await
 }
 
@@ -65,16 +66,17 @@ pub(crate) struct RotateRequest {
     degrees: Option<f32>,
 }
 
-pub(crate) async fn rotate(Json(payload): Json<RotateRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn rotate(ApiJson(payload): ApiJson<RotateRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::rotate(data, payload.degrees))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("rotate", move || {
+        image_operations::rotate(data, payload.degrees)
+    })
+    .await;
     handle_image_result(result).await
 }
 
@@ -85,17 +87,17 @@ pub(crate) struct ResizeRequest {
     height: Option<i32>,
 }
 
-pub(crate) async fn resize(Json(payload): Json<ResizeRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn resize(ApiJson(payload): ApiJson<ResizeRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result =
-        spawn_blocking(move || image_operations::resize(data, payload.width, payload.height))
-            .await
-            .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("resize", move || {
+        image_operations::resize(data, payload.width, payload.height)
+    })
+    .await;
     handle_image_result(result).await
 }
 
@@ -108,14 +110,14 @@ pub(crate) struct CropRequest {
     bottom: Option<i32>,
 }
 
-pub(crate) async fn crop(Json(payload): Json<CropRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn crop(ApiJson(payload): ApiJson<CropRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || {
+    let _permit = acquire_permit().await;
+    let result = run_blocking("crop", move || {
         image_operations::crop(
             data,
             payload.left,
@@ -124,34 +126,29 @@ pub(crate) async fn crop(Json(payload): Json<CropRequest>) -> Response {
             payload.bottom,
         )
     })
-    .await
-    .unwrap();
+    .await;
     handle_image_result(result).await
 }
 
-pub(crate) async fn grayscale(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn grayscale(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::grayscale(data))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("grayscale", move || image_operations::grayscale(data)).await;
     handle_image_result(result).await
 }
 
-pub(crate) async fn invert(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn invert(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::invert(data))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("invert", move || image_operations::invert(data)).await;
     handle_image_result(result).await
 }
 
@@ -160,35 +157,37 @@ pub(crate) struct ImagesRequest {
     image_ids: Vec<String>,
 }
 
-pub(crate) async fn merge_horizontal(Json(payload): Json<ImagesRequest>) -> Response {
+pub(crate) async fn merge_horizontal(ApiJson(payload): ApiJson<ImagesRequest>) -> Response {
     let mut images = vec![];
     for image_id in payload.image_ids {
-        match get_temp_file(&image_id).await {
+        match load_image(&image_id).await {
             Ok(data) => images.push(data),
             Err(err) => return handle_server_error(err).into_response(),
         };
     }
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::merge_horizontal(images))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("merge_horizontal", move || {
+        image_operations::merge_horizontal(images)
+    })
+    .await;
     handle_image_result(result).await
 }
 
-pub(crate) async fn merge_vertical(Json(payload): Json<ImagesRequest>) -> Response {
+pub(crate) async fn merge_vertical(ApiJson(payload): ApiJson<ImagesRequest>) -> Response {
     let mut images = vec![];
     for image_id in payload.image_ids {
-        match get_temp_file(&image_id).await {
+        match load_image(&image_id).await {
             Ok(data) => images.push(data),
             Err(err) => return handle_server_error(err).into_response(),
         };
     }
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::merge_vertical(images))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("merge_vertical", move || {
+        image_operations::merge_vertical(images)
+    })
+    .await;
     handle_image_result(result).await
 }
 
@@ -202,7 +201,7 @@ pub(crate) async fn handle_images_result(result: Result<Vec<Vec<u8>>, Error>) ->
     match result {
         Ok(data) => {
             for d in data {
-                match create_temp_file(d).await {
+                match store_image(d).await {
                     Ok(id) => image_ids.push(id),
                     Err(err) => return handle_server_error(err).into_response(),
                 };
@@ -214,16 +213,14 @@ pub(crate) async fn handle_images_result(result: Result<Vec<Vec<u8>>, Error>) ->
     Json(response).into_response()
 }
 
-pub(crate) async fn gif_split(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn gif_split(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::gif_split(data))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("gif_split", move || image_operations::gif_split(data)).await;
     handle_images_result(result).await
 }
 
@@ -233,32 +230,31 @@ pub(crate) struct GifMergeRequest {
     duration: Option<f32>,
 }
 
-pub(crate) async fn gif_merge(Json(payload): Json<GifMergeRequest>) -> Response {
+pub(crate) async fn gif_merge(ApiJson(payload): ApiJson<GifMergeRequest>) -> Response {
     let mut images = vec![];
     for image in payload.image_ids {
-        match get_temp_file(&image).await {
+        match load_image(&image).await {
             Ok(data) => images.push(data),
             Err(err) => return handle_server_error(err).into_response(),
         };
     }
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::gif_merge(images, payload.duration))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("gif_merge", move || {
+        image_operations::gif_merge(images, payload.duration)
+    })
+    .await;
     handle_image_result(result).await
 }
 
-pub(crate) async fn gif_reverse(Json(payload): Json<ImageRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn gif_reverse(ApiJson(payload): ApiJson<ImageRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || image_operations::gif_reverse(data))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("gif_reverse", move || image_operations::gif_reverse(data)).await;
     handle_image_result(result).await
 }
 
@@ -268,16 +264,16 @@ pub(crate) struct GifDurationRequest {
     duration: f32,
 }
 
-pub(crate) async fn gif_change_duration(Json(payload): Json<GifDurationRequest>) -> Response {
-    let data = match get_temp_file(&payload.image_id).await {
+pub(crate) async fn gif_change_duration(ApiJson(payload): ApiJson<GifDurationRequest>) -> Response {
+    let data = match load_image(&payload.image_id).await {
         Ok(data) => data,
         Err(err) => return handle_server_error(err).into_response(),
     };
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result =
-        spawn_blocking(move || image_operations::gif_change_duration(data, payload.duration))
-            .await
-            .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("gif_change_duration", move || {
+        image_operations::gif_change_duration(data, payload.duration)
+    })
+    .await;
     handle_image_result(result).await
 }
@@ -1,32 +1,95 @@
-use axum::{extract::Json, response::IntoResponse};
-use tokio::task::spawn_blocking;
+use axum::{
+    Json,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
 
-use meme_generator::tools::{
-    RenderMemeListParams, RenderMemeStatisticsParams, render_meme_list, render_meme_statistics,
+use meme_generator::{
+    bbcode::{BBCodeToken, parse_bbcode_strict},
+    tools::{
+        RenderMemeListParams, RenderMemeStatisticsParams, render_meme_list, render_meme_statistics,
+    },
 };
 
-use crate::server::{SEMAPHORE, handle_image_result};
+use crate::{
+    errors::BBCODE_ERROR,
+    extract::ApiJson,
+    server::{acquire_permit, handle_image_result, run_blocking},
+};
 
 pub(crate) mod image_operations;
 
-pub(crate) async fn render_list(Json(payload): Json<RenderMemeListParams>) -> impl IntoResponse {
+pub(crate) async fn render_list(
+    ApiJson(payload): ApiJson<RenderMemeListParams>,
+) -> impl IntoResponse {
     let payload = payload.clone();
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || render_meme_list(payload))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("render_list", move || render_meme_list(payload)).await;
     handle_image_result(result).await
 }
 
 pub(crate) async fn render_statistics(
-    Json(payload): Json<RenderMemeStatisticsParams>,
+    ApiJson(payload): ApiJson<RenderMemeStatisticsParams>,
 ) -> impl IntoResponse {
     let payload = payload.clone();
 
-    let _permit = SEMAPHORE.acquire().await.unwrap();
-    let result = spawn_blocking(move || render_meme_statistics(payload))
-        .await
-        .unwrap();
+    let _permit = acquire_permit().await;
+    let result = run_blocking("render_statistics", move || render_meme_statistics(payload)).await;
     handle_image_result(result).await
 }
+
+#[derive(Deserialize)]
+pub(crate) struct BBCodeRequest {
+    text: String,
+}
+
+fn token_json(token: BBCodeToken) -> Value {
+    match token {
+        BBCodeToken::Text(text) => json!({ "type": "text", "text": text }),
+        BBCodeToken::OpenTag(tag) => {
+            json!({ "type": "open_tag", "tag": tag.name(), "value": tag.value() })
+        }
+        BBCodeToken::CloseTag(name) => json!({ "type": "close_tag", "tag": name }),
+    }
+}
+
+/// Checks BBCode text before it is used to generate a meme.
+pub(crate) async fn parse_bbcode(ApiJson(payload): ApiJson<BBCodeRequest>) -> Response {
+    match parse_bbcode_strict(&payload.text) {
+        Ok(tokens) => {
+            let tokens = tokens.into_iter().map(token_json).collect::<Vec<_>>();
+            Json(json!({ "tokens": tokens })).into_response()
+        }
+        Err(error) => BBCODE_ERROR
+            .error(
+                error.to_string(),
+                json!({ "position": error.position, "error": error.to_string() }),
+            )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use meme_generator::bbcode::parse_bbcode;
+
+    use super::*;
+
+    #[test]
+    fn tokens_keep_tag_values() {
+        let tokens = parse_bbcode("[color=#ff0000]a[/color]")
+            .into_iter()
+            .map(token_json)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                json!({ "type": "open_tag", "tag": "color", "value": "#ff0000" }),
+                json!({ "type": "text", "text": "a" }),
+                json!({ "type": "close_tag", "tag": "color" }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! BBCode 解析
+//!
+//! 支持的标签：
+//! - `[b]` 粗体、`[i]` 斜体、`[u]` 下划线、`[del]` 删除线
+//! - `[color=red]` 文字颜色、`[stroke=#ffffff]` 描边颜色
+//! - `[size=1.5]` 字号，为当前字号的倍数，范围为 0.1~10、`[font=FZXS14]` 字体
+//! - `[bg=yellow]` 背景高亮、`[shadow=black]` 阴影（`[shadow]` 为黑色阴影）
+//! - `[opacity=0.5]` 不透明度，范围为 0~1
+//!
+//! 颜色可以是 `#rrggbb`、`#rrggbbaa` 或颜色名。
+//! `\[`、`\]`、`\\` 分别表示字面的 `[`、`]`、`\`
+
+use std::{error, fmt};
+
+use skia_safe::scalar;
+
+use crate::tools::try_color_from_str;
+
+/// 开始标签，颜色为解析时检查过的原始写法，如 `red`、`#ff0000`
+#[derive(Debug, Clone, PartialEq)]
+pub enum BBCodeTag {
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Color(String),
+    Stroke(String),
+    /// 当前字号的倍数，嵌套时相乘
+    Size(scalar),
+    Font(String),
+    Background(String),
+    Shadow(String),
+    /// 嵌套时相乘
+    Opacity(scalar),
+}
+
+impl BBCodeTag {
+    /// 标签名，即结束标签 `[/name]` 中的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            BBCodeTag::Bold => "b",
+            BBCodeTag::Italic => "i",
+            BBCodeTag::Underline => "u",
+            BBCodeTag::Strikethrough => "del",
+            BBCodeTag::Color(_) => "color",
+            BBCodeTag::Stroke(_) => "stroke",
+            BBCodeTag::Size(_) => "size",
+            BBCodeTag::Font(_) => "font",
+            BBCodeTag::Background(_) => "bg",
+            BBCodeTag::Shadow(_) => "shadow",
+            BBCodeTag::Opacity(_) => "opacity",
+        }
+    }
+
+    /// 标签的值，如 `[color=red]` 中的 `red`，没有值的标签返回 `None`
+    pub fn value(&self) -> Option<String> {
+        match self {
+            BBCodeTag::Bold
+            | BBCodeTag::Italic
+            | BBCodeTag::Underline
+            | BBCodeTag::Strikethrough => None,
+            BBCodeTag::Color(value)
+            | BBCodeTag::Stroke(value)
+            | BBCodeTag::Font(value)
+            | BBCodeTag::Background(value)
+            | BBCodeTag::Shadow(value) => Some(value.clone()),
+            BBCodeTag::Size(value) | BBCodeTag::Opacity(value) => Some(value.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BBCodeToken {
+    Text(String),
+    OpenTag(BBCodeTag),
+    /// 结束标签，值为标签名
+    CloseTag(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BBCodeErrorKind {
+    /// 未知的标签
+    UnknownTag(String),
+    /// 标签的值无效，如 `[size=abc]`
+    InvalidValue { tag: String, value: String },
+    /// 没有对应开始标签的结束标签
+    UnexpectedCloseTag(String),
+    /// 没有结束的标签
+    UnclosedTag(String),
+    /// `[` 之后没有 `]`，字面的 `[` 需写作 `\[`
+    UnterminatedTag,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BBCodeError {
+    /// 出错的标签在文本中的位置，按字符计
+    pub position: usize,
+    pub kind: BBCodeErrorKind,
+}
+
+impl fmt::Display for BBCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = self.position;
+        match &self.kind {
+            BBCodeErrorKind::UnknownTag(tag) => {
+                write!(f, "Unknown tag `[{tag}]` at position {position}")
+            }
+            BBCodeErrorKind::InvalidValue { tag, value } => write!(
+                f,
+                "Invalid value `{value}` for tag `{tag}` at position {position}"
+            ),
+            BBCodeErrorKind::UnexpectedCloseTag(tag) => write!(
+                f,
+                "Close tag `[/{tag}]` at position {position} has no matching open tag"
+            ),
+            BBCodeErrorKind::UnclosedTag(tag) => {
+                write!(f, "Tag `[{tag}]` at position {position} is not closed")
+            }
+            BBCodeErrorKind::UnterminatedTag => {
+                write!(f, "Missing `]` for `[` at position {position}")
+            }
+        }
+    }
+}
+
+impl error::Error for BBCodeError {}
+
+enum TagToken {
+    Open(BBCodeTag),
+    Close(&'static str),
+}
+
+const TAG_NAMES: [&str; 11] = [
+    "b", "i", "u", "del", "color", "stroke", "size", "font", "bg", "shadow", "opacity",
+];
+
+fn parse_tag(content: &str) -> Result<TagToken, BBCodeErrorKind> {
+    if let Some(name) = content.strip_prefix('/') {
+        return match TAG_NAMES.iter().find(|tag| **tag == name) {
+            Some(tag) => Ok(TagToken::Close(tag)),
+            None => Err(BBCodeErrorKind::UnknownTag(content.to_string())),
+        };
+    }
+
+    let (name, value) = match content.split_once('=') {
+        Some((name, value)) => (name, Some(value.trim())),
+        None => (content, None),
+    };
+    let invalid = || BBCodeErrorKind::InvalidValue {
+        tag: name.to_string(),
+        value: value.unwrap_or_default().to_string(),
+    };
+    let color = || {
+        value
+            .filter(|value| try_color_from_str(value).is_some())
+            .map(str::to_string)
+            .ok_or_else(invalid)
+    };
+    let number = |range: (scalar, scalar)| {
+        value
+            .and_then(|value| value.parse::<scalar>().ok())
+            .filter(|number| (range.0..=range.1).contains(number))
+            .ok_or_else(invalid)
+    };
+
+    let tag = match (name, value) {
+        ("b", None) => BBCodeTag::Bold,
+        ("i", None) => BBCodeTag::Italic,
+        ("u", None) => BBCodeTag::Underline,
+        ("del", None) => BBCodeTag::Strikethrough,
+        ("color", _) => BBCodeTag::Color(color()?),
+        ("stroke", _) => BBCodeTag::Stroke(color()?),
+        ("size", _) => BBCodeTag::Size(number((0.1, 10.0))?),
+        ("font", Some(font)) if !font.is_empty() => BBCodeTag::Font(font.to_string()),
+        ("font", _) => return Err(invalid()),
+        ("bg", _) => BBCodeTag::Background(color()?),
+        ("shadow", None) => BBCodeTag::Shadow("black".to_string()),
+        ("shadow", _) => BBCodeTag::Shadow(color()?),
+        ("opacity", _) => BBCodeTag::Opacity(number((0.0, 1.0))?),
+        _ if TAG_NAMES.contains(&name) => return Err(invalid()),
+        _ => return Err(BBCodeErrorKind::UnknownTag(content.to_string())),
+    };
+    Ok(TagToken::Open(tag))
+}
+
+fn parse(input: &str, strict: bool) -> Result<Vec<BBCodeToken>, BBCodeError> {
+    let error = |position: usize, kind: BBCodeErrorKind| BBCodeError {
+        position: input[..position].chars().count(),
+        kind,
+    };
+
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    // 未结束的标签名及其位置
+    let mut open_tags: Vec<(&str, usize)> = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, next @ ('[' | ']' | '\\'))) => {
+                    text.push(next);
+                    chars.next();
+                }
+                _ => text.push(c),
+            },
+            '[' => {
+                let rest = &input[position + 1..];
+                let end = match rest.find(']') {
+                    Some(end) if !rest[..end].contains('[') => end,
+                    _ => {
+                        if strict {
+                            return Err(error(position, BBCodeErrorKind::UnterminatedTag));
+                        }
+                        text.push(c);
+                        continue;
+                    }
+                };
+                let tag_end = position + 1 + end;
+                while chars.next_if(|(index, _)| *index <= tag_end).is_some() {}
+
+                let token = match parse_tag(&rest[..end]) {
+                    Ok(TagToken::Open(tag)) => {
+                        open_tags.push((tag.name(), position));
+                        BBCodeToken::OpenTag(tag)
+                    }
+                    Ok(TagToken::Close(name)) => {
+                        match open_tags.iter().rposition(|(open, _)| *open == name) {
+                            Some(index) => {
+                                open_tags.remove(index);
+                                BBCodeToken::CloseTag(name.to_string())
+                            }
+                            None if strict => {
+                                let kind = BBCodeErrorKind::UnexpectedCloseTag(name.to_string());
+                                return Err(error(position, kind));
+                            }
+                            None => continue,
+                        }
+                    }
+                    Err(kind) if strict => return Err(error(position, kind)),
+                    // 宽松模式下忽略无效的标签
+                    Err(_) => continue,
+                };
+                if !text.is_empty() {
+                    tokens.push(BBCodeToken::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(token);
+            }
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(BBCodeToken::Text(text));
+    }
+    if strict && let Some((name, position)) = open_tags.first() {
+        let kind = BBCodeErrorKind::UnclosedTag(name.to_string());
+        return Err(error(*position, kind));
+    }
+    Ok(tokens)
+}
+
+/// 解析 BBCode，忽略未知或无效的标签以及多余的结束标签
+pub fn parse_bbcode(input: &str) -> Vec<BBCodeToken> {
+    parse(input, false).unwrap_or_default()
+}
+
+/// 解析 BBCode，遇到未知或无效的标签、标签不配对时返回错误
+///
+/// 可用于在制作表情前检查用户输入的文字
+pub fn parse_bbcode_strict(input: &str) -> Result<Vec<BBCodeToken>, BBCodeError> {
+    parse(input, true)
+}
+
+/// 去除 BBCode 标签，返回纯文本
+pub fn strip_bbcode(input: &str) -> String {
+    parse_bbcode(input)
+        .into_iter()
+        .filter_map(|token| match token {
+            BBCodeToken::Text(text) => Some(text),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> BBCodeToken {
+        BBCodeToken::Text(text.to_string())
+    }
+
+    fn close(name: &str) -> BBCodeToken {
+        BBCodeToken::CloseTag(name.to_string())
+    }
+
+    #[test]
+    fn parse_tags() {
+        assert_eq!(
+            parse_bbcode("a[b]b[color=#ff0000]c[/color][/b]"),
+            [
+                text("a"),
+                BBCodeToken::OpenTag(BBCodeTag::Bold),
+                text("b"),
+                BBCodeToken::OpenTag(BBCodeTag::Color("#ff0000".to_string())),
+                text("c"),
+                close("color"),
+                close("b"),
+            ]
+        );
+        assert_eq!(
+            parse_bbcode("[size=1.5][font= FZXS14 ][shadow][opacity=0.5]x"),
+            [
+                BBCodeToken::OpenTag(BBCodeTag::Size(1.5)),
+                BBCodeToken::OpenTag(BBCodeTag::Font("FZXS14".to_string())),
+                BBCodeToken::OpenTag(BBCodeTag::Shadow("black".to_string())),
+                BBCodeToken::OpenTag(BBCodeTag::Opacity(0.5)),
+                text("x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_values() {
+        assert_eq!(BBCodeTag::Bold.value(), None);
+        assert_eq!(
+            BBCodeTag::Stroke("white".to_string()).value().as_deref(),
+            Some("white")
+        );
+        assert_eq!(BBCodeTag::Size(0.5).value().as_deref(), Some("0.5"));
+        assert_eq!(BBCodeTag::Background("red".to_string()).name(), "bg");
+    }
+
+    #[test]
+    fn parse_escapes() {
+        assert_eq!(
+            parse_bbcode(r"\[b\]\\[b]"),
+            [text(r"[b]\"), BBCodeToken::OpenTag(BBCodeTag::Bold)]
+        );
+        assert_eq!(parse_bbcode(r"a\b"), [text(r"a\b")]);
+        assert_eq!(strip_bbcode(r"[u]\[x\][/u]"), "[x]");
+    }
+
+    #[test]
+    fn lenient_parse_skips_invalid_tags() {
+        assert_eq!(
+            parse_bbcode("[x]a[/b][size=100]b[color=nope]c[/i]"),
+            [text("abc")]
+        );
+        assert_eq!(parse_bbcode("a[b"), [text("a[b")]);
+        assert_eq!(
+            parse_bbcode("[i]a"),
+            [BBCodeToken::OpenTag(BBCodeTag::Italic), text("a")]
+        );
+    }
+
+    #[test]
+    fn strict_parse_reports_errors() {
+        let error = |input: &str| parse_bbcode_strict(input).unwrap_err();
+        assert_eq!(
+            error("字[x]"),
+            BBCodeError {
+                position: 1,
+                kind: BBCodeErrorKind::UnknownTag("x".to_string()),
+            }
+        );
+        assert_eq!(
+            error("[size=abc]").kind,
+            BBCodeErrorKind::InvalidValue {
+                tag: "size".to_string(),
+                value: "abc".to_string(),
+            }
+        );
+        assert_eq!(
+            error("[size=20]").kind,
+            BBCodeErrorKind::InvalidValue {
+                tag: "size".to_string(),
+                value: "20".to_string(),
+            }
+        );
+        assert_eq!(
+            error("[color=nope]").kind,
+            BBCodeErrorKind::InvalidValue {
+                tag: "color".to_string(),
+                value: "nope".to_string(),
+            }
+        );
+        assert_eq!(
+            error("a[/b]"),
+            BBCodeError {
+                position: 1,
+                kind: BBCodeErrorKind::UnexpectedCloseTag("b".to_string()),
+            }
+        );
+        assert_eq!(
+            error("[b][i]a[/i]"),
+            BBCodeError {
+                position: 0,
+                kind: BBCodeErrorKind::UnclosedTag("b".to_string()),
+            }
+        );
+        assert_eq!(error("ab[c").kind, BBCodeErrorKind::UnterminatedTag);
+        assert_eq!(
+            error("[b]").to_string(),
+            "Tag `[b]` at position 0 is not closed"
+        );
+        assert!(parse_bbcode_strict("[b][i]a[/i][/b]").is_ok());
+    }
+}
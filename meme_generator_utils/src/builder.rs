@@ -10,7 +10,11 @@ use meme_generator_core::{
     meme::{self, Meme, MemeInfo, MemeOption, MemeParams, MemeShortcut, OptionValue},
 };
 
-use crate::{decoder::CodecExt, encoder::encode_png, tools::GRID_PATTERN_IMAGE};
+use crate::{
+    decoder::{CodecExt, FrameCache, FrameCacheStats, check_input_bytes},
+    encoder::encode_png,
+    tools::GRID_PATTERN_IMAGE,
+};
 
 pub use meme_options_derive::MemeOptions;
 
@@ -60,23 +64,33 @@ pub mod shortcut_setters {
 pub struct InputImage<'a> {
     pub name: String,
     pub image: Image,
-    pub(crate) codec: Codec<'a>,
+    pub(crate) frames: FrameCache<'a>,
 }
 
 impl InputImage<'static> {
     pub fn from(input: meme::Image) -> Result<InputImage<'static>, Error> {
+        check_input_bytes(input.data.len())?;
         let data = Data::new_copy(&input.data);
-        let mut codec = Codec::from_data(data)
+        let codec = Codec::from_data(data)
             .ok_or(Error::ImageDecodeError("Skia decode error".to_string()))?;
-        let image = codec.first_frame()?;
+        let mut frames = FrameCache::new(codec)?;
+        frames.attach_shared(&input.data);
+        let image = frames.first_frame()?;
         Ok(InputImage {
             name: input.name,
             image,
-            codec,
+            frames,
         })
     }
 }
 
+impl InputImage<'_> {
+    /// 解码帧缓存的统计信息
+    pub fn frame_stats(&self) -> FrameCacheStats {
+        self.frames.stats()
+    }
+}
+
 type MemeFunction<T> = fn(Vec<InputImage>, Vec<String>, T) -> Result<Vec<u8>, Error>;
 
 pub struct MemeBuilder<T>
@@ -73,6 +73,27 @@ fn draw_text(
     text2image.draw_on_canvas(canvas, origin);
 }
 
+/// 在区域内排版，放不下时返回 `None`，否则返回居中后的绘制位置
+///
+/// 竖排时按区域高度换列
+fn layout_in_rect(text2image: &mut Text2Image, rect: &Rect) -> Option<Point> {
+    if text2image.is_vertical() {
+        text2image.layout(rect.height());
+        if text2image.width() > rect.width() || text2image.height() > rect.height() {
+            return None;
+        }
+        let left = rect.left() + (rect.width() - text2image.width()) / 2.0;
+        Some(Point::new(left, rect.top()))
+    } else {
+        text2image.layout(rect.width());
+        if text2image.height() > rect.height() {
+            return None;
+        }
+        let top = rect.top() + (rect.height() - text2image.height()) / 2.0;
+        Some(Point::new(rect.left(), top))
+    }
+}
+
 fn draw_text_area(
     canvas: &Canvas,
     rect: impl Into<Rect>,
@@ -88,12 +109,8 @@ fn draw_text_area(
     } else {
         Text2Image::from_text(text.clone(), font_size, text_params)
     };
-    text2image.layout(rect.width());
-    if text2image.height() > rect.height() {
-        return Err(Error::TextOverLength(text));
-    }
-    let top = rect.top() + (rect.height() - text2image.height()) / 2.0;
-    text2image.draw_on_canvas(canvas, (rect.left(), top));
+    let origin = layout_in_rect(&mut text2image, &rect).ok_or(Error::TextOverLength(text))?;
+    text2image.draw_on_canvas(canvas, origin);
     Ok(())
 }
 
@@ -116,10 +133,8 @@ fn draw_text_area_auto_font_size(
         } else {
             Text2Image::from_text(&text, font_size, text_params.clone())
         };
-        text2image.layout(rect.width());
-        if text2image.height() <= rect.height() {
-            let top = rect.top() + (rect.height() - text2image.height()) / 2.0;
-            text2image.draw_on_canvas(canvas, (rect.left(), top));
+        if let Some(origin) = layout_in_rect(&mut text2image, &rect) {
+            text2image.draw_on_canvas(canvas, origin);
             return Ok(());
         }
         if let Some(stroke_paint) = &mut text_params.stroke_paint {
@@ -1,14 +1,15 @@
 use std::{path::PathBuf, sync::LazyLock};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 use meme_generator_core::config::{MEME_HOME, read_config_file};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub api: ApiConfig,
+    pub decoder: DecoderConfig,
     pub encoder: EncoderConfig,
     pub font: FontConfig,
 }
@@ -17,13 +18,14 @@ impl Default for Config {
     fn default() -> Self {
         Config {
             api: ApiConfig::default(),
+            decoder: DecoderConfig::default(),
             encoder: EncoderConfig::default(),
             font: FontConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ApiConfig {
     pub baidu_trans_appid: Option<String>,
@@ -39,21 +41,62 @@ impl Default for ApiConfig {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecoderConfig {
+    pub max_image_size: u32,
+    pub frame_cache_max_bytes: usize,
+    pub max_input_bytes: usize,
+    pub max_pixel_area: u64,
+    pub max_frames: u32,
+    pub max_decoded_bytes: usize,
+    pub max_decode_millis: u64,
+    pub limit_policy: LimitPolicy,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        DecoderConfig {
+            max_image_size: 4096,
+            frame_cache_max_bytes: 256 * 1024 * 1024,
+            max_input_bytes: 20 * 1024 * 1024,
+            max_pixel_area: 8192 * 8192,
+            max_frames: 500,
+            max_decoded_bytes: 1024 * 1024 * 1024,
+            max_decode_millis: 30_000,
+            limit_policy: LimitPolicy::Downscale,
+        }
+    }
+}
+
+/// 输入图片超出限制时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPolicy {
+    /// 返回 `Error::ImageLimitExceeded`
+    Reject,
+
+    /// 缩小图片、抽取部分帧
+    Downscale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct EncoderConfig {
     pub gif_max_frames: u16,
+    pub render_threads: usize,
 }
 
 impl Default for EncoderConfig {
     fn default() -> Self {
         EncoderConfig {
             gif_max_frames: 200,
+            render_threads: 0,
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FontConfig {
     pub use_local_fonts: bool,
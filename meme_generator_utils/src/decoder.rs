@@ -1,12 +1,36 @@
-use skia_safe::{AlphaType, Codec, ColorType, Image, ImageInfo, codec};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use skia_safe::{
+    AlphaType, Codec, ColorType, Data, EncodedImageFormat, ISize, Image, ImageInfo,
+    codec::{self, codec_animation::DisposalMethod},
+    images,
+};
+use tracing::debug;
 
 use meme_generator_core::error::Error;
 
+use crate::{
+    config::{CONFIG, LimitPolicy},
+    image::ImageExt,
+};
+
 pub trait CodecExt {
     fn is_multi_frame(&mut self) -> bool;
 
     fn get_average_duration(&mut self) -> Result<f32, Error>;
 
+    /// 获取每一帧的时长，单位为秒
+    fn get_durations(&mut self) -> Result<Vec<f32>, Error>;
+
     fn first_frame(&mut self) -> Result<Image, Error>;
 
     fn get_frame(&mut self, index: usize) -> Result<Image, Error>;
@@ -29,6 +53,25 @@ impl<'a> CodecExt for Codec<'a> {
         Ok((total_duration / count as f32).max(0.02))
     }
 
+    fn get_durations(&mut self) -> Result<Vec<f32>, Error> {
+        let count = self.get_frame_count();
+        let average_duration = self.get_average_duration()?;
+        let mut durations = Vec::with_capacity(count);
+        for i in 0..count {
+            let frame_info = self
+                .get_frame_info(i)
+                .ok_or(Error::ImageDecodeError("Skia decode error".to_string()))?;
+            // 时长过短的帧在大多数播放器中不会按原时长显示，使用平均时长代替
+            let duration = frame_info.duration as f32 / 1000.0;
+            durations.push(if duration < 0.02 {
+                average_duration
+            } else {
+                duration
+            });
+        }
+        Ok(durations)
+    }
+
     fn first_frame(&mut self) -> Result<Image, Error> {
         self.get_frame(0)
     }
@@ -48,3 +91,555 @@ impl<'a> CodecExt for Codec<'a> {
             .map_err(|err| Error::ImageDecodeError(format!("Skia decode error: {err:?}")))
     }
 }
+
+static TOTAL_STATS: Mutex<FrameCacheStats> = Mutex::new(FrameCacheStats {
+    decoded: 0,
+    hits: 0,
+    uncached: 0,
+    bytes: 0,
+    downscaled: 0,
+    decimated: 0,
+});
+
+/// 当前所有帧缓存占用的字节数
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// 帧缓存的统计信息
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FrameCacheStats {
+    /// 实际解码的帧数
+    pub decoded: usize,
+
+    /// 从缓存中取得帧的次数
+    pub hits: usize,
+
+    /// 超出内存上限未能缓存的帧数
+    pub uncached: usize,
+
+    /// 缓存的帧占用的字节数，累计统计中为当前所有帧缓存占用的字节数
+    pub bytes: usize,
+
+    /// 被缩小的图片数
+    pub downscaled: usize,
+
+    /// 因帧数超出限制被丢弃的帧数
+    pub decimated: usize,
+}
+
+impl FrameCacheStats {
+    fn merge(&mut self, other: &FrameCacheStats) {
+        self.decoded += other.decoded;
+        self.hits += other.hits;
+        self.uncached += other.uncached;
+        self.downscaled += other.downscaled;
+        self.decimated += other.decimated;
+    }
+}
+
+/// 所有已释放的帧缓存的累计统计信息，`bytes` 为当前所有帧缓存占用的字节数
+pub fn total_frame_cache_stats() -> FrameCacheStats {
+    FrameCacheStats {
+        bytes: LIVE_BYTES.load(Ordering::Relaxed),
+        ..*TOTAL_STATS.lock().unwrap()
+    }
+}
+
+/// 检查输入图片的大小是否超出 `decoder.max_input_bytes`
+pub fn check_input_bytes(size: usize) -> Result<(), Error> {
+    let max_input_bytes = CONFIG.decoder.max_input_bytes;
+    if max_input_bytes > 0 && size > max_input_bytes {
+        return Err(Error::ImageLimitExceeded(
+            "input_bytes".to_string(),
+            max_input_bytes as u64,
+            size as u64,
+        ));
+    }
+    Ok(())
+}
+
+/// 一张输入图片的共享帧，与同一批次的其他图片共用字节预算
+struct SharedFrameList {
+    frames: Mutex<Vec<Option<Image>>>,
+    bytes: Arc<AtomicUsize>,
+    max_bytes: usize,
+}
+
+impl SharedFrameList {
+    fn get(&self, index: usize) -> Option<Image> {
+        self.frames.lock().unwrap().get(index).cloned().flatten()
+    }
+
+    /// 共享一帧，超出 `decoder.frame_cache_max_bytes` 时不共享
+    fn insert(&self, index: usize, image: &Image, size: usize) {
+        let mut frames = self.frames.lock().unwrap();
+        let Some(frame @ None) = frames.get_mut(index) else {
+            return;
+        };
+        let reserved = self
+            .bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bytes| {
+                (bytes + size <= self.max_bytes).then_some(bytes + size)
+            })
+            .is_ok();
+        if reserved {
+            *frame = Some(image.clone());
+        }
+    }
+}
+
+/// 在多次制作表情之间共享的解码结果，相同的输入图片只解码一次
+///
+/// 所有共享帧的总大小不超过 `decoder.frame_cache_max_bytes`，超出后新解码的帧不再共享
+#[derive(Clone)]
+pub struct SharedFrames {
+    inputs: Arc<Mutex<HashMap<[u8; 16], Arc<SharedFrameList>>>>,
+    bytes: Arc<AtomicUsize>,
+    max_bytes: usize,
+}
+
+impl Default for SharedFrames {
+    fn default() -> Self {
+        Self {
+            inputs: Arc::default(),
+            bytes: Arc::default(),
+            max_bytes: CONFIG.decoder.frame_cache_max_bytes,
+        }
+    }
+}
+
+impl SharedFrames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 共享帧的总字节数
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    fn get(&self, data: &[u8], frame_count: usize) -> Arc<SharedFrameList> {
+        let key = md5::compute(data).0;
+        self.inputs
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| {
+                Arc::new(SharedFrameList {
+                    frames: Mutex::new(vec![None; frame_count]),
+                    bytes: self.bytes.clone(),
+                    max_bytes: self.max_bytes,
+                })
+            })
+            .clone()
+    }
+}
+
+thread_local! {
+    static SHARED_FRAMES: RefCell<Option<SharedFrames>> = const { RefCell::new(None) };
+}
+
+struct SharedFramesGuard {
+    shared: Option<SharedFrames>,
+}
+
+impl Drop for SharedFramesGuard {
+    fn drop(&mut self) {
+        SHARED_FRAMES.set(self.shared.take());
+    }
+}
+
+/// 在执行函数期间，当前线程中创建的 [`InputImage`](crate::builder::InputImage)
+/// 会复用 `shared` 中已解码的帧
+pub fn with_shared_frames<T>(shared: &SharedFrames, func: impl FnOnce() -> T) -> T {
+    let _guard = SharedFramesGuard {
+        shared: SHARED_FRAMES.replace(Some(shared.clone())),
+    };
+    func()
+}
+
+/// 解码帧缓存，保证每一帧只解码一次
+///
+/// 图片边长超过 `decoder.max_image_size`，或全部帧解码后的大小超过
+/// `decoder.frame_cache_max_bytes` 时，会将解码后的帧等比缩小
+///
+/// 像素数、帧数或解码后的总大小超出 `decoder` 中的限制时，
+/// 按照 `decoder.limit_policy` 返回错误，或缩小图片、抽取部分帧
+///
+/// 编解码器支持时（如 JPEG、WebP）直接按缩小后的尺寸解码；
+/// 不支持时按原尺寸解码到一块复用的缓冲区再缩小，
+/// 这块缓冲区本身超出像素数或解码大小限制时总是返回错误
+pub struct FrameCache<'a> {
+    codec: Codec<'a>,
+    frames: Vec<Option<Image>>,
+    /// 每一帧对应的原始帧序号
+    frame_indexes: Vec<usize>,
+    /// 抽帧后每一帧的时长，未抽帧时为 `None`
+    durations: Option<Vec<f32>>,
+    /// 与其他表情共享的解码结果
+    shared: Option<Arc<SharedFrameList>>,
+    /// 上一次解码的原始帧序号及其像素，解码依赖它的帧时作为 `prior_frame`
+    prior: Option<(usize, Vec<u8>)>,
+    dimensions: ISize,
+    /// 编解码器实际解码的尺寸，不小于 `dimensions`
+    decode_dimensions: ISize,
+    max_bytes: usize,
+    decode_time: Duration,
+    stats: FrameCacheStats,
+}
+
+impl<'a> FrameCache<'a> {
+    pub fn new(mut codec: Codec<'a>) -> Result<Self, Error> {
+        let config = &CONFIG.decoder;
+        let reject = config.limit_policy == LimitPolicy::Reject;
+        let limit_error = |limit: &str, max: u64, actual: u64| {
+            Error::ImageLimitExceeded(limit.to_string(), max, actual)
+        };
+
+        let mut stats = FrameCacheStats::default();
+
+        let source_frame_count = codec.get_frame_count().max(1);
+        let mut frame_indexes: Vec<usize> = (0..source_frame_count).collect();
+        let mut durations = None;
+        let max_frames = config.max_frames as usize;
+        if max_frames > 0 && source_frame_count > max_frames {
+            if reject {
+                return Err(limit_error(
+                    "frames",
+                    max_frames as u64,
+                    source_frame_count as u64,
+                ));
+            }
+            // 等间隔抽帧，被丢弃的帧的时长累加到前一帧上
+            let step = source_frame_count.div_ceil(max_frames);
+            let source_durations = codec.get_durations()?;
+            frame_indexes = (0..source_frame_count).step_by(step).collect();
+            durations = Some(
+                source_durations
+                    .chunks(step)
+                    .map(|chunk| chunk.iter().sum())
+                    .collect(),
+            );
+            stats.decimated = source_frame_count - frame_indexes.len();
+        }
+        let frame_count = frame_indexes.len();
+
+        let origin = codec.dimensions();
+        let (width, height) = (origin.width.max(1) as f64, origin.height.max(1) as f64);
+        let mut scale: f64 = 1.0;
+
+        let pixel_area = width * height;
+        if config.max_pixel_area > 0 && pixel_area > config.max_pixel_area as f64 {
+            if reject {
+                return Err(limit_error(
+                    "pixel_area",
+                    config.max_pixel_area,
+                    pixel_area as u64,
+                ));
+            }
+            scale = scale.min((config.max_pixel_area as f64 / pixel_area).sqrt());
+        }
+
+        let decoded_bytes = pixel_area * 4.0 * frame_count as f64;
+        if config.max_decoded_bytes > 0 && decoded_bytes > config.max_decoded_bytes as f64 {
+            if reject {
+                return Err(limit_error(
+                    "decoded_bytes",
+                    config.max_decoded_bytes as u64,
+                    decoded_bytes as u64,
+                ));
+            }
+            scale = scale.min((config.max_decoded_bytes as f64 / decoded_bytes).sqrt());
+        }
+
+        if config.max_image_size > 0 {
+            scale = scale.min(config.max_image_size as f64 / width.max(height));
+        }
+        if decoded_bytes > config.frame_cache_max_bytes as f64 {
+            scale = scale.min((config.frame_cache_max_bytes as f64 / decoded_bytes).sqrt());
+        }
+
+        // 编解码器不支持按比例解码时，会按原尺寸解码到缓冲区中再缩小
+        let decode_dimensions = if scale < 1.0 {
+            codec.get_scaled_dimensions(scale as f32)
+        } else {
+            origin
+        };
+        let decode_area = decode_dimensions.width as f64 * decode_dimensions.height as f64;
+        if config.max_pixel_area > 0 && decode_area > config.max_pixel_area as f64 {
+            return Err(limit_error(
+                "pixel_area",
+                config.max_pixel_area,
+                decode_area as u64,
+            ));
+        }
+        if config.max_decoded_bytes > 0 {
+            // 解码缓冲区也计入解码后的大小
+            let max_decoded_bytes = config.max_decoded_bytes as f64;
+            let buffer_bytes = decode_area * 4.0;
+            let total_bytes = decoded_bytes * scale * scale + buffer_bytes;
+            if total_bytes > max_decoded_bytes {
+                if reject || buffer_bytes >= max_decoded_bytes {
+                    return Err(limit_error(
+                        "decoded_bytes",
+                        config.max_decoded_bytes as u64,
+                        total_bytes as u64,
+                    ));
+                }
+                scale = scale.min(((max_decoded_bytes - buffer_bytes) / decoded_bytes).sqrt());
+            }
+        }
+
+        let dimensions = if scale < 1.0 {
+            stats.downscaled = 1;
+            ISize::new(
+                ((width * scale) as i32).max(1),
+                ((height * scale) as i32).max(1),
+            )
+        } else {
+            origin
+        };
+
+        Ok(Self {
+            codec,
+            frames: vec![None; frame_count],
+            frame_indexes,
+            durations,
+            shared: None,
+            prior: None,
+            dimensions,
+            decode_dimensions,
+            max_bytes: config.frame_cache_max_bytes,
+            decode_time: Duration::ZERO,
+            stats,
+        })
+    }
+
+    /// 解码下一帧时占用的内存：已缓存的帧、解码缓冲区和正在解码的这一帧。
+    /// 未能缓存的帧解码后即被释放，重复解码它们不会增加占用
+    fn live_bytes(&self) -> usize {
+        let frame_bytes = self.dimensions.width as usize * self.dimensions.height as usize * 4;
+        let buffer_bytes =
+            self.decode_dimensions.width as usize * self.decode_dimensions.height as usize * 4;
+        self.stats.bytes + buffer_bytes + frame_bytes
+    }
+
+    /// 在 [`with_shared_frames`] 中时，与其他相同的输入图片共享解码结果
+    pub fn attach_shared(&mut self, data: &[u8]) {
+        let frame_count = self.frames.len();
+        self.shared = SHARED_FRAMES
+            .with_borrow(|shared| shared.as_ref().map(|shared| shared.get(data, frame_count)));
+    }
+
+    /// 解码后的帧尺寸
+    pub fn dimensions(&self) -> ISize {
+        self.dimensions
+    }
+
+    /// 原图的尺寸
+    pub fn source_dimensions(&self) -> ISize {
+        self.codec.dimensions()
+    }
+
+    /// 原图的帧数
+    pub fn source_frame_count(&mut self) -> usize {
+        self.codec.get_frame_count()
+    }
+
+    pub fn encoded_format(&self) -> EncodedImageFormat {
+        self.codec.encoded_format()
+    }
+
+    /// 抽帧后的帧数
+    pub fn get_frame_count(&mut self) -> usize {
+        self.frame_indexes.len()
+    }
+
+    pub fn stats(&self) -> FrameCacheStats {
+        self.stats
+    }
+
+    /// 按顺序解码时，在上一帧的像素上继续解码，避免 Skia 为每一帧重新解码整条依赖链
+    fn decode_frame(&mut self, source_index: usize) -> Result<Image, Error> {
+        let info = ImageInfo::new(
+            self.decode_dimensions,
+            ColorType::RGBA8888,
+            AlphaType::Unpremul,
+            None,
+        );
+        let row_bytes = info.min_row_bytes();
+        let required_frame = self
+            .codec
+            .get_frame_info(source_index)
+            .map_or(-1, |frame_info| frame_info.required_frame);
+        let prior_frame = match &self.prior {
+            Some((prior, _))
+                if required_frame >= 0
+                    && (required_frame as usize..source_index).contains(prior)
+                    && self.codec.get_frame_info(*prior).is_some_and(|frame_info| {
+                        frame_info.disposal_method != DisposalMethod::RestorePrevious
+                    }) =>
+            {
+                Some(*prior)
+            }
+            _ => None,
+        };
+        let mut pixels = match self.prior.take() {
+            Some((_, pixels)) => pixels,
+            None => vec![0; info.compute_byte_size(row_bytes)],
+        };
+
+        let options = codec::Options {
+            frame_index: source_index,
+            prior_frame,
+            ..Default::default()
+        };
+        let result =
+            self.codec
+                .get_pixels_with_options(&info, &mut pixels, row_bytes, Some(&options));
+        if !matches!(
+            result,
+            codec::Result::Success | codec::Result::IncompleteInput | codec::Result::ErrorInInput
+        ) {
+            return Err(Error::ImageDecodeError(format!(
+                "Skia decode error: {result:?}"
+            )));
+        }
+        let image = images::raster_from_data(&info, Data::new_copy(&pixels), row_bytes)
+            .ok_or(Error::ImageDecodeError("Skia decode error".to_string()))?;
+        self.prior = Some((source_index, pixels));
+        Ok(image)
+    }
+}
+
+impl CodecExt for FrameCache<'_> {
+    fn is_multi_frame(&mut self) -> bool {
+        self.frame_indexes.len() > 1
+    }
+
+    fn get_average_duration(&mut self) -> Result<f32, Error> {
+        match &self.durations {
+            Some(durations) => {
+                let total_duration: f32 = durations.iter().sum();
+                Ok((total_duration / durations.len() as f32).max(0.02))
+            }
+            None => self.codec.get_average_duration(),
+        }
+    }
+
+    fn get_durations(&mut self) -> Result<Vec<f32>, Error> {
+        match &self.durations {
+            Some(durations) => Ok(durations.clone()),
+            None => self.codec.get_durations(),
+        }
+    }
+
+    fn first_frame(&mut self) -> Result<Image, Error> {
+        self.get_frame(0)
+    }
+
+    fn get_frame(&mut self, index: usize) -> Result<Image, Error> {
+        if let Some(Some(image)) = self.frames.get(index) {
+            self.stats.hits += 1;
+            return Ok(image.clone());
+        }
+        if let Some(shared) = &self.shared
+            && let Some(image) = shared.get(index)
+        {
+            self.stats.hits += 1;
+            self.frames[index] = Some(image.clone());
+            return Ok(image.clone());
+        }
+
+        let max_decode_millis = CONFIG.decoder.max_decode_millis;
+        if max_decode_millis > 0 && self.decode_time.as_millis() > max_decode_millis as u128 {
+            return Err(Error::ImageLimitExceeded(
+                "decode_millis".to_string(),
+                max_decode_millis,
+                self.decode_time.as_millis() as u64,
+            ));
+        }
+
+        // 解码前检查，避免这一帧解码后占用的内存超出 `decoder.max_decoded_bytes`
+        let max_decoded_bytes = CONFIG.decoder.max_decoded_bytes;
+        let decoded_bytes = self.live_bytes();
+        if max_decoded_bytes > 0 && decoded_bytes > max_decoded_bytes {
+            return Err(Error::ImageLimitExceeded(
+                "decoded_bytes".to_string(),
+                max_decoded_bytes as u64,
+                decoded_bytes as u64,
+            ));
+        }
+
+        let start = Instant::now();
+        let source_index = self.frame_indexes.get(index).copied().unwrap_or(index);
+        let mut image = self.decode_frame(source_index)?;
+        if image.dimensions() != self.dimensions {
+            image = image.resize_exact(self.dimensions);
+        }
+        self.decode_time += start.elapsed();
+        self.stats.decoded += 1;
+
+        let size = image.width() as usize * image.height() as usize * 4;
+        if index < self.frames.len() && self.stats.bytes + size <= self.max_bytes {
+            self.stats.bytes += size;
+            LIVE_BYTES.fetch_add(size, Ordering::Relaxed);
+            self.frames[index] = Some(image.clone());
+            // 只共享本地缓存接受的帧
+            if let Some(shared) = &self.shared {
+                shared.insert(index, &image, size);
+            }
+        } else {
+            self.stats.uncached += 1;
+        }
+        Ok(image)
+    }
+}
+
+impl Drop for FrameCache<'_> {
+    fn drop(&mut self) {
+        debug!("Frame cache stats: {:?}", self.stats);
+        LIVE_BYTES.fetch_sub(self.stats.bytes, Ordering::Relaxed);
+        TOTAL_STATS.lock().unwrap().merge(&self.stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use skia_safe::{Color, surfaces};
+
+    use super::*;
+    use crate::encoder::encode_png;
+
+    fn new_cache(width: i32, height: i32) -> FrameCache<'static> {
+        let mut surface = surfaces::raster_n32_premul((width, height)).unwrap();
+        surface.canvas().clear(Color::RED);
+        let data = encode_png(surface.image_snapshot()).unwrap();
+        FrameCache::new(Codec::from_data(Data::new_copy(&data)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn redecoding_uncached_frames_keeps_live_bytes() {
+        let mut cache = new_cache(16, 8);
+        cache.max_bytes = 0;
+        let live_bytes = cache.live_bytes();
+        assert_eq!(live_bytes, 2 * 16 * 8 * 4);
+        for _ in 0..3 {
+            cache.get_frame(0).unwrap();
+            assert_eq!(cache.live_bytes(), live_bytes);
+        }
+        assert_eq!(cache.stats.decoded, 3);
+        assert_eq!(cache.stats.uncached, 3);
+    }
+
+    #[test]
+    fn cached_frames_count_once() {
+        let mut cache = new_cache(16, 8);
+        let live_bytes = cache.live_bytes();
+        for _ in 0..3 {
+            cache.get_frame(0).unwrap();
+        }
+        assert_eq!(cache.live_bytes(), live_bytes + 16 * 8 * 4);
+        assert_eq!(cache.stats.decoded, 1);
+        assert_eq!(cache.stats.hits, 2);
+    }
+}
@@ -1,10 +1,24 @@
-use std::thread::{self, JoinHandle};
-
+use std::{
+    sync::LazyLock,
+    thread::{self, JoinHandle},
+};
+
+use rayon::{
+    ThreadPool, ThreadPoolBuilder,
+    iter::{IntoParallelIterator, ParallelIterator},
+};
 use skia_safe::{AlphaType, ColorType, EncodedImageFormat, Image, ImageInfo, image::CachingHint};
+use tracing::warn;
 
 use meme_generator_core::error::Error;
 
-use crate::{builder::InputImage, config::CONFIG, decoder::CodecExt};
+use crate::{
+    builder::InputImage,
+    config::CONFIG,
+    decoder::{CodecExt, FrameCache},
+    progress::report_progress,
+    random::{frame_seed, mark_used, with_seed},
+};
 
 pub struct GifEncoder {
     collector: Option<gifski::Collector>,
@@ -130,18 +144,34 @@ pub enum FrameAlign {
     ExtendLast,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GifInfo {
     /// 帧数
     pub frame_num: u32,
 
     /// 帧间隔，单位为秒
     pub duration: f32,
+
+    /// 每一帧的时长，单位为秒，为 `None` 时每一帧的时长均为 `duration`
+    pub durations: Option<Vec<f32>>,
 }
 
 impl GifInfo {
+    /// 第 `index` 帧的时长，单位为秒
+    pub fn frame_duration(&self, index: usize) -> f32 {
+        self.durations
+            .as_ref()
+            .and_then(|durations| durations.get(index).copied())
+            .unwrap_or(self.duration)
+    }
+
     pub fn total_duration(&self) -> f32 {
-        self.frame_num as f32 * self.duration
+        match self.durations {
+            Some(_) => (0..self.frame_num as usize)
+                .map(|i| self.frame_duration(i))
+                .sum(),
+            None => self.frame_num as f32 * self.duration,
+        }
     }
 }
 
@@ -151,6 +181,8 @@ impl GifInfo {
 /// - `target_gif_info` 目标 gif 的帧数和帧间隔
 /// - `frame_align` gif 对齐方式
 ///
+/// 对齐按照每一帧的实际时长进行
+///
 /// 返回值：每个 gif 的帧索引列表和目标 gif 的帧索引列表
 pub fn get_aligned_gif_indexes(
     gif_infos: &Vec<GifInfo>,
@@ -168,28 +200,32 @@ pub fn get_aligned_gif_indexes(
 
     let diff_duration = max_total_duration - target_total_duration;
     if diff_duration >= target_gif_info.duration {
-        let diff_num = (diff_duration / target_gif_info.duration).ceil() as i32;
         let frame_align = frame_align.into().unwrap_or(FrameAlign::NoExtend);
         match frame_align {
             FrameAlign::ExtendFirst => {
+                let diff_num = (diff_duration / target_gif_info.frame_duration(0)).ceil() as usize;
                 let mut origin_frame_indexes = target_frame_indexes.clone();
-                target_frame_indexes = vec![0; diff_num as usize];
+                target_frame_indexes = vec![0; diff_num];
                 target_frame_indexes.append(&mut origin_frame_indexes);
             }
             FrameAlign::ExtendLast => {
-                let mut append_frame_indexes =
-                    vec![target_gif_info.frame_num as usize - 1; diff_num as usize];
+                let last_index = target_gif_info.frame_num as usize - 1;
+                let diff_num =
+                    (diff_duration / target_gif_info.frame_duration(last_index)).ceil() as usize;
+                let mut append_frame_indexes = vec![last_index; diff_num];
                 target_frame_indexes.append(&mut append_frame_indexes);
             }
             FrameAlign::ExtendLoop => {
                 let mut total_frame_num = target_gif_info.frame_num;
+                let mut loop_num = 1;
                 let max_frame_num = CONFIG.encoder.gif_max_frames;
                 while total_frame_num + target_gif_info.frame_num <= max_frame_num as u32 {
                     total_frame_num += target_gif_info.frame_num;
+                    loop_num += 1;
                     let mut append_frame_indexes =
                         (0..target_gif_info.frame_num as usize).collect();
                     target_frame_indexes.append(&mut append_frame_indexes);
-                    let total_duration = total_frame_num as f32 * target_gif_info.duration;
+                    let total_duration = loop_num as f32 * target_total_duration;
                     if gif_infos.iter().all(|gif_info| {
                         ((total_duration / gif_info.total_duration() as f32).round()
                             * gif_info.total_duration()
@@ -205,26 +241,37 @@ pub fn get_aligned_gif_indexes(
         }
     }
 
+    // 目标 gif 每一帧的开始时间
+    let mut time = 0.0;
+    let target_frame_times = target_frame_indexes
+        .iter()
+        .map(|index| {
+            let start = time;
+            time += target_gif_info.frame_duration(*index) as f64;
+            start
+        })
+        .collect::<Vec<f64>>();
+
     let mut frame_indexes: Vec<Vec<usize>> = Vec::new();
     for gif_info in gif_infos {
-        let mut frame_index = 0;
-        let mut time_start = 0.0;
+        let frame_num = gif_info.frame_num as usize;
         let mut indexes: Vec<usize> = Vec::new();
-        for i in 0..target_frame_indexes.len() {
-            while frame_index < gif_info.frame_num {
-                let duration = i as f32 * target_gif_info.duration - time_start;
-                if duration >= frame_index as f32 * gif_info.duration
-                    && duration < (frame_index + 1) as f32 * gif_info.duration
-                {
-                    indexes.push(frame_index as usize);
+        if frame_num == 0 || gif_info.total_duration() <= 0.0 {
+            indexes.resize(target_frame_times.len(), 0);
+            frame_indexes.push(indexes);
+            continue;
+        }
+        let mut frame_index = 0;
+        let mut frame_start = 0.0;
+        for time in target_frame_times.iter() {
+            loop {
+                let frame_end = frame_start + gif_info.frame_duration(frame_index) as f64;
+                if *time < frame_end {
+                    indexes.push(frame_index);
                     break;
-                } else {
-                    frame_index += 1;
-                    if frame_index >= gif_info.frame_num {
-                        frame_index = 0;
-                        time_start += gif_info.total_duration();
-                    }
                 }
+                frame_start = frame_end;
+                frame_index = (frame_index + 1) % frame_num;
             }
         }
         frame_indexes.push(indexes);
@@ -233,56 +280,172 @@ pub fn get_aligned_gif_indexes(
     (frame_indexes, target_frame_indexes)
 }
 
-/// 制作 png 或 gif
-///
-/// - `images` 图片列表
-/// - `func`: 图片处理函数，传入图片列表，返回处理后的图片
-///
-pub fn make_png_or_gif<F>(images: Vec<InputImage>, mut func: F) -> Result<Vec<u8>, Error>
-where
-    F: FnMut(Vec<Image>) -> Result<Image, Error>,
-{
-    let mut images = images
-        .into_iter()
-        .map(|image| image.codec)
-        .collect::<Vec<_>>();
+static RENDER_POOL: LazyLock<Option<ThreadPool>> = LazyLock::new(|| {
+    let threads = match CONFIG.encoder.render_threads {
+        0 => thread::available_parallelism()
+            .map(|num| num.get())
+            .unwrap_or(1),
+        num => num,
+    };
+    if threads <= 1 {
+        return None;
+    }
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("meme-render-{i}"))
+        .build()
+        .inspect_err(|err| warn!("Failed to build render thread pool: {err}"))
+        .ok()
+});
+
+/// 单帧的渲染任务
+struct FrameJob {
+    /// 传入图片处理函数的帧序号
+    index: usize,
+
+    /// 每个动图输入在该帧使用的帧序号
+    frame_indexes: Vec<usize>,
+
+    /// 该帧的时长，单位为秒
+    duration: f32,
+}
+
+/// gif 的渲染计划
+struct FramePlan<'a> {
+    inputs: Vec<FrameCache<'a>>,
+    gif_flags: Vec<bool>,
+    jobs: Vec<FrameJob>,
+}
+
+impl FramePlan<'_> {
+    fn decode_frame(&mut self, job: &FrameJob) -> Result<Vec<Image>, Error> {
+        let mut frame_images: Vec<Image> = Vec::new();
+        let mut gif_index = 0;
+        for (j, input) in self.inputs.iter_mut().enumerate() {
+            if self.gif_flags[j] {
+                frame_images.push(input.get_frame(job.frame_indexes[gif_index])?);
+                gif_index += 1;
+            } else {
+                frame_images.push(input.first_frame()?);
+            }
+        }
+        Ok(frame_images)
+    }
+
+    /// 在当前线程中依次渲染每一帧
+    fn render_sequential<F>(mut self, mut func: F) -> Result<Vec<u8>, Error>
+    where
+        F: FnMut(usize, Vec<Image>) -> Result<Image, Error>,
+    {
+        let jobs = std::mem::take(&mut self.jobs);
+        let total = jobs.len();
+        let mut encoder = GifEncoder::new();
+        for (i, job) in jobs.into_iter().enumerate() {
+            let frame_images = self.decode_frame(&job)?;
+            let (frame, _) = with_seed(frame_seed(job.index), || func(job.index, frame_images));
+            encoder.add_frame(frame?, job.duration)?;
+            report_progress(i + 1, total);
+        }
+        encoder.finish()
+    }
 
+    /// 在线程池中并行渲染，解码仍在当前线程中进行，渲染结果按帧顺序写入编码器
+    fn render_parallel<F>(mut self, func: F) -> Result<Vec<u8>, Error>
+    where
+        F: Fn(usize, Vec<Image>) -> Result<Image, Error> + Sync,
+    {
+        let Some(pool) = RENDER_POOL.as_ref() else {
+            return self.render_sequential(func);
+        };
+
+        let jobs = std::mem::take(&mut self.jobs);
+        let total = jobs.len();
+        let mut done = 0;
+        let chunk_size = pool.current_num_threads() * 2;
+        let mut encoder = GifEncoder::new();
+        let mut jobs = jobs.into_iter().peekable();
+        while jobs.peek().is_some() {
+            let mut inputs = Vec::with_capacity(chunk_size);
+            let mut durations = Vec::with_capacity(chunk_size);
+            for job in jobs.by_ref().take(chunk_size) {
+                let frame_images = self.decode_frame(&job)?;
+                inputs.push((job.index, frame_images, frame_seed(job.index)));
+                durations.push(job.duration);
+            }
+            let frames = pool.install(|| {
+                inputs
+                    .into_par_iter()
+                    .map(|(index, frame_images, seed)| {
+                        with_seed(seed, || func(index, frame_images))
+                    })
+                    .collect::<Vec<_>>()
+            });
+            for ((frame, used_random), duration) in frames.into_iter().zip(durations) {
+                if used_random {
+                    mark_used();
+                }
+                encoder.add_frame(frame?, duration)?;
+                done += 1;
+            }
+            report_progress(done, total);
+        }
+        encoder.finish()
+    }
+}
+
+fn get_gif_infos(inputs: &mut [FrameCache]) -> Result<(Vec<bool>, Vec<GifInfo>), Error> {
     let mut gif_flags: Vec<bool> = Vec::new();
     let mut gif_infos: Vec<GifInfo> = Vec::new();
-    for image in images.iter_mut() {
-        if image.is_multi_frame() {
+    for input in inputs.iter_mut() {
+        if input.is_multi_frame() {
             gif_flags.push(true);
             gif_infos.push(GifInfo {
-                frame_num: image.get_frame_count() as u32,
-                duration: image.get_average_duration()?,
+                frame_num: input.get_frame_count() as u32,
+                duration: input.get_average_duration()?,
+                durations: Some(input.get_durations()?),
             });
         } else {
             gif_flags.push(false);
         }
     }
+    Ok((gif_flags, gif_infos))
+}
+
+enum PngOrGif<'a> {
+    /// 输入中没有动图，各图片的第一帧
+    Png(Vec<Image>),
+
+    /// gif 的渲染计划
+    Gif(FramePlan<'a>),
+}
+
+fn plan_png_or_gif(images: Vec<InputImage>) -> Result<PngOrGif, Error> {
+    let mut inputs = images
+        .into_iter()
+        .map(|image| image.frames)
+        .collect::<Vec<_>>();
+    let (gif_flags, mut gif_infos) = get_gif_infos(&mut inputs)?;
 
     if gif_infos.len() == 0 {
-        let images = images
+        let images = inputs
             .iter_mut()
-            .map(|image| image.first_frame())
+            .map(|input| input.first_frame())
             .collect::<Result<Vec<_>, Error>>()?;
-        return Ok(encode_png(func(images)?)?);
+        return Ok(PngOrGif::Png(images));
     } else if gif_infos.len() == 1 {
-        let mut encoder = GifEncoder::new();
         let gif_info = &gif_infos[0];
-        for i in 0..gif_info.frame_num {
-            let mut frame_images: Vec<Image> = Vec::new();
-            for (j, image) in images.iter_mut().enumerate() {
-                if gif_flags[j] {
-                    frame_images.push(image.get_frame(i as usize)?);
-                } else {
-                    frame_images.push(image.first_frame()?);
-                }
-            }
-            let frame = func(frame_images)?;
-            encoder.add_frame(frame, gif_info.duration)?;
-        }
-        return Ok(encoder.finish()?);
+        let jobs = (0..gif_info.frame_num as usize)
+            .map(|i| FrameJob {
+                index: i,
+                frame_indexes: vec![i],
+                duration: gif_info.frame_duration(i),
+            })
+            .collect();
+        return Ok(PngOrGif::Gif(FramePlan {
+            inputs,
+            gif_flags,
+            jobs,
+        }));
     }
 
     let mut target_gif_index = 0;
@@ -298,25 +461,103 @@ where
 
     let (mut frame_indexes, target_frame_indexes) =
         get_aligned_gif_indexes(&gif_infos, &target_gif_info, FrameAlign::ExtendLoop);
-    let target_frame_num = target_frame_indexes.len();
+    let target_durations = target_frame_indexes
+        .iter()
+        .map(|index| target_gif_info.frame_duration(*index))
+        .collect::<Vec<_>>();
     frame_indexes.insert(target_gif_index, target_frame_indexes);
 
-    let mut encoder = GifEncoder::new();
-    for i in 0..target_frame_num {
-        let mut frame_images: Vec<Image> = Vec::new();
-        let mut gif_index = 0;
-        for (j, image) in images.iter_mut().enumerate() {
-            if gif_flags[j] {
-                frame_images.push(image.get_frame(frame_indexes[gif_index][i] as usize)?);
-                gif_index += 1;
-            } else {
-                frame_images.push(image.first_frame()?);
-            }
-        }
-        let frame = func(frame_images)?;
-        encoder.add_frame(frame, target_duration)?;
+    let jobs = target_durations
+        .into_iter()
+        .enumerate()
+        .map(|(i, duration)| FrameJob {
+            index: i,
+            frame_indexes: frame_indexes.iter().map(|indexes| indexes[i]).collect(),
+            duration,
+        })
+        .collect();
+    Ok(PngOrGif::Gif(FramePlan {
+        inputs,
+        gif_flags,
+        jobs,
+    }))
+}
+
+fn plan_gif_or_combined_gif(
+    images: Vec<InputImage>,
+    target_gif_info: GifInfo,
+    frame_align: Option<FrameAlign>,
+) -> Result<FramePlan, Error> {
+    let mut inputs = images
+        .into_iter()
+        .map(|image| image.frames)
+        .collect::<Vec<_>>();
+    let (gif_flags, gif_infos) = get_gif_infos(&mut inputs)?;
+
+    if gif_infos.len() == 0 {
+        let jobs = (0..target_gif_info.frame_num as usize)
+            .map(|i| FrameJob {
+                index: i,
+                frame_indexes: Vec::new(),
+                duration: target_gif_info.frame_duration(i),
+            })
+            .collect();
+        return Ok(FramePlan {
+            inputs,
+            gif_flags,
+            jobs,
+        });
+    }
+
+    let (frame_indexes, target_frame_indexes) =
+        get_aligned_gif_indexes(&gif_infos, &target_gif_info, frame_align);
+
+    let jobs = target_frame_indexes
+        .iter()
+        .enumerate()
+        .map(|(i, target_index)| FrameJob {
+            index: *target_index,
+            frame_indexes: frame_indexes.iter().map(|indexes| indexes[i]).collect(),
+            duration: target_gif_info.frame_duration(*target_index),
+        })
+        .collect();
+    Ok(FramePlan {
+        inputs,
+        gif_flags,
+        jobs,
+    })
+}
+
+/// 制作 png 或 gif
+///
+/// - `images` 图片列表
+/// - `func`: 图片处理函数，传入图片列表，返回处理后的图片
+///
+/// gif 的各帧在线程池中并行渲染，线程数由 `encoder.render_threads` 配置；
+/// 处理函数需要在帧之间保存状态时使用 [`make_png_or_gif_sequential`]
+pub fn make_png_or_gif<F>(images: Vec<InputImage>, func: F) -> Result<Vec<u8>, Error>
+where
+    F: Fn(Vec<Image>) -> Result<Image, Error> + Sync,
+{
+    match plan_png_or_gif(images)? {
+        PngOrGif::Png(images) => encode_png(func(images)?),
+        PngOrGif::Gif(plan) => plan.render_parallel(|_, images| func(images)),
+    }
+}
+
+/// 制作 png 或 gif，在当前线程中依次渲染每一帧
+///
+/// - `images` 图片列表
+/// - `func`: 图片处理函数，传入图片列表，返回处理后的图片
+///
+pub fn make_png_or_gif_sequential<F>(images: Vec<InputImage>, mut func: F) -> Result<Vec<u8>, Error>
+where
+    F: FnMut(Vec<Image>) -> Result<Image, Error>,
+{
+    match plan_png_or_gif(images)? {
+        PngOrGif::Png(images) => encode_png(func(images)?),
+        PngOrGif::Gif(plan) => plan.render_sequential(|_, images| func(images)),
     }
-    Ok(encoder.finish()?)
 }
 
 /// 使用静图或动图制作 gif
@@ -326,64 +567,125 @@ where
 /// - `target_gif_info` 目标 gif 的帧数和时间间隔
 /// - `frame_align` gif 对齐方式
 ///
+/// gif 的各帧在线程池中并行渲染，线程数由 `encoder.render_threads` 配置；
+/// 处理函数需要在帧之间保存状态时使用 [`make_gif_or_combined_gif_sequential`]
 pub fn make_gif_or_combined_gif<F>(
     images: Vec<InputImage>,
-    mut func: F,
+    func: F,
+    target_gif_info: GifInfo,
+    frame_align: impl Into<Option<FrameAlign>>,
+) -> Result<Vec<u8>, Error>
+where
+    F: Fn(usize, Vec<Image>) -> Result<Image, Error> + Sync,
+{
+    plan_gif_or_combined_gif(images, target_gif_info, frame_align.into())?.render_parallel(func)
+}
+
+/// 使用静图或动图制作 gif，在当前线程中依次渲染每一帧
+///
+/// - `images` 图片列表
+/// - `func` 图片处理函数，传入第几帧和图片列表，返回处理后的图片
+/// - `target_gif_info` 目标 gif 的帧数和时间间隔
+/// - `frame_align` gif 对齐方式
+///
+pub fn make_gif_or_combined_gif_sequential<F>(
+    images: Vec<InputImage>,
+    func: F,
     target_gif_info: GifInfo,
     frame_align: impl Into<Option<FrameAlign>>,
 ) -> Result<Vec<u8>, Error>
 where
     F: FnMut(usize, Vec<Image>) -> Result<Image, Error>,
 {
-    let mut images = images
-        .into_iter()
-        .map(|image| image.codec)
-        .collect::<Vec<_>>();
+    plan_gif_or_combined_gif(images, target_gif_info, frame_align.into())?.render_sequential(func)
+}
 
-    let mut gif_flags: Vec<bool> = Vec::new();
-    let mut gif_infos: Vec<GifInfo> = Vec::new();
-    for image in images.iter_mut() {
-        if image.is_multi_frame() {
-            gif_flags.push(true);
-            gif_infos.push(GifInfo {
-                frame_num: image.get_frame_count() as u32,
-                duration: image.get_average_duration()?,
-            });
-        } else {
-            gif_flags.push(false);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(frame_num: u32, duration: f32) -> GifInfo {
+        GifInfo {
+            frame_num,
+            duration,
+            durations: None,
         }
     }
 
-    if gif_infos.len() == 0 {
-        let mut encoder = GifEncoder::new();
-        for i in 0..target_gif_info.frame_num {
-            let frame_images = images
-                .iter_mut()
-                .map(|image| image.first_frame())
-                .collect::<Result<Vec<_>, Error>>()?;
-            let frame = func(i as usize, frame_images)?;
-            encoder.add_frame(frame, target_gif_info.duration)?;
+    fn variable(durations: &[f32]) -> GifInfo {
+        GifInfo {
+            frame_num: durations.len() as u32,
+            duration: durations[0],
+            durations: Some(durations.to_vec()),
         }
-        return Ok(encoder.finish()?);
     }
 
-    let (frame_indexes, target_frame_indexes) =
-        get_aligned_gif_indexes(&gif_infos, &target_gif_info, frame_align);
+    #[test]
+    fn align_uniform_durations() {
+        let (indexes, target) =
+            get_aligned_gif_indexes(&vec![uniform(4, 0.25)], &uniform(8, 0.125), None);
+        assert_eq!(indexes, [vec![0, 0, 1, 1, 2, 2, 3, 3]]);
+        assert_eq!(target, (0..8).collect::<Vec<_>>());
+    }
 
-    let mut encoder = GifEncoder::new();
-    for (i, target_index) in target_frame_indexes.iter().enumerate() {
-        let mut frame_images: Vec<Image> = Vec::new();
-        let mut gif_index = 0;
-        for (j, image) in images.iter_mut().enumerate() {
-            if gif_flags[j] {
-                frame_images.push(image.get_frame(frame_indexes[gif_index][i] as usize)?);
-                gif_index += 1;
-            } else {
-                frame_images.push(image.first_frame()?);
-            }
-        }
-        let frame = func(*target_index, frame_images)?;
-        encoder.add_frame(frame, target_gif_info.duration)?;
+    #[test]
+    fn align_variable_input_durations() {
+        let (indexes, target) = get_aligned_gif_indexes(
+            &vec![variable(&[0.25, 0.125, 0.125])],
+            &uniform(4, 0.125),
+            None,
+        );
+        assert_eq!(indexes, [vec![0, 0, 1, 2]]);
+        assert_eq!(target, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn align_variable_target_durations() {
+        let (indexes, target) = get_aligned_gif_indexes(
+            &vec![uniform(4, 0.125)],
+            &variable(&[0.25, 0.125, 0.125]),
+            None,
+        );
+        assert_eq!(indexes, [vec![0, 2, 3]]);
+        assert_eq!(target, [0, 1, 2]);
+    }
+
+    #[test]
+    fn align_with_frame_align() {
+        let gif_infos = vec![uniform(8, 0.125)];
+        let target_info = uniform(4, 0.125);
+
+        let (indexes, target) = get_aligned_gif_indexes(&gif_infos, &target_info, None);
+        assert_eq!(indexes, [vec![0, 1, 2, 3]]);
+        assert_eq!(target, [0, 1, 2, 3]);
+
+        let (indexes, target) =
+            get_aligned_gif_indexes(&gif_infos, &target_info, FrameAlign::ExtendFirst);
+        assert_eq!(indexes, [(0..8).collect::<Vec<_>>()]);
+        assert_eq!(target, [0, 0, 0, 0, 0, 1, 2, 3]);
+
+        let (indexes, target) =
+            get_aligned_gif_indexes(&gif_infos, &target_info, FrameAlign::ExtendLast);
+        assert_eq!(indexes, [(0..8).collect::<Vec<_>>()]);
+        assert_eq!(target, [0, 1, 2, 3, 3, 3, 3, 3]);
+
+        let (indexes, target) =
+            get_aligned_gif_indexes(&gif_infos, &target_info, FrameAlign::ExtendLoop);
+        assert_eq!(indexes, [(0..8).collect::<Vec<_>>()]);
+        assert_eq!(target, [0, 1, 2, 3, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn align_multiple_and_empty_gifs() {
+        let (indexes, target) = get_aligned_gif_indexes(
+            &vec![uniform(2, 0.25), uniform(0, 0.125), uniform(3, 0.125)],
+            &uniform(4, 0.125),
+            None,
+        );
+        assert_eq!(
+            indexes,
+            [vec![0, 0, 1, 1], vec![0, 0, 0, 0], vec![0, 1, 2, 0]]
+        );
+        assert_eq!(target, [0, 1, 2, 3]);
     }
-    Ok(encoder.finish()?)
 }
@@ -1,9 +1,13 @@
 pub mod api;
+pub mod bbcode;
 pub mod builder;
 pub mod canvas;
 pub mod config;
 pub mod decoder;
 pub mod encoder;
 pub mod image;
+pub mod progress;
+pub mod random;
 pub mod text;
 pub mod tools;
+mod vertical;
@@ -0,0 +1,33 @@
+use std::{cell::RefCell, rc::Rc};
+
+thread_local! {
+    static CALLBACK: RefCell<Option<Rc<dyn Fn(usize, usize)>>> = const { RefCell::new(None) };
+}
+
+struct CallbackGuard {
+    callback: Option<Rc<dyn Fn(usize, usize)>>,
+}
+
+impl Drop for CallbackGuard {
+    fn drop(&mut self) {
+        CALLBACK.set(self.callback.take());
+    }
+}
+
+/// 在执行函数期间，通过回调获取 gif 的渲染进度
+///
+/// 回调的参数为已完成的帧数和总帧数
+pub fn with_progress<T>(callback: impl Fn(usize, usize) + 'static, func: impl FnOnce() -> T) -> T {
+    let _guard = CallbackGuard {
+        callback: CALLBACK.replace(Some(Rc::new(callback))),
+    };
+    func()
+}
+
+/// 报告当前线程的渲染进度
+pub fn report_progress(done: usize, total: usize) {
+    let callback = CALLBACK.with_borrow(|callback| callback.clone());
+    if let Some(callback) = callback {
+        callback(done, total);
+    }
+}
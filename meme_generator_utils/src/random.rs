@@ -0,0 +1,68 @@
+use std::cell::Cell;
+
+use rand::{SeedableRng, rngs::StdRng};
+
+thread_local! {
+    static SEED: Cell<Option<u64>> = const { Cell::new(None) };
+    static COUNTER: Cell<u64> = const { Cell::new(0) };
+    static USED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// 获取随机数生成器
+///
+/// 在 [`with_seed`] 中指定了种子时，依次获取的生成器是确定的；否则使用随机种子
+pub fn rng() -> StdRng {
+    USED.set(true);
+    match SEED.get() {
+        Some(seed) => {
+            let counter = COUNTER.get();
+            COUNTER.set(counter + 1);
+            StdRng::seed_from_u64(seed ^ counter.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        }
+        None => rand::make_rng(),
+    }
+}
+
+struct SeedGuard {
+    seed: Option<u64>,
+    counter: u64,
+    used: bool,
+}
+
+impl Drop for SeedGuard {
+    fn drop(&mut self) {
+        let used = USED.get();
+        SEED.set(self.seed);
+        COUNTER.set(self.counter);
+        USED.set(self.used || used);
+    }
+}
+
+/// 在指定的随机数种子下执行函数
+///
+/// 返回函数的结果，以及执行过程中是否获取过随机数生成器
+pub fn with_seed<T>(seed: Option<u64>, func: impl FnOnce() -> T) -> (T, bool) {
+    let _guard = SeedGuard {
+        seed: SEED.replace(seed),
+        counter: COUNTER.replace(0),
+        used: USED.replace(false),
+    };
+    let result = func();
+    (result, USED.get())
+}
+
+/// 为第 `index` 帧派生随机数种子，用于在其他线程中通过 [`with_seed`] 复现随机结果
+///
+/// 当前未指定种子时返回 `None`
+pub fn frame_seed(index: usize) -> Option<u64> {
+    SEED.get().map(|seed| {
+        let counter = COUNTER.get();
+        seed ^ counter.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (index as u64 + 1).wrapping_mul(0xbf58_476d_1ce4_e5b9)
+    })
+}
+
+/// 标记当前线程获取过随机数生成器，用于汇总其他线程中的随机数使用情况
+pub fn mark_used() {
+    USED.set(true);
+}
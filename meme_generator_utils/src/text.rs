@@ -1,20 +1,25 @@
 use std::{
-    collections::VecDeque,
+    collections::{BTreeSet, VecDeque},
+    path::{Path, PathBuf},
     sync::{LazyLock, Mutex},
 };
 
+use serde::Serialize;
+
 use skia_safe::{
     Canvas, Color, Data, FontMgr, FontStyle, Paint, Point, Typeface, scalar,
     textlayout::{
         FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextAlign, TextDecoration,
-        TextStyle, TypefaceFontProvider,
+        TextShadow, TextStyle, TypefaceFontProvider,
     },
 };
 use tracing::warn;
 
 use crate::{
+    bbcode::{BBCodeTag, BBCodeToken, parse_bbcode},
     config::{CONFIG, FONTS_DIR},
     tools::{color_from_str, new_decoration, new_paint, new_stroke_paint},
+    vertical::{StyledRun, VerticalText},
 };
 
 static FONT_MANAGER: LazyLock<Mutex<FontManager>> =
@@ -22,20 +27,26 @@ static FONT_MANAGER: LazyLock<Mutex<FontManager>> =
 
 struct FontManager {
     font_collection: FontCollection,
+    local_fonts: Vec<LocalFont>,
+    failed_files: Vec<PathBuf>,
+    /// 制作表情时请求过的字体族
+    requested_families: BTreeSet<String>,
 }
 
 /// Create a Typeface from a font file using mmap (Data::from_filename)
 /// instead of fs::read, avoiding an extra heap allocation per font.
-fn typeface_from_file(font_mgr: &FontMgr, path: &std::path::Path) -> Option<Typeface> {
+fn typeface_from_file(font_mgr: &FontMgr, path: &Path) -> Option<Typeface> {
     let data = Data::from_filename(path)?;
     font_mgr.new_from_data(data.as_bytes(), None)
 }
 
-fn construct_font_provider() -> TypefaceFontProvider {
+fn construct_font_provider() -> (TypefaceFontProvider, Vec<LocalFont>, Vec<PathBuf>) {
     let mut font_provider = TypefaceFontProvider::new();
+    let mut local_fonts = Vec::new();
+    let mut failed_files = Vec::new();
     let font_mgr = FontMgr::new();
     if !FONTS_DIR.exists() {
-        return font_provider;
+        return (font_provider, local_fonts, failed_files);
     }
     let entries = FONTS_DIR.read_dir();
     if let Ok(entries) = entries {
@@ -49,16 +60,21 @@ fn construct_font_provider() -> TypefaceFontProvider {
                             continue;
                         }
                         if let Some(font) = typeface_from_file(&font_mgr, &path) {
+                            local_fonts.push(LocalFont {
+                                family: font.family_name(),
+                                file: path,
+                            });
                             font_provider.register_typeface(font, None);
                         } else {
                             warn!("Failed to create typeface from font file: {path:?}");
+                            failed_files.push(path);
                         }
                     }
                 }
             }
         }
     }
-    font_provider
+    (font_provider, local_fonts, failed_files)
 }
 
 impl FontManager {
@@ -67,30 +83,143 @@ impl FontManager {
         let mut font_collection = FontCollection::new();
         font_collection.set_default_font_manager(font_mgr, None);
 
+        let mut local_fonts = Vec::new();
+        let mut failed_files = Vec::new();
         if CONFIG.font.use_local_fonts {
-            let font_provider = construct_font_provider();
+            let (font_provider, fonts, failed) = construct_font_provider();
             font_collection.set_asset_font_manager(FontMgr::from(font_provider));
+            local_fonts = fonts;
+            failed_files = failed;
         }
 
         Self {
             font_collection: font_collection,
+            local_fonts,
+            failed_files,
+            requested_families: BTreeSet::new(),
         }
     }
 
     pub fn font_collection(&self) -> &FontCollection {
         &self.font_collection
     }
+
+    fn record_families(&mut self, families: &[String]) {
+        for family in families {
+            if !self.requested_families.contains(family) {
+                self.requested_families.insert(family.clone());
+            }
+        }
+    }
+
+    fn font_source(&self, family: &str) -> FontSource {
+        if self
+            .local_fonts
+            .iter()
+            .any(|font| font.family.eq_ignore_ascii_case(family))
+        {
+            return FontSource::Local;
+        }
+        match FontMgr::new().match_family_style(family, FontStyle::normal()) {
+            Some(typeface) if typeface.family_name().eq_ignore_ascii_case(family) => {
+                FontSource::System
+            }
+            _ => FontSource::Missing,
+        }
+    }
 }
 
 unsafe impl Send for FontManager {}
 
+/// 初始化字体，返回加载的本地字体数量
+pub fn init_fonts() -> usize {
+    FONT_MANAGER.lock().unwrap().local_fonts.len()
+}
+
+/// 字体族的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FontSource {
+    /// 字体目录中的本地字体
+    Local,
+    /// 系统中安装的字体
+    System,
+    /// 找不到该字体，绘制时会回退到其他字体
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalFont {
+    pub file: PathBuf,
+    pub family: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FontReport {
+    pub use_local_fonts: bool,
+    /// 成功加载的本地字体
+    pub local_fonts: Vec<LocalFont>,
+    /// 无法加载的字体文件
+    pub failed_files: Vec<PathBuf>,
+    /// 默认字体族和制作表情时请求过的字体族，以及它们的来源
+    pub families: Vec<(String, FontSource)>,
+}
+
+/// 字体的加载情况，首次调用时会初始化字体
+///
+/// 只有已经制作过的表情所请求的字体族会被列出
+pub fn font_report() -> FontReport {
+    let font_manager = FONT_MANAGER.lock().unwrap();
+    let mut families = CONFIG.font.default_font_families.clone();
+    for family in &font_manager.requested_families {
+        if !families.contains(family) {
+            families.push(family.clone());
+        }
+    }
+    FontReport {
+        use_local_fonts: CONFIG.font.use_local_fonts,
+        local_fonts: font_manager.local_fonts.clone(),
+        failed_files: font_manager.failed_files.clone(),
+        families: families
+            .into_iter()
+            .map(|family| {
+                let source = font_manager.font_source(&family);
+                (family, source)
+            })
+            .collect(),
+    }
+}
+
+/// 文字的书写方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WritingMode {
+    /// 横排，从左到右
+    #[default]
+    Horizontal,
+    /// 竖排，从上到下、从右到左
+    Vertical,
+}
+
+/// 竖排时西文等横排文字的方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextOrientation {
+    /// 西文单词顺时针旋转 90°，中日韩文字直立
+    #[default]
+    Mixed,
+    /// 所有文字都直立，逐字排列
+    Upright,
+}
+
 #[derive(Debug, Clone)]
 pub struct TextParams {
     pub font_style: FontStyle,
     pub font_families: Vec<String>,
+    /// 对齐方式，竖排时 `Left` 为顶部对齐，`Right` 为底部对齐
     pub text_align: TextAlign,
     pub paint: Paint,
     pub stroke_paint: Option<Paint>,
+    pub writing_mode: WritingMode,
+    pub text_orientation: TextOrientation,
 }
 
 impl Default for TextParams {
@@ -101,6 +230,8 @@ impl Default for TextParams {
             text_align: TextAlign::Center,
             paint: new_paint(Color::BLACK),
             stroke_paint: None,
+            writing_mode: WritingMode::Horizontal,
+            text_orientation: TextOrientation::Mixed,
         }
     }
 }
@@ -120,6 +251,8 @@ macro_rules! text_params {
 pub mod text_params_setters {
     use skia_safe::{FontStyle, Paint, textlayout::TextAlign};
 
+    use super::{TextOrientation, WritingMode};
+
     pub fn font_style(style: FontStyle) -> FontStyle {
         style
     }
@@ -139,11 +272,28 @@ pub mod text_params_setters {
     pub fn stroke_paint(paint: Paint) -> Option<Paint> {
         Some(paint)
     }
+
+    pub fn writing_mode(mode: WritingMode) -> WritingMode {
+        mode
+    }
+
+    pub fn text_orientation(orientation: TextOrientation) -> TextOrientation {
+        orientation
+    }
+}
+
+enum TextLayout {
+    Horizontal {
+        paragraph: Paragraph,
+        stroke_paragraph: Option<Paragraph>,
+        /// 背景高亮和阴影
+        background_paragraph: Option<Paragraph>,
+    },
+    Vertical(VerticalText),
 }
 
 pub struct Text2Image {
-    paragraph: Paragraph,
-    stroke_paragraph: Option<Paragraph>,
+    layout: TextLayout,
 }
 
 impl Text2Image {
@@ -160,7 +310,8 @@ impl Text2Image {
         let mut paragraph_style = ParagraphStyle::new();
         paragraph_style.set_text_align(text_params.text_align);
 
-        let font_manager = FONT_MANAGER.lock().unwrap();
+        let mut font_manager = FONT_MANAGER.lock().unwrap();
+        font_manager.record_families(&text_params.font_families);
         let mut builder = ParagraphBuilder::new(&paragraph_style, font_manager.font_collection());
         let mut style = TextStyle::new();
         style.set_font_size(font_size);
@@ -168,6 +319,27 @@ impl Text2Image {
         style.set_foreground_paint(&text_params.paint);
         style.set_font_families(&font_families);
         builder.push_style(&style);
+
+        if text_params.writing_mode == WritingMode::Vertical {
+            let stroke_style = text_params.stroke_paint.as_ref().map(|stroke_paint| {
+                let mut stroke_style = style.clone();
+                stroke_style.set_foreground_paint(stroke_paint);
+                stroke_style
+            });
+            let runs = [StyledRun {
+                text,
+                style,
+                stroke_style,
+                background_style: None,
+            }];
+            return Self::vertical(
+                &runs,
+                text_params.text_align,
+                text_params.text_orientation,
+                font_manager.font_collection(),
+            );
+        }
+
         builder.add_text(text.clone());
         let mut paragraph = builder.build();
         paragraph.layout(scalar::INFINITY);
@@ -191,8 +363,11 @@ impl Text2Image {
         };
 
         let mut text2image = Self {
-            paragraph,
-            stroke_paragraph,
+            layout: TextLayout::Horizontal {
+                paragraph,
+                stroke_paragraph,
+                background_paragraph: None,
+            },
         };
         text2image.layout(text2image.longest_line().ceil());
         text2image
@@ -211,7 +386,8 @@ impl Text2Image {
         let mut paragraph_style = ParagraphStyle::new();
         paragraph_style.set_text_align(text_params.text_align);
 
-        let font_manager = FONT_MANAGER.lock().unwrap();
+        let mut font_manager = FONT_MANAGER.lock().unwrap();
+        font_manager.record_families(&text_params.font_families);
         let mut builder = ParagraphBuilder::new(&paragraph_style, font_manager.font_collection());
         let mut style = TextStyle::new();
         style.set_font_size(font_size);
@@ -231,10 +407,19 @@ impl Text2Image {
         stroke_style.set_font_families(&font_families);
         stroke_builder.push_style(&stroke_style);
 
+        let mut background_builder =
+            ParagraphBuilder::new(&paragraph_style, font_manager.font_collection());
+        let mut background_style = style.clone();
+        background_style.set_foreground_paint(&new_paint(Color::TRANSPARENT));
+        background_builder.push_style(&background_style);
+
         let mut paint = text_params.paint;
         let mut stroke_paint = text_params
             .stroke_paint
             .unwrap_or(new_stroke_paint(Color::BLACK, 0.04 * font_size));
+        let default_color = paint.color();
+        let default_stroke_color = stroke_paint.color();
+        let default_stroke_width = stroke_paint.stroke_width();
 
         let mut bold_stack = VecDeque::new();
         let mut italic_stack = VecDeque::new();
@@ -242,9 +427,16 @@ impl Text2Image {
         let mut strikethrough_stack = VecDeque::new();
         let mut color_stack = VecDeque::new();
         let mut stroke_stack = VecDeque::new();
+        let mut size_stack = VecDeque::new();
+        let mut font_stack = VecDeque::new();
+        let mut background_stack = VecDeque::new();
+        let mut shadow_stack = VecDeque::new();
+        let mut opacity_stack = VecDeque::new();
         let mut has_stroke = false;
+        let mut has_background = false;
+        let mut runs = Vec::new();
 
-        let tokens = tokenize_bbcode(&text);
+        let tokens = parse_bbcode(&text);
         for token in tokens {
             match token {
                 BBCodeToken::OpenTag(tag) => match tag {
@@ -261,42 +453,94 @@ impl Text2Image {
                         strikethrough_stack.push_back(true);
                     }
                     BBCodeTag::Color(color) => {
-                        let color = color_from_str(&color);
-                        color_stack.push_back(color);
+                        color_stack.push_back(color_from_str(&color));
                     }
                     BBCodeTag::Stroke(color) => {
-                        let color = color_from_str(&color);
-                        stroke_stack.push_back(color);
+                        stroke_stack.push_back(color_from_str(&color));
                         has_stroke = true;
                     }
+                    BBCodeTag::Size(size) => {
+                        size_stack.push_back(size);
+                    }
+                    BBCodeTag::Font(font) => {
+                        font_manager.record_families(std::slice::from_ref(&font));
+                        font_stack.push_back(font);
+                    }
+                    BBCodeTag::Background(color) => {
+                        background_stack.push_back(color_from_str(&color));
+                        has_background = true;
+                    }
+                    BBCodeTag::Shadow(color) => {
+                        shadow_stack.push_back(color_from_str(&color));
+                        has_background = true;
+                    }
+                    BBCodeTag::Opacity(opacity) => {
+                        opacity_stack.push_back(opacity);
+                    }
                 },
-                BBCodeToken::CloseTag(tag) => match tag {
-                    BBCodeTag::Bold => {
+                BBCodeToken::CloseTag(name) => match name.as_str() {
+                    "b" => {
                         bold_stack.pop_back();
                     }
-                    BBCodeTag::Italic => {
+                    "i" => {
                         italic_stack.pop_back();
                     }
-                    BBCodeTag::Underline => {
+                    "u" => {
                         underline_stack.pop_back();
                     }
-                    BBCodeTag::Strikethrough => {
+                    "del" => {
                         strikethrough_stack.pop_back();
                     }
-                    BBCodeTag::Color(_) => {
+                    "color" => {
                         color_stack.pop_back();
                     }
-                    BBCodeTag::Stroke(_) => {
+                    "stroke" => {
                         stroke_stack.pop_back();
                     }
+                    "size" => {
+                        size_stack.pop_back();
+                    }
+                    "font" => {
+                        font_stack.pop_back();
+                    }
+                    "bg" => {
+                        background_stack.pop_back();
+                    }
+                    "shadow" => {
+                        shadow_stack.pop_back();
+                    }
+                    "opacity" => {
+                        opacity_stack.pop_back();
+                    }
+                    _ => {}
                 },
                 BBCodeToken::Text(text) => {
                     let bold = bold_stack.back().cloned().unwrap_or(false);
                     let italic = italic_stack.back().cloned().unwrap_or(false);
                     let underline = underline_stack.back().cloned().unwrap_or(false);
                     let strikethrough = strikethrough_stack.back().cloned().unwrap_or(false);
-                    let color = color_stack.back().cloned().unwrap_or(paint.color());
-                    let stroke_color = stroke_stack.back().cloned().unwrap_or(stroke_paint.color());
+                    // 字号为当前字号的倍数，嵌套时相乘
+                    let size = font_size * size_stack.iter().product::<scalar>();
+                    // 嵌套的不透明度相乘
+                    let opacity: scalar = opacity_stack.iter().product();
+                    let color = with_opacity(
+                        color_stack.back().cloned().unwrap_or(default_color),
+                        opacity,
+                    );
+                    let stroke_color = with_opacity(
+                        stroke_stack.back().cloned().unwrap_or(default_stroke_color),
+                        opacity,
+                    );
+                    let background_color = background_stack
+                        .back()
+                        .map(|color| with_opacity(*color, opacity));
+                    let shadow_color = shadow_stack
+                        .back()
+                        .map(|color| with_opacity(*color, opacity));
+                    let families = match font_stack.back() {
+                        Some(font) => [vec![font.clone()], font_families.clone()].concat(),
+                        None => font_families.clone(),
+                    };
 
                     let font_style = if bold && italic {
                         FontStyle::bold_italic()
@@ -317,24 +561,69 @@ impl Text2Image {
                         TextDecoration::NO_DECORATION
                     };
                     let decoration = new_decoration(text_decoration, color);
+                    style.set_font_size(size);
+                    style.set_font_families(&families);
                     style.set_font_style(font_style);
                     style.set_decoration(&decoration);
                     paint.set_color(color);
                     style.set_foreground_paint(&paint);
 
+                    stroke_style.set_font_size(size);
+                    stroke_style.set_font_families(&families);
                     stroke_style.set_font_style(font_style);
                     stroke_style.set_decoration(&decoration);
                     stroke_paint.set_color(stroke_color);
+                    stroke_paint.set_stroke_width(default_stroke_width * size / font_size);
                     stroke_style.set_foreground_paint(&stroke_paint);
 
+                    // 背景和阴影绘制在描边之下，文字本身透明
+                    background_style = style.clone();
+                    background_style.set_decoration_type(TextDecoration::NO_DECORATION);
+                    background_style.set_foreground_paint(&new_paint(Color::TRANSPARENT));
+                    if let Some(color) = background_color {
+                        background_style.set_background_paint(&new_paint(color));
+                    }
+                    if let Some(color) = shadow_color {
+                        let offset = 0.05 * size;
+                        background_style.add_shadow(TextShadow::new(
+                            color,
+                            (offset, offset),
+                            offset as f64,
+                        ));
+                    }
+
                     builder.pop();
                     builder.push_style(&style);
                     builder.add_text(text.clone());
                     stroke_builder.pop();
                     stroke_builder.push_style(&stroke_style);
-                    stroke_builder.add_text(text);
+                    stroke_builder.add_text(text.clone());
+                    background_builder.pop();
+                    background_builder.push_style(&background_style);
+                    background_builder.add_text(text.clone());
+                    runs.push(StyledRun {
+                        text,
+                        style: style.clone(),
+                        stroke_style: Some(stroke_style.clone()),
+                        background_style: (background_color.is_some() || shadow_color.is_some())
+                            .then(|| background_style.clone()),
+                    });
+                }
+            }
+        }
+
+        if text_params.writing_mode == WritingMode::Vertical {
+            if !has_stroke {
+                for run in &mut runs {
+                    run.stroke_style = None;
                 }
             }
+            return Self::vertical(
+                &runs,
+                text_params.text_align,
+                text_params.text_orientation,
+                font_manager.font_collection(),
+            );
         }
 
         let mut paragraph = builder.build();
@@ -348,113 +637,108 @@ impl Text2Image {
             None
         };
 
+        let background_paragraph = if has_background {
+            let mut background_paragraph = background_builder.build();
+            background_paragraph.layout(scalar::INFINITY);
+            Some(background_paragraph)
+        } else {
+            None
+        };
+
         let mut text2image = Self {
-            paragraph,
-            stroke_paragraph,
+            layout: TextLayout::Horizontal {
+                paragraph,
+                stroke_paragraph,
+                background_paragraph,
+            },
         };
         text2image.layout(text2image.longest_line().ceil());
         text2image
     }
 
-    pub fn longest_line(&self) -> scalar {
-        self.paragraph.longest_line()
+    fn vertical(
+        runs: &[StyledRun],
+        text_align: TextAlign,
+        text_orientation: TextOrientation,
+        font_collection: &FontCollection,
+    ) -> Self {
+        let mut vertical_text =
+            VerticalText::new(runs, text_align, text_orientation, font_collection);
+        vertical_text.layout(vertical_text.longest_column().ceil());
+        Self {
+            layout: TextLayout::Vertical(vertical_text),
+        }
     }
 
-    pub fn height(&self) -> scalar {
-        self.paragraph.height()
+    pub fn is_vertical(&self) -> bool {
+        matches!(self.layout, TextLayout::Vertical(_))
     }
 
-    pub fn layout(&mut self, width: scalar) {
-        self.paragraph.layout(width);
-        if let Some(stroke_paragraph) = &mut self.stroke_paragraph {
-            stroke_paragraph.layout(width);
+    /// 最长一行的长度，竖排时为最长一列的长度
+    pub fn longest_line(&self) -> scalar {
+        match &self.layout {
+            TextLayout::Horizontal { paragraph, .. } => paragraph.longest_line(),
+            TextLayout::Vertical(vertical_text) => vertical_text.longest_column(),
         }
     }
 
-    pub fn draw_on_canvas(&self, canvas: &Canvas, origin: impl Into<Point>) {
-        let origin: Point = origin.into();
-        if let Some(stroke_paragraph) = &self.stroke_paragraph {
-            stroke_paragraph.paint(canvas, origin);
+    /// 排版后的宽度，横排时为排版宽度
+    pub fn width(&self) -> scalar {
+        match &self.layout {
+            TextLayout::Horizontal { paragraph, .. } => paragraph.max_width(),
+            TextLayout::Vertical(vertical_text) => vertical_text.width(),
         }
-        self.paragraph.paint(canvas, origin);
     }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-enum BBCodeTag {
-    Bold,
-    Italic,
-    Underline,
-    Strikethrough,
-    Color(String),
-    Stroke(String),
-}
 
-#[derive(Debug, Clone, PartialEq)]
-enum BBCodeToken {
-    Text(String),
-    OpenTag(BBCodeTag),
-    CloseTag(BBCodeTag),
-}
-
-fn tokenize_bbcode(input: &str) -> Vec<BBCodeToken> {
-    let mut tokens = Vec::new();
-    let mut i = 0;
+    pub fn height(&self) -> scalar {
+        match &self.layout {
+            TextLayout::Horizontal { paragraph, .. } => paragraph.height(),
+            TextLayout::Vertical(vertical_text) => vertical_text.height(),
+        }
+    }
 
-    while i < input.len() {
-        if let Some(tag_start) = input[i..].find('[') {
-            if tag_start > 0 {
-                let text = input[i..i + tag_start].to_string();
-                tokens.push(BBCodeToken::Text(text));
+    /// 按行的最大长度排版，竖排时为列高
+    pub fn layout(&mut self, width: scalar) {
+        match &mut self.layout {
+            TextLayout::Horizontal {
+                paragraph,
+                stroke_paragraph,
+                background_paragraph,
+            } => {
+                paragraph.layout(width);
+                if let Some(stroke_paragraph) = stroke_paragraph {
+                    stroke_paragraph.layout(width);
+                }
+                if let Some(background_paragraph) = background_paragraph {
+                    background_paragraph.layout(width);
+                }
             }
+            TextLayout::Vertical(vertical_text) => vertical_text.layout(width),
+        }
+    }
 
-            if let Some(tag_end) = input[i + tag_start..].find(']') {
-                let tag = &input[i + tag_start + 1..i + tag_start + tag_end];
-                let end_tag = tag.starts_with('/');
-                let tag_name = if end_tag { &tag[1..] } else { tag };
-
-                i += tag_start + tag_end + 1;
-
-                if end_tag {
-                    match tag_name {
-                        "b" => tokens.push(BBCodeToken::CloseTag(BBCodeTag::Bold)),
-                        "i" => tokens.push(BBCodeToken::CloseTag(BBCodeTag::Italic)),
-                        "u" => tokens.push(BBCodeToken::CloseTag(BBCodeTag::Underline)),
-                        "del" => tokens.push(BBCodeToken::CloseTag(BBCodeTag::Strikethrough)),
-                        "color" => {
-                            tokens.push(BBCodeToken::CloseTag(BBCodeTag::Color(String::new())))
-                        }
-                        "stroke" => {
-                            tokens.push(BBCodeToken::CloseTag(BBCodeTag::Stroke(String::new())))
-                        }
-                        _ => {}
-                    }
-                } else {
-                    if tag_name.starts_with("color=") {
-                        let color_code = tag_name[6..].to_string();
-                        tokens.push(BBCodeToken::OpenTag(BBCodeTag::Color(color_code)));
-                    } else if tag_name.starts_with("stroke=") {
-                        let stroke_code = tag_name[7..].to_string();
-                        tokens.push(BBCodeToken::OpenTag(BBCodeTag::Stroke(stroke_code)));
-                    } else {
-                        match tag_name {
-                            "b" => tokens.push(BBCodeToken::OpenTag(BBCodeTag::Bold)),
-                            "i" => tokens.push(BBCodeToken::OpenTag(BBCodeTag::Italic)),
-                            "u" => tokens.push(BBCodeToken::OpenTag(BBCodeTag::Underline)),
-                            "del" => tokens.push(BBCodeToken::OpenTag(BBCodeTag::Strikethrough)),
-                            _ => {}
-                        }
-                    }
+    pub fn draw_on_canvas(&self, canvas: &Canvas, origin: impl Into<Point>) {
+        let origin: Point = origin.into();
+        match &self.layout {
+            TextLayout::Horizontal {
+                paragraph,
+                stroke_paragraph,
+                background_paragraph,
+            } => {
+                if let Some(background_paragraph) = background_paragraph {
+                    background_paragraph.paint(canvas, origin);
                 }
-            } else {
-                tokens.push(BBCodeToken::Text(input[i..].to_string()));
-                break;
+                if let Some(stroke_paragraph) = stroke_paragraph {
+                    stroke_paragraph.paint(canvas, origin);
+                }
+                paragraph.paint(canvas, origin);
             }
-        } else {
-            tokens.push(BBCodeToken::Text(input[i..].to_string()));
-            break;
+            TextLayout::Vertical(vertical_text) => vertical_text.draw_on_canvas(canvas, origin),
         }
     }
+}
 
-    tokens
+/// 按不透明度调整颜色的透明度
+fn with_opacity(color: Color, opacity: scalar) -> Color {
+    color.with_a((color.a() as scalar * opacity).round() as u8)
 }
@@ -55,9 +55,18 @@ pub fn color_from_hex_code(hex_code: &str) -> Color {
     Color::from_argb(a, r, g, b)
 }
 
+/// 解析颜色，无法识别时为黑色
 pub fn color_from_str(color: &str) -> Color {
-    if Regex::new(r"^#[0-9a-fA-F]{6}$").unwrap().is_match(color) {
-        color_from_hex_code(color)
+    try_color_from_str(color).unwrap_or(Color::BLACK)
+}
+
+/// 解析 `#rrggbb`、`#rrggbbaa` 格式或颜色名，无法识别时返回 `None`
+pub fn try_color_from_str(color: &str) -> Option<Color> {
+    if Regex::new(r"^#([0-9a-fA-F]{6}|[0-9a-fA-F]{8})$")
+        .unwrap()
+        .is_match(color)
+    {
+        Some(color_from_hex_code(color))
     } else {
         let color = color.trim().to_lowercase();
         let (r, g, b) = match color.as_str() {
@@ -209,9 +218,9 @@ pub fn color_from_str(color: &str) -> Color {
             "whitesmoke" => (245, 245, 245),
             "yellow" => (255, 255, 0),
             "yellowgreen" => (154, 205, 50),
-            _ => (0, 0, 0),
+            _ => return None,
         };
-        Color::from_rgb(r, g, b)
+        Some(Color::from_rgb(r, g, b))
     }
 }
 
@@ -0,0 +1,509 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use skia_safe::{
+    Canvas, Point, Typeface, TypefaceId, scalar,
+    textlayout::{
+        FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextAlign, TextStyle,
+    },
+};
+
+use crate::text::TextOrientation;
+
+/// 竖排时旋转 90° 显示的全角字符
+const ROTATED_CHARS: [char; 10] = ['ー', '～', '〜', '－', '＿', '＝', '“', '”', '‘', '’'];
+
+/// 不能出现在列首的标点，换列时与前一个字一起移到下一列
+const NO_BREAK_BEFORE: [char; 18] = [
+    '︐', '︑', '︒', '︓', '︔', '︕', '︖', '︙', '︶', '︸', '︺', '︼', '︾', '﹀', '﹂', '﹄',
+    '﹈', '︘',
+];
+
+/// 字体是否提供竖排字形，按字体缓存
+static VERTICAL_FEATURES: LazyLock<Mutex<HashMap<TypefaceId, bool>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 全角标点对应的竖排字形，字体不支持 `vert` 特性时使用
+fn vertical_form(c: char) -> char {
+    match c {
+        '，' => '︐',
+        '、' => '︑',
+        '。' => '︒',
+        '：' => '︓',
+        '；' => '︔',
+        '！' => '︕',
+        '？' => '︖',
+        '…' => '︙',
+        '—' => '︱',
+        '（' => '︵',
+        '）' => '︶',
+        '｛' => '︷',
+        '｝' => '︸',
+        '〔' => '︹',
+        '〕' => '︺',
+        '【' => '︻',
+        '】' => '︼',
+        '《' => '︽',
+        '》' => '︾',
+        '〈' => '︿',
+        '〉' => '﹀',
+        '「' => '﹁',
+        '」' => '﹂',
+        '『' => '﹃',
+        '』' => '﹄',
+        '［' => '﹇',
+        '］' => '﹈',
+        '〖' => '︗',
+        '〗' => '︘',
+        _ => c,
+    }
+}
+
+/// 竖排时直立显示的字符：中日韩文字、全角字符、emoji 等
+fn is_upright(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF
+            | 0x2E80..=0x2FDF
+            | 0x3000..=0x303F
+            | 0x3040..=0x30FF
+            | 0x3100..=0x318F
+            | 0x31F0..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7AF
+            | 0xF900..=0xFAFF
+            | 0xFE10..=0xFE1F
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFFEF
+            | 0x1F300..=0x1FAFF
+            | 0x20000..=0x3FFFF
+    )
+}
+
+/// 在字体的 GSUB 表中查找 OpenType 的 `vert` 或 `vrt2` 特性
+fn has_vertical_features(typeface: &Typeface) -> bool {
+    let tag = u32::from_be_bytes(*b"GSUB");
+    let Some(size) = typeface.get_table_size(tag) else {
+        return false;
+    };
+    let mut data = vec![0; size];
+    typeface.get_table_data(tag, &mut data);
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    // GSUB 表头的第 6~7 字节为 FeatureList 的偏移，FeatureList 为数量和 6 字节的 FeatureRecord
+    let Some(feature_list) = read_u16(6) else {
+        return false;
+    };
+    let count = read_u16(feature_list).unwrap_or(0);
+    (0..count).any(|index| {
+        let record = feature_list + 2 + index * 6;
+        matches!(data.get(record..record + 4), Some(b"vert" | b"vrt2"))
+    })
+}
+
+/// 段落中使用的字体是否都提供竖排字形
+fn uses_vertical_features(paragraph: &Paragraph) -> bool {
+    let fonts = paragraph.get_fonts();
+    let mut cache = VERTICAL_FEATURES.lock().unwrap();
+    !fonts.is_empty()
+        && fonts.iter().all(|info| {
+            let typeface = info.font.typeface();
+            *cache
+                .entry(typeface.unique_id())
+                .or_insert_with(|| has_vertical_features(&typeface))
+        })
+}
+
+/// 开启 `vert` 和 `vrt2` 特性的样式，由字体将字符替换为竖排字形
+fn vertical_style(style: &TextStyle) -> TextStyle {
+    let mut style = style.clone();
+    style.add_font_feature("vert", 1);
+    style.add_font_feature("vrt2", 1);
+    style
+}
+
+/// 一段带样式的文字，`stroke_style` 为描边的样式，`background_style` 为背景高亮和阴影的样式
+pub(crate) struct StyledRun {
+    pub text: String,
+    pub style: TextStyle,
+    pub stroke_style: Option<TextStyle>,
+    pub background_style: Option<TextStyle>,
+}
+
+struct VerticalGlyph {
+    paragraph: Paragraph,
+    stroke_paragraph: Option<Paragraph>,
+    background_paragraph: Option<Paragraph>,
+    /// 是否顺时针旋转 90°
+    rotated: bool,
+    /// 沿列方向占据的长度
+    advance: scalar,
+    no_break_before: bool,
+}
+
+enum VerticalItem {
+    Glyph(VerticalGlyph),
+    /// 换行符，之后的文字从新的一列开始
+    Break,
+}
+
+#[derive(Clone, Copy)]
+enum Pass {
+    Background,
+    Stroke,
+    Fill,
+}
+
+#[derive(Default)]
+struct Column {
+    items: Vec<usize>,
+    length: scalar,
+}
+
+/// 竖排文字，从上到下、从右到左排列
+///
+/// 中日韩文字直立显示，全角标点使用竖排字形；
+/// 西文单词按 `TextOrientation` 旋转或直立显示
+pub(crate) struct VerticalText {
+    items: Vec<VerticalItem>,
+    text_align: TextAlign,
+    column_width: scalar,
+    extent: scalar,
+    columns: Vec<Column>,
+}
+
+fn build_paragraph(text: &str, style: &TextStyle, font_collection: &FontCollection) -> Paragraph {
+    let mut builder = ParagraphBuilder::new(&ParagraphStyle::new(), font_collection);
+    builder.push_style(style);
+    builder.add_text(text);
+    let mut paragraph = builder.build();
+    paragraph.layout(scalar::INFINITY);
+    let width = paragraph.longest_line().ceil();
+    paragraph.layout(width);
+    paragraph
+}
+
+fn new_glyph(
+    text: &str,
+    rotated: bool,
+    run: &StyledRun,
+    font_collection: &FontCollection,
+) -> VerticalGlyph {
+    let paragraph = build_paragraph(text, &run.style, font_collection);
+    let stroke_paragraph = run
+        .stroke_style
+        .as_ref()
+        .map(|style| build_paragraph(text, style, font_collection));
+    let background_paragraph = run
+        .background_style
+        .as_ref()
+        .map(|style| build_paragraph(text, style, font_collection));
+    let advance = if rotated {
+        paragraph.longest_line()
+    } else {
+        run.style.font_size()
+    };
+    let no_break_before = text
+        .chars()
+        .next()
+        .is_some_and(|c| NO_BREAK_BEFORE.contains(&vertical_form(c)));
+    VerticalGlyph {
+        paragraph,
+        stroke_paragraph,
+        background_paragraph,
+        rotated,
+        advance,
+        no_break_before,
+    }
+}
+
+/// 单个直立显示的字符
+///
+/// 字体支持 `vert` 特性时由字体提供竖排字形，
+/// 否则标点使用竖排字形的码位，`ROTATED_CHARS` 中的字符旋转 90° 显示
+fn char_glyph(c: char, run: &StyledRun, font_collection: &FontCollection) -> VerticalGlyph {
+    let vertical_run = StyledRun {
+        text: String::new(),
+        style: vertical_style(&run.style),
+        stroke_style: run.stroke_style.as_ref().map(vertical_style),
+        background_style: run.background_style.as_ref().map(vertical_style),
+    };
+    let form = vertical_form(c);
+    let rotated = ROTATED_CHARS.contains(&c);
+    if form == c && !rotated {
+        return new_glyph(&c.to_string(), false, &vertical_run, font_collection);
+    }
+    let paragraph = build_paragraph(&c.to_string(), &vertical_run.style, font_collection);
+    if uses_vertical_features(&paragraph) {
+        new_glyph(&c.to_string(), false, &vertical_run, font_collection)
+    } else {
+        new_glyph(&form.to_string(), rotated, run, font_collection)
+    }
+}
+
+impl VerticalText {
+    pub(crate) fn new(
+        runs: &[StyledRun],
+        text_align: TextAlign,
+        orientation: TextOrientation,
+        font_collection: &FontCollection,
+    ) -> Self {
+        let mut items = Vec::new();
+        for run in runs {
+            // 连续的西文字符组成单词，空格附在单词末尾
+            let mut word = String::new();
+            let flush = |word: &mut String, items: &mut Vec<VerticalItem>| {
+                if !word.is_empty() {
+                    items.push(VerticalItem::Glyph(new_glyph(
+                        word,
+                        true,
+                        run,
+                        font_collection,
+                    )));
+                    word.clear();
+                }
+            };
+            for c in run.text.chars() {
+                if c == '\n' {
+                    flush(&mut word, &mut items);
+                    items.push(VerticalItem::Break);
+                    continue;
+                }
+                if c == '\r' {
+                    continue;
+                }
+                let rotated = ROTATED_CHARS.contains(&c);
+                if orientation == TextOrientation::Mixed
+                    && !rotated
+                    && !is_upright(vertical_form(c))
+                {
+                    word.push(c);
+                    if c.is_whitespace() {
+                        flush(&mut word, &mut items);
+                    }
+                    continue;
+                }
+                flush(&mut word, &mut items);
+                items.push(VerticalItem::Glyph(char_glyph(c, run, font_collection)));
+            }
+            flush(&mut word, &mut items);
+        }
+
+        let column_width = items
+            .iter()
+            .filter_map(|item| match item {
+                VerticalItem::Glyph(glyph) => Some(glyph.paragraph.height()),
+                VerticalItem::Break => None,
+            })
+            .fold(0.0, scalar::max);
+        let mut text = Self {
+            items,
+            text_align,
+            column_width,
+            extent: scalar::INFINITY,
+            columns: Vec::new(),
+        };
+        text.layout(scalar::INFINITY);
+        text
+    }
+
+    /// 按列高换列，单个西文单词超出列高时不会被拆开
+    pub(crate) fn layout(&mut self, extent: scalar) {
+        self.extent = extent;
+        let mut columns = vec![Column::default()];
+        for (index, item) in self.items.iter().enumerate() {
+            let glyph = match item {
+                VerticalItem::Glyph(glyph) => glyph,
+                VerticalItem::Break => {
+                    columns.push(Column::default());
+                    continue;
+                }
+            };
+            let column = columns.last_mut().unwrap();
+            if !column.items.is_empty() && column.length + glyph.advance > extent {
+                let mut next = Column::default();
+                // 避免标点出现在列首
+                if glyph.no_break_before && column.items.len() > 1 {
+                    let last = column.items.pop().unwrap();
+                    let advance = self.advance(last);
+                    column.length -= advance;
+                    next.items.push(last);
+                    next.length += advance;
+                }
+                columns.push(next);
+            }
+            let column = columns.last_mut().unwrap();
+            column.items.push(index);
+            column.length += glyph.advance;
+        }
+        self.columns = columns;
+    }
+
+    fn advance(&self, index: usize) -> scalar {
+        match &self.items[index] {
+            VerticalItem::Glyph(glyph) => glyph.advance,
+            VerticalItem::Break => 0.0,
+        }
+    }
+
+    /// 最长一列的长度
+    pub(crate) fn longest_column(&self) -> scalar {
+        self.columns
+            .iter()
+            .map(|column| column.length)
+            .fold(0.0, scalar::max)
+    }
+
+    pub(crate) fn width(&self) -> scalar {
+        self.columns.len() as scalar * self.column_width
+    }
+
+    /// 排版的列高，有超出列高的单词时为最长一列的长度
+    pub(crate) fn height(&self) -> scalar {
+        if self.extent.is_finite() {
+            self.extent.max(self.longest_column())
+        } else {
+            self.longest_column()
+        }
+    }
+
+    pub(crate) fn draw_on_canvas(&self, canvas: &Canvas, origin: Point) {
+        let height = self.height();
+        let right = origin.x + self.width();
+        // 依次绘制背景、描边和文字
+        for pass in [Pass::Background, Pass::Stroke, Pass::Fill] {
+            for (index, column) in self.columns.iter().enumerate() {
+                let left = right - (index + 1) as scalar * self.column_width;
+                let mut top = origin.y
+                    + match self.text_align {
+                        TextAlign::Center => (height - column.length) / 2.0,
+                        TextAlign::Right | TextAlign::End => height - column.length,
+                        _ => 0.0,
+                    };
+                for item in &column.items {
+                    let VerticalItem::Glyph(glyph) = &self.items[*item] else {
+                        continue;
+                    };
+                    let paragraph = match pass {
+                        Pass::Background => glyph.background_paragraph.as_ref(),
+                        Pass::Stroke => glyph.stroke_paragraph.as_ref(),
+                        Pass::Fill => Some(&glyph.paragraph),
+                    };
+                    if let Some(paragraph) = paragraph {
+                        self.draw_glyph(canvas, paragraph, glyph, left, top);
+                    }
+                    top += glyph.advance;
+                }
+            }
+        }
+    }
+
+    fn draw_glyph(
+        &self,
+        canvas: &Canvas,
+        paragraph: &Paragraph,
+        glyph: &VerticalGlyph,
+        left: scalar,
+        top: scalar,
+    ) {
+        let width = glyph.paragraph.longest_line();
+        let height = glyph.paragraph.height();
+        if glyph.rotated {
+            // 旋转后文字的顶部朝右，在列中水平居中
+            canvas.save();
+            canvas.translate((left + (self.column_width + height) / 2.0, top));
+            canvas.rotate(90.0, None);
+            paragraph.paint(canvas, (0.0, 0.0));
+            canvas.restore();
+        } else {
+            let x = left + (self.column_width - width) / 2.0;
+            let y = top + (glyph.advance - height) / 2.0;
+            paragraph.paint(canvas, (x, y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use skia_safe::FontMgr;
+
+    use super::*;
+
+    fn vertical_text(text: &str) -> VerticalText {
+        let mut font_collection = FontCollection::new();
+        font_collection.set_default_font_manager(FontMgr::new(), None);
+        let mut style = TextStyle::new();
+        style.set_font_size(10.0);
+        let runs = [StyledRun {
+            text: text.to_string(),
+            style,
+            stroke_style: None,
+            background_style: None,
+        }];
+        VerticalText::new(
+            &runs,
+            TextAlign::Left,
+            TextOrientation::Mixed,
+            &font_collection,
+        )
+    }
+
+    fn column_sizes(text: &VerticalText) -> Vec<usize> {
+        text.columns
+            .iter()
+            .map(|column| column.items.len())
+            .collect()
+    }
+
+    #[test]
+    fn layout_wraps_columns() {
+        let mut text = vertical_text("一二三四五六七");
+        assert_eq!(column_sizes(&text), [7]);
+        assert_eq!(text.longest_column(), 70.0);
+
+        text.layout(30.0);
+        assert_eq!(column_sizes(&text), [3, 3, 1]);
+        assert_eq!(text.longest_column(), 30.0);
+        assert_eq!(text.height(), 30.0);
+        assert_eq!(text.width(), 3.0 * text.column_width);
+
+        text.layout(35.0);
+        assert_eq!(column_sizes(&text), [3, 3, 1]);
+        assert_eq!(text.height(), 35.0);
+    }
+
+    #[test]
+    fn layout_starts_new_column_at_line_break() {
+        let mut text = vertical_text("一二\n三");
+        text.layout(30.0);
+        assert_eq!(column_sizes(&text), [2, 1]);
+    }
+
+    #[test]
+    fn layout_keeps_punctuation_off_column_start() {
+        let mut text = vertical_text("一二三，四");
+        text.layout(30.0);
+        assert_eq!(column_sizes(&text), [2, 3]);
+        assert_eq!(text.columns[0].length, 20.0);
+        assert_eq!(text.columns[1].length, 30.0);
+
+        // 列中只有一个字时不移动
+        let mut text = vertical_text("一。");
+        text.layout(10.0);
+        assert_eq!(column_sizes(&text), [1, 1]);
+    }
+
+    #[test]
+    fn vertical_forms_of_punctuation() {
+        assert_eq!(vertical_form('，'), '︐');
+        assert_eq!(vertical_form('」'), '﹂');
+        assert_eq!(vertical_form('字'), '字');
+        assert!(NO_BREAK_BEFORE.contains(&vertical_form('。')));
+        assert!(!NO_BREAK_BEFORE.contains(&vertical_form('「')));
+    }
+}